@@ -0,0 +1,124 @@
+//! Conversion of buildings which the game draws into the map's overlay layer instead of using sprites.
+//!
+//! All buildings share one tileset with a single palette bank, which the game allocates in
+//! the map's charblock alongside the map's tiles. `buildings.json` describes which tiles each building is made of.
+
+use crate::content::TILE_BUILDINGS;
+use crate::image::Image;
+use crate::map::{placeholder_color, TILES_PER_BLOCK};
+use crate::sprites::SpriteLibrary;
+use crate::tiles::{TileError, Tileset, TILE_SIZE};
+
+use serde::Serialize;
+
+/// Number of tiles reserved for buildings in the map's charblock.
+pub const MAX_TILES: usize = 64;
+/// GBFS file containing the 4bpp tile graphics.
+const TILES_NAME: &str = "buildingTiles";
+/// GBFS file containing the palette bank.
+const PALETTE_NAME: &str = "buildingPal";
+
+/// Top-level structure of `buildings.json`.
+/// Mirrors the structs in the game's `map/buildings.rs`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Buildings {
+    pub buildings: Vec<BuildingEntry>,
+}
+
+/// Describes how a single building is drawn.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BuildingEntry {
+    /// Mindustry's name for the building
+    pub name: String,
+    /// Width and height in tiles
+    pub size: usize,
+    /// Screen entries in left-to-right, top-to-bottom order, referring to the building tileset
+    pub entries: Vec<u16>,
+}
+
+/// Converted buildings and the files that have to be stored in GBFS alongside them.
+pub struct ConvertedBuildings {
+    pub buildings: Buildings,
+    pub files: Vec<(String, Vec<u8>)>,
+}
+
+/// Draws all buildings in `content::TILE_BUILDINGS` with the sprites from the library.
+pub fn convert(sprites: &mut SpriteLibrary) -> Result<ConvertedBuildings, TileError> {
+    let mut tileset = Tileset::new(1, MAX_TILES);
+    let mut entries = Vec::with_capacity(TILE_BUILDINGS.len());
+    for (name, blocks) in TILE_BUILDINGS {
+        let size = blocks * TILES_PER_BLOCK;
+        let size_in_px = size * TILE_SIZE;
+        let img = match sprites.block_sprite(name) {
+            Some(img) => img.scaled(size_in_px, size_in_px),
+            None => {
+                eprintln!(
+                    "[BUILDINGS] No sprite for {}, substituting a solid color",
+                    name
+                );
+                Image::filled(size_in_px, size_in_px, placeholder_color(name))
+            }
+        };
+        let mut building_entries = Vec::with_capacity(size * size);
+        for y in 0..size {
+            for x in 0..size {
+                building_entries.push(tileset.add_tile(&img, x * TILE_SIZE, y * TILE_SIZE)?);
+            }
+        }
+        entries.push(BuildingEntry {
+            name: name.to_string(),
+            size,
+            entries: building_entries,
+        });
+    }
+    println!(
+        "[BUILDINGS] {} buildings use {} tiles",
+        entries.len(),
+        tileset.num_tiles()
+    );
+
+    let buildings = Buildings { buildings: entries };
+    let mut palette = tileset.palette();
+    // Buildings always get a full bank, even if they use fewer colors
+    palette.resize(16, 0);
+    let files = vec![
+        (TILES_NAME.to_string(), tileset.tile_data()),
+        (
+            PALETTE_NAME.to_string(),
+            palette.iter().flat_map(|c| c.to_le_bytes()).collect(),
+        ),
+    ];
+    return Ok(ConvertedBuildings { buildings, files });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_buildings() {
+        let mut wall = Image::filled(32, 32, [100, 100, 100, 255]);
+        wall.set(0, 0, [200, 0, 0, 255]);
+        let mut sprites = SpriteLibrary::from_images(vec![("copper-wall", wall)]);
+        let converted = convert(&mut sprites).unwrap();
+        let buildings = &converted.buildings;
+
+        assert_eq!(buildings.buildings.len(), TILE_BUILDINGS.len());
+        let wall = &buildings.buildings[0];
+        assert_eq!(wall.name, "copper-wall");
+        assert_eq!(wall.size, 2);
+        assert_eq!(wall.entries.len(), 4);
+        // The top-left tile contains the red pixel, the rest are identical
+        assert_ne!(wall.entries[0], wall.entries[1]);
+        assert_eq!(wall.entries[1], wall.entries[3]);
+        assert_eq!(buildings.buildings[1].entries.len(), 16);
+        // All tiles use the first bank
+        for building in &buildings.buildings {
+            assert!(building.entries.iter().all(|e| *e != 0 && *e >> 12 == 0));
+        }
+
+        let (name, palette) = &converted.files[1];
+        assert_eq!(name, "buildingPal");
+        assert_eq!(palette.len(), 16 * 2);
+    }
+}
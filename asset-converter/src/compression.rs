@@ -0,0 +1,250 @@
+//! Compression of assets into the LZ77 and RLE formats understood by the GBA BIOS.
+//!
+//! A compressed file starts with `MAGIC`, followed by the data in the format the BIOS decompression
+//! functions expect: a 4 byte header holding the compression type in the lowest byte and the decompressed
+//! length in the upper 24 bits, followed by the compressed stream, padded to a multiple of 4 bytes.
+//! Files without the magic are stored as-is, which is what the game falls back to
+//! for files that aren't worth compressing.
+
+use std::convert::TryInto;
+
+/// Magic bytes at the start of every compressed file.
+pub const MAGIC: &[u8; 4] = b"GBAZ";
+/// Size of the magic and the BIOS header in bytes.
+const HEADER_LEN: usize = 8;
+/// Largest decompressed length the BIOS header can describe.
+const MAX_LEN: usize = 0xFF_FFFF;
+
+const LZ77_TYPE: u8 = 0x10;
+const LZ77_WINDOW: usize = 4096;
+const LZ77_MIN_MATCH: usize = 3;
+const LZ77_MAX_MATCH: usize = 18;
+/// The VRAM variant of the BIOS function writes 16 bits at a time,
+/// so the byte directly before the current one hasn't been written yet and can't be referenced.
+const LZ77_MIN_DISP: usize = 2;
+
+const RLE_TYPE: u8 = 0x30;
+const RLE_MIN_RUN: usize = 3;
+const RLE_MAX_RUN: usize = 130;
+const RLE_MAX_LITERALS: usize = 128;
+
+/// Compression formats supported by the BIOS.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Lz77,
+    Rle,
+}
+
+/// Compresses the data with whichever format yields the smallest result.
+///
+/// If compressing doesn't save any space, the data is returned unchanged.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    if data.len() > MAX_LEN {
+        return data.to_vec();
+    }
+    let smallest = [Compression::Lz77, Compression::Rle]
+        .iter()
+        .map(|compression| compress_with(data, *compression))
+        .min_by_key(Vec::len)
+        .unwrap();
+    // Data which happens to start with the magic has to be compressed, as it would be mistaken for compressed data otherwise
+    if smallest.len() < data.len() || data.starts_with(MAGIC) {
+        return smallest;
+    }
+    return data.to_vec();
+}
+
+/// Compresses the data with the given format.
+pub fn compress_with(data: &[u8], compression: Compression) -> Vec<u8> {
+    assert!(data.len() <= MAX_LEN, "Data too large to compress");
+    let mut out = MAGIC.to_vec();
+    let type_byte = match compression {
+        Compression::Lz77 => LZ77_TYPE,
+        Compression::Rle => RLE_TYPE,
+    };
+    out.extend_from_slice(&((data.len() as u32) << 8 | type_byte as u32).to_le_bytes());
+    match compression {
+        Compression::Lz77 => lz77(data, &mut out),
+        Compression::Rle => rle(data, &mut out),
+    }
+    out.resize(out.len().div_ceil(4) * 4, 0);
+    return out;
+}
+
+/// Returns whether the data is a compressed file.
+pub fn is_compressed(data: &[u8]) -> bool {
+    return data.len() >= HEADER_LEN && data.starts_with(MAGIC);
+}
+
+/// Returns the length of the file's contents after decompression.
+pub fn decompressed_len(data: &[u8]) -> usize {
+    if !is_compressed(data) {
+        return data.len();
+    }
+    return (u32::from_le_bytes(data[MAGIC.len()..HEADER_LEN].try_into().unwrap()) >> 8) as usize;
+}
+
+fn lz77(data: &[u8], out: &mut Vec<u8>) {
+    let mut pos = 0;
+    while pos < data.len() {
+        // Each flag byte describes whether the following 8 blocks are literals or back-references, MSB first
+        let flag_idx = out.len();
+        out.push(0);
+        for bit in 0..8 {
+            if pos >= data.len() {
+                break;
+            }
+            let (len, disp) = lz77_longest_match(data, pos);
+            if len >= LZ77_MIN_MATCH {
+                out[flag_idx] |= 0x80 >> bit;
+                out.push((((len - LZ77_MIN_MATCH) << 4) | ((disp - 1) >> 8)) as u8);
+                out.push(((disp - 1) & 0xFF) as u8);
+                pos += len;
+            } else {
+                out.push(data[pos]);
+                pos += 1;
+            }
+        }
+    }
+}
+
+/// Returns the length and displacement of the longest match for the data at `pos` within the window.
+fn lz77_longest_match(data: &[u8], pos: usize) -> (usize, usize) {
+    let max_len = LZ77_MAX_MATCH.min(data.len() - pos);
+    let mut best = (0, 0);
+    for disp in LZ77_MIN_DISP..=LZ77_WINDOW.min(pos) {
+        let start = pos - disp;
+        // Matches may overlap the data they produce
+        let len = (0..max_len)
+            .take_while(|i| data[start + i] == data[pos + i])
+            .count();
+        if len > best.0 {
+            best = (len, disp);
+            if len == max_len {
+                break;
+            }
+        }
+    }
+    return best;
+}
+
+fn rle(data: &[u8], out: &mut Vec<u8>) {
+    let mut literals_start = 0;
+    let mut pos = 0;
+    while pos < data.len() {
+        let run = data[pos..]
+            .iter()
+            .take(RLE_MAX_RUN)
+            .take_while(|b| **b == data[pos])
+            .count();
+        if run >= RLE_MIN_RUN {
+            rle_literals(&data[literals_start..pos], out);
+            out.push(0x80 | (run - RLE_MIN_RUN) as u8);
+            out.push(data[pos]);
+            pos += run;
+            literals_start = pos;
+        } else {
+            pos += 1;
+        }
+    }
+    rle_literals(&data[literals_start..], out);
+}
+
+fn rle_literals(literals: &[u8], out: &mut Vec<u8>) {
+    for chunk in literals.chunks(RLE_MAX_LITERALS) {
+        out.push((chunk.len() - 1) as u8);
+        out.extend_from_slice(chunk);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decompresses data the way the BIOS does.
+    fn decompress(data: &[u8]) -> Vec<u8> {
+        let len = decompressed_len(data);
+        let mut out = Vec::with_capacity(len);
+        let mut pos = HEADER_LEN;
+        match data[MAGIC.len()] {
+            LZ77_TYPE => {
+                while out.len() < len {
+                    let flags = data[pos];
+                    pos += 1;
+                    for bit in 0..8 {
+                        if out.len() >= len {
+                            break;
+                        }
+                        if flags & (0x80 >> bit) == 0 {
+                            out.push(data[pos]);
+                            pos += 1;
+                            continue;
+                        }
+                        let match_len = (data[pos] >> 4) as usize + LZ77_MIN_MATCH;
+                        let disp = (((data[pos] & 0xF) as usize) << 8 | data[pos + 1] as usize) + 1;
+                        assert!(disp >= LZ77_MIN_DISP);
+                        pos += 2;
+                        for _ in 0..match_len {
+                            out.push(out[out.len() - disp]);
+                        }
+                    }
+                }
+            }
+            RLE_TYPE => {
+                while out.len() < len {
+                    let flag = data[pos] as usize;
+                    pos += 1;
+                    if flag & 0x80 != 0 {
+                        out.resize(out.len() + (flag & 0x7F) + RLE_MIN_RUN, data[pos]);
+                        pos += 1;
+                    } else {
+                        out.extend_from_slice(&data[pos..pos + flag + 1]);
+                        pos += flag + 1;
+                    }
+                }
+            }
+            other => panic!("Unknown compression type {:#x}", other),
+        }
+        assert_eq!(out.len(), len);
+        return out;
+    }
+
+    fn test_data() -> Vec<u8> {
+        let mut data = vec![0; 300];
+        data.extend(
+            (0..=255)
+                .cycle()
+                .take(1000)
+                .map(|i: u32| (i * 7 % 13) as u8),
+        );
+        data.extend_from_slice(b"abcabcabcabcabcd");
+        return data;
+    }
+
+    #[test]
+    fn roundtrips() {
+        let data = test_data();
+        for compression in &[Compression::Lz77, Compression::Rle] {
+            let compressed = compress_with(&data, *compression);
+            assert!(is_compressed(&compressed));
+            assert_eq!(compressed.len() % 4, 0);
+            assert_eq!(decompressed_len(&compressed), data.len());
+            assert_eq!(decompress(&compressed), data);
+        }
+        assert!(decompress(&compress_with(&[], Compression::Lz77)).is_empty());
+        assert_eq!(decompress(&compress_with(&[1], Compression::Rle)), [1]);
+    }
+
+    #[test]
+    fn picks_smallest() {
+        let zeros = vec![0; 1024];
+        assert!(compress(&zeros).len() < 32);
+        assert_eq!(decompress(&compress(&zeros)), zeros);
+        // Nothing to gain here, so the data is stored uncompressed
+        assert_eq!(compress(&[1, 2, 3]), [1, 2, 3]);
+        assert_eq!(decompressed_len(&[1, 2, 3]), 3);
+        // Unless it would be mistaken for compressed data
+        assert!(is_compressed(&compress(b"GBAZGBAZ")));
+        assert_eq!(decompress(&compress(b"GBAZGBAZ")), b"GBAZGBAZ");
+    }
+}
@@ -0,0 +1,196 @@
+//! Knowledge about Mindustry's content that isn't stored in save files.
+
+/// Blocks which are part of the terrain rather than buildings, and therefore never have a tile entity.
+/// Names from both Mindustry 5 and 6 are included.
+const STATIC_BLOCKS: &[&str] = &[
+    "air",
+    "spawn",
+    "cliff",
+    "cliffs",
+    "rocks",
+    "sporerocks",
+    "icerocks",
+    "dunerocks",
+    "sandrocks",
+    "shalerocks",
+    "snowrocks",
+    "saltrocks",
+    "dark-metal",
+    "stone-wall",
+    "spore-wall",
+    "dirt-wall",
+    "dacite-wall",
+    "ice-wall",
+    "snow-wall",
+    "dune-wall",
+    "sand-wall",
+    "salt-wall",
+    "shale-wall",
+    "pine",
+    "spore-pine",
+    "snow-pine",
+    "shrubs",
+    "white-tree",
+    "white-tree-dead",
+    "spore-cluster",
+    "boulder",
+    "sand-boulder",
+    "shale-boulder",
+    "snow-boulder",
+    "dacite-boulder",
+    "basalt-boulder",
+];
+
+/// Static blocks which units can move over, unlike walls, rocks and trees.
+const PASSABLE_STATIC_BLOCKS: &[&str] = &[
+    "air",
+    "spawn",
+    "spore-cluster",
+    "boulder",
+    "sand-boulder",
+    "shale-boulder",
+    "snow-boulder",
+    "dacite-boulder",
+    "basalt-boulder",
+];
+
+/// Ores and the value they're represented by in the ore layer.
+/// The value is the index of the mined item in the game's `Item` enum, plus one.
+const ORES: &[(&str, u8)] = &[
+    ("ore-scrap", 1),
+    ("ore-copper", 2),
+    ("ore-lead", 3),
+    ("ore-coal", 5),
+    ("ore-titanium", 6),
+    ("ore-thorium", 7),
+];
+
+/// The ore layer value of tiles without any ore.
+pub const NO_ORE: u8 = 0;
+
+/// Buildings the player can place, which are drawn as background tiles rather than sprites,
+/// and their edge length in Mindustry tiles.
+pub const TILE_BUILDINGS: &[(&str, usize)] = &[
+    ("copper-wall", 1),
+    ("mechanical-drill", 2),
+    ("container", 2),
+];
+
+/// A block which is animated by cycling through several frames.
+pub struct Animation {
+    /// Mindustry's name for the block
+    pub block: &'static str,
+    /// The frames in order, each made of a sprite and how many pixels to the right it's moved
+    /// (with wraparound) once scaled to the size of a map tile.
+    /// Moving the sprite lets liquids flow without needing a sprite per frame.
+    pub frames: &'static [(&'static str, usize)],
+    /// How many vblanks each frame is shown for
+    pub frame_duration: u8,
+}
+
+/// Blocks which are animated on the map.
+/// Mindustry animates these with shaders, so we approximate that by moving their sprites.
+pub const ANIMATIONS: &[Animation] = &[
+    Animation {
+        block: "water",
+        frames: &[("water", 0), ("water", 4), ("water", 8), ("water", 12)],
+        frame_duration: 16,
+    },
+    Animation {
+        block: "deep-water",
+        frames: &[
+            ("deep-water", 0),
+            ("deep-water", 4),
+            ("deep-water", 8),
+            ("deep-water", 12),
+        ],
+        frame_duration: 20,
+    },
+    Animation {
+        block: "tar",
+        frames: &[("tar", 0), ("tar", 4), ("tar", 8), ("tar", 12)],
+        frame_duration: 32,
+    },
+    Animation {
+        block: "slag",
+        frames: &[("slag", 0), ("slag", 4), ("slag", 8), ("slag", 12)],
+        frame_duration: 24,
+    },
+    // Only ends up on maps once derelict buildings are drawn into them
+    Animation {
+        block: "conveyor",
+        frames: &[
+            ("conveyor-0-0", 0),
+            ("conveyor-0-1", 0),
+            ("conveyor-0-2", 0),
+            ("conveyor-0-3", 0),
+        ],
+        frame_duration: 4,
+    },
+];
+
+/// Returns the animation of the given block, if it's animated.
+pub fn animation(name: &str) -> Option<&'static Animation> {
+    return ANIMATIONS.iter().find(|animation| animation.block == name);
+}
+
+/// Whether the block is terrain that's drawn as part of the map rather than placed as a building.
+pub fn is_static_block(name: &str) -> bool {
+    return STATIC_BLOCKS.contains(&name);
+}
+
+/// Whether the block is terrain which units can't move through.
+pub fn is_solid(name: &str) -> bool {
+    return is_static_block(name) && !PASSABLE_STATIC_BLOCKS.contains(&name);
+}
+
+/// Returns the ore layer value of the given overlay, if it's an ore.
+pub fn ore_value(name: &str) -> Option<u8> {
+    return ORES
+        .iter()
+        .find(|(ore, _)| *ore == name)
+        .map(|(_, value)| *value);
+}
+
+/// Whether the overlay marks an enemy spawn point.
+pub fn is_spawn(name: &str) -> bool {
+    return name == "spawn";
+}
+
+/// Returns the file names (without extension) a sprite for the given block may be stored under.
+///
+/// Environment sprites usually have several variants, of which we always use the first one.
+/// Mindustry 5 names ore sprites after the item (`copper1`) instead of the block (`ore-copper1`).
+pub fn sprite_candidates(name: &str) -> Vec<String> {
+    let mut candidates = vec![name.to_string(), format!("{}1", name)];
+    if let Some(item) = name.strip_prefix("ore-") {
+        candidates.push(format!("{}1", item));
+    }
+    return candidates;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_blocks() {
+        assert!(is_static_block("air"));
+        assert!(is_static_block("dunerocks"));
+        assert!(!is_static_block("copper-wall"));
+        assert!(is_solid("dunerocks"));
+        assert!(!is_solid("air"));
+        assert!(!is_solid("boulder"));
+        // Buildings are solid, but not part of the terrain
+        assert!(!is_solid("copper-wall"));
+        assert_eq!(ore_value("ore-copper"), Some(2));
+        assert_eq!(ore_value("spawn"), None);
+        assert!(is_spawn("spawn"));
+        assert_eq!(animation("water").unwrap().frames.len(), 4);
+        assert!(animation("stone").is_none());
+        assert_eq!(
+            sprite_candidates("ore-lead"),
+            ["ore-lead", "ore-lead1", "lead1"]
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Conversion of a monospace 8x8 TTF font into the text engine's tiles.
+//!
+//! Every character the font contains is rendered into a single 4bpp tile, ordered by code point.
+//! The characters are stored in that same order in a UTF-8 text file,
+//! which the text engine uses to map characters to tiles.
+
+use crate::image::Image;
+use crate::sprite_tiles::SharedPalette;
+use crate::tiles::TILE_SIZE;
+
+use ab_glyph::{point, Font, FontRef, InvalidFont, PxScale, ScaleFont};
+
+/// Glyphs are drawn in this color.
+const GLYPH_COLOR: [u8; 4] = [255, 255, 255, 255];
+/// Pixels covered at least this much by a glyph's outline are drawn.
+const COVERAGE_THRESHOLD: f32 = 0.5;
+
+/// A font converted for the text engine.
+pub struct ConvertedFont {
+    /// Characters in the order of their tiles
+    pub chars: String,
+    pub tiles: Vec<u8>,
+    pub palette: Vec<u8>,
+}
+
+/// Renders all characters of the TTF font.
+pub fn convert(ttf: &[u8]) -> Result<ConvertedFont, InvalidFont> {
+    let font = FontRef::try_from_slice(ttf)?;
+    // Scale the font so that an em is exactly one tile, like other tools do for bitmap fonts
+    let units_per_em = font.units_per_em().unwrap_or(1.0);
+    let scale = PxScale::from(TILE_SIZE as f32 * font.height_unscaled() / units_per_em);
+    let scaled = font.as_scaled(scale);
+
+    let mut chars: Vec<char> = font.codepoint_ids().map(|(_, c)| c).collect();
+    chars.sort_unstable();
+    chars.dedup();
+
+    let mut img = Image::new(chars.len() * TILE_SIZE, TILE_SIZE);
+    for (i, c) in chars.iter().enumerate() {
+        let glyph = scaled
+            .glyph_id(*c)
+            .with_scale_and_position(scale, point(0.0, scaled.ascent()));
+        if let Some(outline) = font.outline_glyph(glyph) {
+            let bounds = outline.px_bounds();
+            outline.draw(|x, y, coverage| {
+                let x = bounds.min.x as i32 + x as i32;
+                let y = bounds.min.y as i32 + y as i32;
+                let inside =
+                    (0..TILE_SIZE as i32).contains(&x) && (0..TILE_SIZE as i32).contains(&y);
+                if inside && coverage >= COVERAGE_THRESHOLD {
+                    img.set(i * TILE_SIZE + x as usize, y as usize, GLYPH_COLOR);
+                }
+            });
+        }
+    }
+
+    let palette = SharedPalette::for_images(&[img.clone()], 4);
+    return Ok(ConvertedFont {
+        chars: chars.into_iter().collect(),
+        tiles: palette.tiles(&img),
+        palette: palette.to_bytes(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_font() {
+        let font = convert(include_bytes!("../../Px437_IBM_BIOS.ttf")).unwrap();
+        let num_chars = font.chars.chars().count();
+        assert!(font.chars.contains('A'));
+        assert_eq!(font.tiles.len(), num_chars * 32);
+        // Transparent and white
+        assert_eq!(font.palette, [0, 0, 0xFF, 0x7F]);
+
+        let tile = |c: char| {
+            let idx = font.chars.chars().position(|x| x == c).unwrap();
+            return &font.tiles[idx * 32..(idx + 1) * 32];
+        };
+        assert!(tile(' ').iter().all(|b| *b == 0));
+        assert!(tile('A').iter().any(|b| *b != 0));
+        assert_ne!(tile('A'), tile('B'));
+    }
+}
@@ -0,0 +1,217 @@
+//! Reading of GBFS archives, the filesystem the game loads its assets from.
+//!
+//! The format is described at <https://www.pineight.com/gba/gbfs.txt>.
+//! An archive consists of a 32 byte header, followed by a directory of 32 byte entries
+//! sorted by name, followed by the file data.
+
+use std::convert::TryInto;
+use std::fmt;
+
+/// Magic bytes at the start of every archive.
+pub const MAGIC: &[u8; 16] = b"PinEightGBFS\r\n\x1a\n";
+/// Size of the archive header in bytes.
+pub const HEADER_LEN: usize = 32;
+/// Size of a directory entry in bytes.
+pub const ENTRY_LEN: usize = 32;
+/// Maximum length of a file name in bytes.
+pub const MAX_NAME_LEN: usize = 24;
+/// Alignment of file data within the archive, so that the game can access files as `u32`s.
+const DATA_ALIGN: usize = 16;
+
+/// Things that may go wrong while reading an archive.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GbfsError {
+    /// The data doesn't start with the GBFS magic.
+    BadMagic,
+    /// The directory or a file lies outside of the archive.
+    Truncated,
+    /// The file name doesn't fit into a directory entry.
+    NameTooLong(String),
+}
+
+impl fmt::Display for GbfsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GbfsError::BadMagic => write!(f, "GbfsError: File is not a GBFS archive"),
+            GbfsError::Truncated => write!(f, "GbfsError: Archive is truncated"),
+            GbfsError::NameTooLong(name) => write!(
+                f,
+                "GbfsError: File name {} is longer than {} bytes",
+                name, MAX_NAME_LEN
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GbfsError {}
+
+/// A file stored in an archive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GbfsFile<'a> {
+    pub name: String,
+    pub data: &'a [u8],
+}
+
+/// Returns all files in the archive, in directory order.
+pub fn read(archive: &[u8]) -> Result<Vec<GbfsFile<'_>>, GbfsError> {
+    if archive.len() < HEADER_LEN {
+        return Err(GbfsError::Truncated);
+    }
+    if &archive[..MAGIC.len()] != MAGIC {
+        return Err(GbfsError::BadMagic);
+    }
+    let dir_offset = u16_at(archive, 20) as usize;
+    let num_entries = u16_at(archive, 22) as usize;
+
+    let mut files = Vec::with_capacity(num_entries);
+    for i in 0..num_entries {
+        let entry = archive
+            .get(dir_offset + i * ENTRY_LEN..dir_offset + (i + 1) * ENTRY_LEN)
+            .ok_or(GbfsError::Truncated)?;
+        let name_len = entry[..MAX_NAME_LEN]
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(MAX_NAME_LEN);
+        let len = u32_at(entry, 24) as usize;
+        let offset = u32_at(entry, 28) as usize;
+        files.push(GbfsFile {
+            name: String::from_utf8_lossy(&entry[..name_len]).into_owned(),
+            data: archive
+                .get(offset..offset + len)
+                .ok_or(GbfsError::Truncated)?,
+        });
+    }
+    return Ok(files);
+}
+
+/// Builds an archive containing the given files.
+///
+/// The directory is sorted by name, as the format requires.
+pub fn write(files: &[GbfsFile]) -> Result<Vec<u8>, GbfsError> {
+    let mut files = files.to_vec();
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut dir = Vec::with_capacity(files.len() * ENTRY_LEN);
+    let mut data = Vec::new();
+    let data_start = align(HEADER_LEN + files.len() * ENTRY_LEN);
+    for file in &files {
+        if file.name.len() > MAX_NAME_LEN {
+            return Err(GbfsError::NameTooLong(file.name.clone()));
+        }
+        let mut entry = [0; ENTRY_LEN];
+        entry[..file.name.len()].copy_from_slice(file.name.as_bytes());
+        entry[24..28].copy_from_slice(&(file.data.len() as u32).to_le_bytes());
+        entry[28..32].copy_from_slice(&((data_start + data.len()) as u32).to_le_bytes());
+        dir.extend_from_slice(&entry);
+        data.extend_from_slice(file.data);
+        data.resize(align(data.len()), 0);
+    }
+
+    let mut archive = Vec::with_capacity(data_start + data.len());
+    archive.extend_from_slice(MAGIC);
+    archive.extend_from_slice(&((data_start + data.len()) as u32).to_le_bytes());
+    archive.extend_from_slice(&(HEADER_LEN as u16).to_le_bytes());
+    archive.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    archive.resize(HEADER_LEN, 0);
+    archive.extend_from_slice(&dir);
+    archive.resize(data_start, 0);
+    archive.extend_from_slice(&data);
+    return Ok(archive);
+}
+
+fn align(offset: usize) -> usize {
+    return offset.div_ceil(DATA_ALIGN) * DATA_ALIGN;
+}
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    return u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap());
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    return u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Builds an archive the way the `gbfs` tool does, for testing.
+    pub(crate) fn build_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut files = files.to_vec();
+        files.sort_by_key(|(name, _)| *name);
+        let data_start = HEADER_LEN + files.len() * ENTRY_LEN;
+        let total_len = data_start + files.iter().map(|(_, data)| data.len()).sum::<usize>();
+
+        let mut archive = Vec::with_capacity(total_len);
+        archive.extend_from_slice(MAGIC);
+        archive.extend_from_slice(&(total_len as u32).to_le_bytes());
+        archive.extend_from_slice(&(HEADER_LEN as u16).to_le_bytes());
+        archive.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        archive.resize(HEADER_LEN, 0);
+        let mut offset = data_start;
+        for (name, data) in &files {
+            let mut entry = [0; ENTRY_LEN];
+            entry[..name.len()].copy_from_slice(name.as_bytes());
+            entry[24..28].copy_from_slice(&(data.len() as u32).to_le_bytes());
+            entry[28..32].copy_from_slice(&(offset as u32).to_le_bytes());
+            archive.extend_from_slice(&entry);
+            offset += data.len();
+        }
+        for (_, data) in &files {
+            archive.extend_from_slice(data);
+        }
+        return archive;
+    }
+
+    #[test]
+    fn reads_archive() {
+        let archive = build_archive(&[("b.txt", b"second"), ("a.bin", &[1, 2, 3])]);
+        let files = read(&archive).unwrap();
+        assert_eq!(
+            files,
+            [
+                GbfsFile {
+                    name: "a.bin".to_string(),
+                    data: &[1, 2, 3],
+                },
+                GbfsFile {
+                    name: "b.txt".to_string(),
+                    data: b"second",
+                },
+            ]
+        );
+        assert_eq!(read(&archive[..40]), Err(GbfsError::Truncated));
+        assert_eq!(read(&[0; 64]), Err(GbfsError::BadMagic));
+    }
+
+    #[test]
+    fn writes_archive() {
+        let files = [
+            GbfsFile {
+                name: "b.txt".to_string(),
+                data: b"second",
+            },
+            GbfsFile {
+                name: "a.bin".to_string(),
+                data: &[1, 2, 3],
+            },
+        ];
+        let archive = write(&files).unwrap();
+        let read_back = read(&archive).unwrap();
+        assert_eq!(read_back, [files[1].clone(), files[0].clone()]);
+        for file in &read_back {
+            assert_eq!(
+                (file.data.as_ptr() as usize - archive.as_ptr() as usize) % DATA_ALIGN,
+                0
+            );
+        }
+        let long_name = GbfsFile {
+            name: "a".repeat(MAX_NAME_LEN + 1),
+            data: &[],
+        };
+        assert_eq!(
+            write(&[long_name]),
+            Err(GbfsError::NameTooLong("a".repeat(MAX_NAME_LEN + 1)))
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! A minimal RGBA image type, just capable enough for compositing and slicing sprites.
+
+use std::fs::File;
+use std::path::Path;
+
+/// An RGBA pixel.
+pub type Rgba = [u8; 4];
+
+/// A fully transparent pixel.
+pub const TRANSPARENT: Rgba = [0, 0, 0, 0];
+
+/// Pixels with an alpha value below this are considered transparent.
+/// The GBA has no notion of partial transparency.
+const ALPHA_THRESHOLD: u8 = 128;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<Rgba>,
+}
+
+impl Image {
+    /// Creates a fully transparent image.
+    pub fn new(width: usize, height: usize) -> Image {
+        return Image {
+            width,
+            height,
+            pixels: vec![TRANSPARENT; width * height],
+        };
+    }
+
+    /// Creates an image entirely filled with a single color.
+    pub fn filled(width: usize, height: usize, color: Rgba) -> Image {
+        return Image {
+            width,
+            height,
+            pixels: vec![color; width * height],
+        };
+    }
+
+    /// Loads a PNG file, converting it to RGBA.
+    pub fn load_png(path: &Path) -> Result<Image, png::DecodingError> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        let (width, height) = (info.width as usize, info.height as usize);
+
+        let channels = info.color_type.samples();
+        let pixels = buf[..width * height * channels]
+            .chunks_exact(channels)
+            .map(|px| match info.color_type {
+                png::ColorType::Grayscale => [px[0], px[0], px[0], 255],
+                png::ColorType::GrayscaleAlpha => [px[0], px[0], px[0], px[1]],
+                png::ColorType::Rgb => [px[0], px[1], px[2], 255],
+                _ => [px[0], px[1], px[2], px[3]],
+            })
+            .collect();
+        return Ok(Image {
+            width,
+            height,
+            pixels,
+        });
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Rgba {
+        return self.pixels[x + y * self.width];
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Rgba) {
+        self.pixels[x + y * self.width] = color;
+    }
+
+    /// Draws `other` on top of this image with its top-left corner at (x, y).
+    /// Transparent pixels of `other` are skipped, anything outside of this image is clipped.
+    pub fn draw(&mut self, other: &Image, x: usize, y: usize) {
+        for oy in 0..other.height.min(self.height.saturating_sub(y)) {
+            for ox in 0..other.width.min(self.width.saturating_sub(x)) {
+                let px = other.get(ox, oy);
+                if is_opaque(px) {
+                    self.set(x + ox, y + oy, px);
+                }
+            }
+        }
+    }
+
+    /// Returns a copy of the given area of the image.
+    /// Areas outside of the image are transparent.
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Image {
+        let mut cropped = Image::new(width, height);
+        for cy in 0..height {
+            for cx in 0..width {
+                if x + cx < self.width && y + cy < self.height {
+                    cropped.set(cx, cy, self.get(x + cx, y + cy));
+                }
+            }
+        }
+        return cropped;
+    }
+
+    /// Returns a copy of the image moved `dx` pixels to the right,
+    /// with the pixels moved past the right edge wrapping around to the left.
+    pub fn shifted(&self, dx: usize) -> Image {
+        let mut shifted = Image::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                shifted.set((x + dx) % self.width, y, self.get(x, y));
+            }
+        }
+        return shifted;
+    }
+
+    /// Returns a copy of the image resized to the given dimensions using nearest-neighbor sampling.
+    pub fn scaled(&self, width: usize, height: usize) -> Image {
+        let mut scaled = Image::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                scaled.set(
+                    x,
+                    y,
+                    self.get(x * self.width / width, y * self.height / height),
+                );
+            }
+        }
+        return scaled;
+    }
+}
+
+/// Whether the pixel should be drawn on the GBA.
+pub fn is_opaque(px: Rgba) -> bool {
+    return px[3] >= ALPHA_THRESHOLD;
+}
+
+/// Converts a pixel to the GBA's 15 bit BGR color format.
+/// Returns `None` for transparent pixels.
+pub fn to_gba_color(px: Rgba) -> Option<u16> {
+    if !is_opaque(px) {
+        return None;
+    }
+    let [r, g, b, _] = px;
+    return Some(((b as u16 >> 3) << 10) | ((g as u16 >> 3) << 5) | (r as u16 >> 3));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_colors() {
+        assert_eq!(to_gba_color([255, 0, 0, 255]), Some(0x001F));
+        assert_eq!(to_gba_color([0, 255, 0, 255]), Some(0x03E0));
+        assert_eq!(to_gba_color([0, 0, 255, 255]), Some(0x7C00));
+        assert_eq!(to_gba_color([255, 255, 255, 10]), None);
+    }
+
+    #[test]
+    fn draws_and_scales() {
+        let mut canvas = Image::filled(4, 4, [1, 2, 3, 255]);
+        let mut stamp = Image::new(2, 2);
+        stamp.set(1, 1, [9, 9, 9, 255]);
+        canvas.draw(&stamp, 3, 3);
+        assert_eq!(canvas.get(3, 3), [1, 2, 3, 255]);
+        canvas.draw(&stamp, 2, 2);
+        assert_eq!(canvas.get(3, 3), [9, 9, 9, 255]);
+        assert_eq!(canvas.get(2, 2), [1, 2, 3, 255]);
+
+        let halved = canvas.scaled(2, 2);
+        assert_eq!(halved.get(1, 1), [1, 2, 3, 255]);
+        assert_eq!(canvas.crop(3, 3, 2, 2).get(1, 1), TRANSPARENT);
+
+        let shifted = canvas.shifted(3);
+        assert_eq!(shifted.get(2, 3), [9, 9, 9, 255]);
+        assert_eq!(shifted.get(3, 3), [1, 2, 3, 255]);
+    }
+}
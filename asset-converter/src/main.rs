@@ -0,0 +1,117 @@
+//! Host-side tool converting Mindustry assets into the formats used by the game.
+//!
+//! Usage:
+//!
+//! * `asset-converter build`
+//!
+//!   Runs the entire asset pipeline (see `pipeline`) from the repository root,
+//!   writing `assets.gbfs` and the game's asset manifest. This is what `cargo make assets` does.
+//!
+//! * `asset-converter maps <sprite dir> <output dir> <map.msav>...`
+//!
+//!   Converts the given maps, drawing them with the sprites found below the sprite directory.
+//!   All files which have to be put into the GBFS archive are written to the output directory,
+//!   together with the `maps.json` describing the maps and the tiles of buildings (see `buildings`).
+//!   Map data is compressed where that saves space.
+//!
+//! * `asset-converter compress <assets.gbfs> <suffix>...`
+//!
+//!   Compresses the files in the archive whose names end with one of the suffixes (see `compression`).
+//!   Files which are already compressed or don't get any smaller are left alone.
+//!
+//! * `asset-converter manifest <assets.gbfs> <manifest.rs>`
+//!
+//!   Generates the game's asset manifest for the finished archive.
+
+// Disable a bunch of clippy lints I disagree with
+#![allow(clippy::needless_return)]
+
+mod buildings;
+mod compression;
+mod content;
+mod font;
+mod gbfs;
+mod image;
+mod manifest;
+mod map;
+mod msav;
+mod pipeline;
+mod sound;
+mod sprite_meta;
+mod sprite_tiles;
+mod sprites;
+mod tiles;
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &str = "Usage: asset-converter build
+       asset-converter maps <sprite dir> <output dir> <map.msav>...
+       asset-converter compress <assets.gbfs> <suffix>...
+       asset-converter manifest <assets.gbfs> <manifest.rs>";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("build") if args.len() == 1 => pipeline::run(),
+        Some("maps") if args.len() >= 4 => convert_maps(
+            Path::new(&args[1]),
+            Path::new(&args[2]),
+            &args[3..].iter().map(PathBuf::from).collect::<Vec<_>>(),
+        ),
+        Some("compress") if args.len() >= 3 => compress_archive(Path::new(&args[1]), &args[2..]),
+        Some("manifest") if args.len() == 3 => {
+            generate_manifest(Path::new(&args[1]), Path::new(&args[2]))
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+/// Converts all given maps and buildings, writing their files and JSON descriptions to the output directory.
+fn convert_maps(
+    sprite_dir: &Path,
+    out_dir: &Path,
+    paths: &[PathBuf],
+) -> Result<(), Box<dyn Error>> {
+    let mut contents = pipeline::ArchiveContents::default();
+    pipeline::convert_maps(sprite_dir, paths, &mut contents)?;
+    fs::create_dir_all(out_dir)?;
+    for (name, data) in contents.files() {
+        fs::write(out_dir.join(name), data)?;
+    }
+    return Ok(());
+}
+
+/// Compresses the files in the archive whose names end with one of the suffixes.
+fn compress_archive(archive_path: &Path, suffixes: &[String]) -> Result<(), Box<dyn Error>> {
+    let archive = fs::read(archive_path)?;
+    let mut contents = pipeline::ArchiveContents::default();
+    for file in gbfs::read(&archive)? {
+        contents.add(file.name, file.data.to_vec())?;
+    }
+    contents.compress(&suffixes.iter().map(String::as_str).collect::<Vec<_>>());
+    fs::write(archive_path, contents.to_archive()?)?;
+    return Ok(());
+}
+
+/// Generates the asset manifest for the archive.
+fn generate_manifest(archive_path: &Path, out_path: &Path) -> Result<(), Box<dyn Error>> {
+    let archive = fs::read(archive_path)?;
+    let files = gbfs::read(&archive)?;
+    fs::write(out_path, manifest::generate(&files)?)?;
+    println!(
+        "[MANIFEST] Wrote {} entries to {}",
+        files.len(),
+        out_path.display()
+    );
+    return Ok(());
+}
@@ -0,0 +1,341 @@
+//! Generation of the game's asset manifest, a Rust module with a constant for every file in the asset archive.
+//!
+//! Referring to assets through the manifest turns a missing or renamed asset into a compile error
+//! instead of a panic at runtime, and keeps metadata such as sprite sizes next to the file name.
+//! The `SpriteAsset` and `Asset` types the constants are made of live in the game's `assets` module.
+
+use crate::compression;
+use crate::gbfs::GbfsFile;
+use crate::sprite_meta::{self, SpriteMeta, SpriteMetaError};
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fmt::Write;
+
+/// Suffix grit gives to files containing a palette.
+const PALETTE_SUFFIX: &str = "Pal";
+
+/// Things that may go wrong while generating the manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ManifestError {
+    /// The sprite's dimensions don't match any hardware sprite size.
+    UnsupportedSpriteSize(String, u16, u16),
+    /// The sprite's metadata can't be parsed.
+    InvalidMeta(String, SpriteMetaError),
+    /// The sprite's tile data doesn't have the length its metadata requires.
+    SizeMismatch(String, usize, usize),
+    /// The sprite is 4bpp, but has no palette bank.
+    MissingPalette(String),
+    /// Two files map to the same constant name.
+    DuplicateName(String),
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ManifestError::*;
+        match self {
+            UnsupportedSpriteSize(name, width, height) => write!(
+                f,
+                "ManifestError: Sprite {} has unsupported size {}x{}",
+                name, width, height
+            ),
+            InvalidMeta(name, err) => write!(
+                f,
+                "ManifestError: Invalid metadata for sprite {}: {}",
+                name, err
+            ),
+            SizeMismatch(name, expected, actual) => write!(
+                f,
+                "ManifestError: Sprite {} should be {} bytes, but is {} bytes",
+                name, expected, actual
+            ),
+            MissingPalette(name) => write!(f, "ManifestError: 4bpp sprite {} has no palette", name),
+            DuplicateName(name) => {
+                write!(f, "ManifestError: Multiple files map to constant {}", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+/// Returns the name of the game's `HWSpriteSize` variant for the given dimensions, if any.
+pub fn hw_sprite_size(width: u16, height: u16) -> Option<&'static str> {
+    let name = match (width, height) {
+        (8, 8) => "EightByEight",
+        (16, 16) => "SixteenBySixteen",
+        (32, 32) => "ThirtyTwoByThirtyTwo",
+        (64, 64) => "SixtyFourBySixtyFour",
+        (8, 16) => "EightBySixteen",
+        (8, 32) => "EightByThirtyTwo",
+        (16, 8) => "SixteenByEight",
+        (16, 32) => "SixteenByThirtyTwo",
+        (32, 8) => "ThirtyTwoByEight",
+        (32, 16) => "ThirtyTwoBySixteen",
+        (32, 64) => "ThirtyTwoBySixtyFour",
+        (64, 32) => "SixtyFourByThirtyTwo",
+        _ => return None,
+    };
+    return Some(name);
+}
+
+/// Generates the manifest for the given archive contents.
+///
+/// Tile files with a metadata sidecar (see `sprite_meta`) end up in the `sprites` module,
+/// together with their palette if they're 4bpp. Other palettes end up in `palettes` and everything else in `files`.
+pub fn generate(files: &[GbfsFile]) -> Result<String, ManifestError> {
+    let indices: HashMap<&str, usize> = files
+        .iter()
+        .enumerate()
+        .map(|(i, f)| (f.name.as_str(), i))
+        .collect();
+    let asset = |gbfs_index: usize| {
+        format!(
+            "Asset {{\n        name: {:?},\n        gbfs_index: {},\n    }}",
+            files[gbfs_index].name, gbfs_index
+        )
+    };
+
+    let mut sprite_consts = String::new();
+    let mut palette_consts = String::new();
+    let mut file_consts = String::new();
+    // Metadata and palettes of sprites
+    let mut sprite_sidecars = HashSet::new();
+    let mut names = HashSet::new();
+    for file in files {
+        let meta_index = sprite_meta::meta_name(&file.name).and_then(|m| indices.get(m.as_str()));
+        if let Some(meta_index) = meta_index {
+            sprite_sidecars.insert(*meta_index);
+            let palette_index =
+                sprite_meta::palette_name(&file.name).and_then(|p| indices.get(p.as_str()));
+            if let Some(palette_index) = palette_index {
+                sprite_sidecars.insert(*palette_index);
+            }
+        }
+    }
+    for (gbfs_index, file) in files.iter().enumerate() {
+        if sprite_sidecars.contains(&gbfs_index) {
+            // Reachable through the sprite it belongs to
+            continue;
+        }
+        let meta_index = sprite_meta::meta_name(&file.name).and_then(|m| indices.get(m.as_str()));
+        let (module, name) = if let Some(meta_index) = meta_index {
+            let meta = SpriteMeta::from_bytes(files[*meta_index].data)
+                .map_err(|err| ManifestError::InvalidMeta(file.name.clone(), err))?;
+            let size = hw_sprite_size(meta.width, meta.height).ok_or_else(|| {
+                ManifestError::UnsupportedSpriteSize(file.name.clone(), meta.width, meta.height)
+            })?;
+            let data_len = compression::decompressed_len(file.data);
+            if data_len != meta.data_len() {
+                return Err(ManifestError::SizeMismatch(
+                    file.name.clone(),
+                    meta.data_len(),
+                    data_len,
+                ));
+            }
+            let palette_index =
+                sprite_meta::palette_name(&file.name).and_then(|p| indices.get(p.as_str()));
+            let palette = match palette_index {
+                Some(palette_index) if meta.bpp == 4 => {
+                    format!("Some({})", asset(*palette_index).replace("\n", "\n    "))
+                }
+                None if meta.bpp == 4 => {
+                    return Err(ManifestError::MissingPalette(file.name.clone()));
+                }
+                _ => "None".to_string(),
+            };
+            let name = const_name(file.name.trim_end_matches(sprite_meta::TILES_SUFFIX));
+            writeln!(
+                sprite_consts,
+                "    pub const {}: SpriteAsset = SpriteAsset {{\n        asset: {},\n        meta: {},\n        size: HWSpriteSize::{},\n        palette: {},\n    }};",
+                name,
+                asset(gbfs_index).replace("\n", "\n    "),
+                asset(*meta_index).replace("\n", "\n    "),
+                size,
+                palette
+            )
+            .unwrap();
+            ("sprites", name)
+        } else if file.name.ends_with(PALETTE_SUFFIX) {
+            let name = const_name(file.name.trim_end_matches(PALETTE_SUFFIX));
+            writeln!(
+                palette_consts,
+                "    pub const {}: Asset = {};",
+                name,
+                asset(gbfs_index)
+            )
+            .unwrap();
+            ("palettes", name)
+        } else {
+            let name = const_name(&file.name);
+            writeln!(
+                file_consts,
+                "    pub const {}: Asset = {};",
+                name,
+                asset(gbfs_index)
+            )
+            .unwrap();
+            ("files", name)
+        };
+        if !names.insert((module, name.clone())) {
+            return Err(ManifestError::DuplicateName(format!(
+                "{}::{}",
+                module, name
+            )));
+        }
+    }
+
+    let mut manifest = String::new();
+    manifest.push_str(
+        "// Generated by `asset-converter manifest` from the asset archive. Do not edit.\n\n",
+    );
+    manifest.push_str("/// Hardware sprites.\npub mod sprites {\n    use crate::assets::{Asset, SpriteAsset};\n    use crate::sprite::HWSpriteSize;\n\n");
+    manifest.push_str(&sprite_consts);
+    manifest.push_str("}\n\n/// Palettes.\npub mod palettes {\n    use crate::assets::Asset;\n\n");
+    manifest.push_str(&palette_consts);
+    manifest
+        .push_str("}\n\n/// All other files.\npub mod files {\n    use crate::assets::Asset;\n\n");
+    manifest.push_str(&file_consts);
+    manifest.push_str("}\n");
+    return Ok(manifest);
+}
+
+/// Turns a file name into a constant name, for example `dart_ship` into `DART_SHIP`
+/// and `font_chars.txt` into `FONT_CHARS_TXT`.
+fn const_name(file_name: &str) -> String {
+    let mut name = String::new();
+    let mut prev: Option<char> = None;
+    for c in file_name.chars() {
+        let starts_word = c.is_ascii_uppercase()
+            && prev.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit());
+        if starts_word {
+            name.push('_');
+        }
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_uppercase());
+        } else {
+            name.push('_');
+        }
+        prev = Some(c);
+    }
+    if name.chars().next().is_none_or(|c| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    return name;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file<'a>(name: &str, data: &'a [u8]) -> GbfsFile<'a> {
+        return GbfsFile {
+            name: name.to_string(),
+            data,
+        };
+    }
+
+    #[test]
+    fn names_constants() {
+        assert_eq!(const_name("dart_ship"), "DART_SHIP");
+        assert_eq!(const_name("font_chars.txt"), "FONT_CHARS_TXT");
+        assert_eq!(const_name("fontTiles"), "FONT_TILES");
+        assert_eq!(const_name("crater_0Map"), "CRATER_0_MAP");
+        assert_eq!(const_name("8bit"), "_8BIT");
+    }
+
+    fn meta(width: u16, height: u16) -> [u8; sprite_meta::META_LEN] {
+        return meta_with_bpp(width, height, 8);
+    }
+
+    fn meta_with_bpp(width: u16, height: u16, bpp: u8) -> [u8; sprite_meta::META_LEN] {
+        return SpriteMeta {
+            width,
+            height,
+            bpp,
+            frames: 1,
+        }
+        .to_bytes();
+    }
+
+    #[test]
+    fn generates_manifest() {
+        let tiles = [0; 32 * 32];
+        let meta = meta(32, 32);
+        let files = [
+            file("dart_shipMeta", &meta),
+            file("dart_shipTiles", &tiles),
+            file("fontTiles", &[0; 64]),
+            file("maps.json", b"{}"),
+            file("sprite_sharedPal", &[0; 512]),
+        ];
+        let manifest = generate(&files).unwrap();
+        assert!(manifest.contains(
+            "    pub const DART_SHIP: SpriteAsset = SpriteAsset {\n        asset: Asset {\n            name: \"dart_shipTiles\",\n            gbfs_index: 1,\n        },\n        meta: Asset {\n            name: \"dart_shipMeta\",\n            gbfs_index: 0,\n        },\n        size: HWSpriteSize::ThirtyTwoByThirtyTwo,\n        palette: None,\n    };\n"
+        ));
+        assert!(manifest.contains("    pub const SPRITE_SHARED: Asset = Asset {\n        name: \"sprite_sharedPal\",\n        gbfs_index: 4,\n    };\n"));
+        assert!(manifest.contains("    pub const MAPS_JSON: Asset"));
+        assert!(manifest.contains("    pub const FONT_TILES: Asset"));
+        assert!(!manifest.contains("DART_SHIP_META"));
+    }
+
+    #[test]
+    fn attaches_palettes_to_4bpp_sprites() {
+        let tiles = [0; 8 * 8 / 2];
+        let meta = meta_with_bpp(8, 8, 4);
+        let files = [
+            file("cursorMeta", &meta),
+            file("cursorPal", &[0; 32]),
+            file("cursorTiles", &tiles),
+        ];
+        let manifest = generate(&files).unwrap();
+        assert!(manifest.contains(
+            "        palette: Some(Asset {\n            name: \"cursorPal\",\n            gbfs_index: 1,\n        }),\n"
+        ));
+        assert!(!manifest.contains("pub const CURSOR: Asset"));
+
+        assert_eq!(
+            generate(&[file("cursorMeta", &meta), file("cursorTiles", &tiles)]),
+            Err(ManifestError::MissingPalette("cursorTiles".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_bad_sprites() {
+        let tiles = [0; 64];
+        let check =
+            |meta: &[u8]| generate(&[file("cursorMeta", meta), file("cursorTiles", &tiles)]);
+        assert!(check(&meta(8, 8)).is_ok());
+        // Compressed sprites are checked against their decompressed length
+        let compressed = compression::compress(&tiles);
+        assert!(compressed.len() < tiles.len());
+        assert!(generate(&[
+            file("cursorMeta", &meta(8, 8)),
+            file("cursorTiles", &compressed)
+        ])
+        .is_ok());
+        assert_eq!(
+            check(&meta(16, 16)),
+            Err(ManifestError::SizeMismatch(
+                "cursorTiles".to_string(),
+                256,
+                64
+            ))
+        );
+        assert_eq!(
+            check(&meta(8, 24)),
+            Err(ManifestError::UnsupportedSpriteSize(
+                "cursorTiles".to_string(),
+                8,
+                24
+            ))
+        );
+        assert_eq!(
+            check(&[8]),
+            Err(ManifestError::InvalidMeta(
+                "cursorTiles".to_string(),
+                SpriteMetaError::BadLength(1)
+            ))
+        );
+    }
+}
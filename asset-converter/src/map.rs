@@ -0,0 +1,653 @@
+//! Conversion of decoded Mindustry maps into the data loaded by the game's `map` module.
+//!
+//! Each Mindustry tile is drawn as a 16x16 pixel block made up of 2x2 GBA tiles.
+//! Floors end up in the base layer, while ores and static blocks like rocks are drawn into a transparent
+//! overlay layer on top of it. Both layers share one tileset.
+//! The resulting tilemaps are split into 32x32 tile chunks (one screenblock each),
+//! stored in left-to-right, top-to-bottom order.
+//!
+//! Animated blocks (see `content::ANIMATIONS`) get tiles of their own, whose graphics the game swaps out
+//! with the frames stored in a separate file.
+//!
+//! For the minimap, every Mindustry tile is also reduced to a single color out of a small palette.
+
+use crate::buildings;
+use crate::content::{self, Animation};
+use crate::image::{to_gba_color, Image, Rgba};
+use crate::msav::{MapLayers, Save};
+use crate::sprites::{SpriteLibrary, MINDUSTRY_TILE_SIZE};
+use crate::tiles::{
+    reduce_colors, TileError, Tileset, BLANK_ENTRY, TILE_SIZE, TILE_SIZE_BYTES_4BPP,
+};
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::Serialize;
+
+/// How many GBA tiles wide and high a single Mindustry tile is drawn.
+pub const TILES_PER_BLOCK: usize = 2;
+/// Edge length of a map chunk, in tiles.
+pub const CHUNK_SIZE: usize = 32;
+/// Palette banks available to maps (the last two belong to buildings and the text engine).
+const MAX_PALETTE_BANKS: usize = 14;
+/// Number of 4bpp tiles that fit into the map's charblock, minus the ones reserved for buildings.
+const MAX_TILES: usize = 512 - buildings::MAX_TILES;
+/// Number of colors the minimap may use, which has to match the game's `map::minimap`.
+const MINIMAP_COLORS: usize = 10;
+/// Maximum length of the map name part of GBFS filenames, so that suffixes still fit into 24 bytes.
+const MAX_FILENAME_BASE_LEN: usize = 12;
+
+/// Top-level structure of `maps.json`.
+/// Mirrors the structs in the game's `map.rs`.
+#[derive(Serialize, Debug, Default)]
+pub struct Maps {
+    pub maps: Vec<MapEntry>,
+}
+
+/// Describes a single map.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MapEntry {
+    /// Display name
+    pub name: String,
+    /// Width in tiles
+    pub width: usize,
+    /// Height in tiles
+    pub height: usize,
+    /// GBFS file containing the 4bpp tile graphics
+    pub tiles: String,
+    /// GBFS file containing the palette
+    pub palette: String,
+    /// GBFS file containing the frames of all animated tiles
+    pub animated_tiles: String,
+    /// Tiles whose graphics are swapped out at runtime
+    pub animations: Vec<TileAnimation>,
+    /// GBFS file containing one byte per Mindustry tile (2x2 tiles), describing which ore is there
+    pub ore_layer: String,
+    /// GBFS file containing one byte per Mindustry tile, 1 if units can't move through it and 0 otherwise
+    pub solid_layer: String,
+    /// GBFS file containing one byte per Mindustry tile, the index of its color in the minimap palette
+    pub minimap: String,
+    /// GBFS file containing the minimap palette
+    pub minimap_palette: String,
+    /// Chunks in left-to-right, top-to-bottom order
+    pub chunks: Vec<MapChunk>,
+    /// Chunks of the overlay layer, in the same order
+    pub overlay_chunks: Vec<MapChunk>,
+    /// Enemy spawn points, in tiles
+    pub spawns: Vec<MapPoint>,
+    /// Blocks which are already built when the map is loaded
+    pub derelicts: Vec<DerelictBlock>,
+}
+
+/// Tiles of the map's tileset which are animated together.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TileAnimation {
+    /// Indices of the animated tiles in the map's tileset
+    pub tiles: Vec<u16>,
+    /// Number of frames
+    pub frames: usize,
+    /// How many vblanks each frame is shown for
+    pub frame_duration: u8,
+    /// Index of the first tile of the first frame in the animated tiles file.
+    /// Each frame consists of one tile per entry of `tiles`, in the same order, followed by the next frame.
+    pub data_offset: usize,
+}
+
+/// Describes a 32x32 chunk.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MapChunk {
+    pub filename: String,
+}
+
+/// Position of the top-left corner of a Mindustry tile, in tiles.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct MapPoint {
+    pub x: usize,
+    pub y: usize,
+}
+
+/// A pre-placed block.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DerelictBlock {
+    /// Mindustry's name for the block
+    pub block: String,
+    /// Position of the block's center tile
+    pub pos: MapPoint,
+}
+
+/// A converted map and the files that have to be stored in GBFS alongside it.
+pub struct ConvertedMap {
+    pub entry: MapEntry,
+    pub files: Vec<(String, Vec<u8>)>,
+}
+
+/// Converts a map, drawing it with the sprites from the library.
+///
+/// `fallback_name` is used if the map's metadata doesn't contain a name.
+pub fn convert(
+    save: &Save,
+    fallback_name: &str,
+    sprites: &mut SpriteLibrary,
+) -> Result<ConvertedMap, TileError> {
+    let name = save
+        .meta
+        .get("name")
+        .map(String::as_str)
+        .unwrap_or(fallback_name)
+        .to_string();
+    let base = filename_base(&name);
+    let layers = &save.map;
+
+    let width = layers.width * TILES_PER_BLOCK;
+    let height = layers.height * TILES_PER_BLOCK;
+    let chunks_x = width.div_ceil(CHUNK_SIZE);
+    let chunks_y = height.div_ceil(CHUNK_SIZE);
+    let tilemap_width = chunks_x * CHUNK_SIZE;
+    let mut tilemap = vec![BLANK_ENTRY; tilemap_width * chunks_y * CHUNK_SIZE];
+    let mut overlay_tilemap = tilemap.clone();
+
+    let mut renderer = BlockRenderer {
+        sprites,
+        tileset: Tileset::new(MAX_PALETTE_BANKS, MAX_TILES),
+        rendered: HashMap::new(),
+        animations: Vec::new(),
+        animation_frames: Vec::new(),
+        minimap_colors: HashMap::new(),
+    };
+    let mut ore_layer = Vec::with_capacity(layers.width * layers.height);
+    let mut solid_layer = Vec::with_capacity(layers.width * layers.height);
+    let mut minimap_colors = Vec::with_capacity(layers.width * layers.height);
+    let mut spawns = Vec::new();
+    let mut derelicts = Vec::new();
+
+    // Mindustry's Y axis points up, ours points down
+    for row in 0..layers.height {
+        for x in 0..layers.width {
+            let idx = layers.index(x, layers.height - 1 - row);
+            let pos = MapPoint {
+                x: x * TILES_PER_BLOCK,
+                y: row * TILES_PER_BLOCK,
+            };
+            let overlay = layers.name(layers.overlay[idx]);
+            let block = layers.name(layers.block[idx]);
+
+            ore_layer.push(content::ore_value(overlay).unwrap_or(content::NO_ORE));
+            solid_layer.push(content::is_solid(block) as u8);
+            if content::is_spawn(overlay) {
+                spawns.push(pos);
+            }
+            if layers.building_center[idx] && !content::is_static_block(block) {
+                derelicts.push(DerelictBlock {
+                    block: block.to_string(),
+                    pos,
+                });
+            }
+
+            minimap_colors.push(renderer.minimap_color(layers, idx));
+            let (entries, overlay_entries) = renderer.render(layers, idx)?;
+            for i in 0..TILES_PER_BLOCK * TILES_PER_BLOCK {
+                let tx = pos.x + i % TILES_PER_BLOCK;
+                let ty = pos.y + i / TILES_PER_BLOCK;
+                tilemap[tx + ty * tilemap_width] = entries[i];
+                overlay_tilemap[tx + ty * tilemap_width] = overlay_entries[i];
+            }
+        }
+    }
+
+    println!(
+        "[MAP] {} uses {} tiles, {} animations",
+        name,
+        renderer.tileset.num_tiles(),
+        renderer.animations.len()
+    );
+
+    let mut files = Vec::new();
+    let chunks = split_into_chunks(&tilemap, chunks_x, chunks_y, &base, "Map", &mut files);
+    let overlay_chunks = split_into_chunks(
+        &overlay_tilemap,
+        chunks_x,
+        chunks_y,
+        &base,
+        "Ovl",
+        &mut files,
+    );
+
+    let entry = MapEntry {
+        name,
+        width,
+        height,
+        tiles: format!("{}Tiles", base),
+        palette: format!("{}Pal", base),
+        animated_tiles: format!("{}Anim", base),
+        animations: renderer.animations,
+        ore_layer: format!("{}Ores", base),
+        solid_layer: format!("{}Solid", base),
+        minimap: format!("{}Mini", base),
+        minimap_palette: format!("{}MiniPal", base),
+        chunks,
+        overlay_chunks,
+        spawns,
+        derelicts,
+    };
+    let palette: Vec<u8> = renderer
+        .tileset
+        .palette()
+        .iter()
+        .flat_map(|c| c.to_le_bytes().to_vec())
+        .collect();
+    files.push((entry.tiles.clone(), renderer.tileset.tile_data()));
+    files.push((entry.palette.clone(), palette));
+    files.push((entry.animated_tiles.clone(), renderer.animation_frames));
+    files.push((entry.ore_layer.clone(), ore_layer));
+    files.push((entry.solid_layer.clone(), solid_layer));
+    let (minimap, minimap_palette) = to_minimap(&minimap_colors);
+    files.push((entry.minimap.clone(), minimap));
+    files.push((entry.minimap_palette.clone(), minimap_palette));
+    return Ok(ConvertedMap { entry, files });
+}
+
+/// Reduces the colors of the Mindustry tiles to at most `MINIMAP_COLORS`.
+///
+/// Returns one palette index per tile and the palette.
+fn to_minimap(colors: &[u16]) -> (Vec<u8>, Vec<u8>) {
+    let unique: BTreeSet<u16> = colors.iter().copied().collect();
+    let replacements = reduce_colors(&unique, MINIMAP_COLORS);
+    let palette: Vec<u16> = unique
+        .iter()
+        .filter(|c| !replacements.contains_key(c))
+        .copied()
+        .collect();
+    let indices = colors
+        .iter()
+        .map(|c| {
+            let c = replacements.get(c).unwrap_or(c);
+            palette.iter().position(|p| p == c).unwrap() as u8
+        })
+        .collect();
+    return (
+        indices,
+        palette
+            .iter()
+            .flat_map(|c| c.to_le_bytes().to_vec())
+            .collect(),
+    );
+}
+
+/// Splits the tilemap into chunks named `{base}_{index}{suffix}`, adding them to `files`.
+fn split_into_chunks(
+    tilemap: &[u16],
+    chunks_x: usize,
+    chunks_y: usize,
+    base: &str,
+    suffix: &str,
+    files: &mut Vec<(String, Vec<u8>)>,
+) -> Vec<MapChunk> {
+    let tilemap_width = chunks_x * CHUNK_SIZE;
+    let mut chunks = Vec::with_capacity(chunks_x * chunks_y);
+    for cy in 0..chunks_y {
+        for cx in 0..chunks_x {
+            let mut chunk = Vec::with_capacity(CHUNK_SIZE * CHUNK_SIZE * 2);
+            for ty in 0..CHUNK_SIZE {
+                let start = cx * CHUNK_SIZE + (cy * CHUNK_SIZE + ty) * tilemap_width;
+                for entry in &tilemap[start..start + CHUNK_SIZE] {
+                    chunk.extend_from_slice(&entry.to_le_bytes());
+                }
+            }
+            let filename = format!("{}_{}{}", base, chunks.len(), suffix);
+            files.push((filename.clone(), chunk));
+            chunks.push(MapChunk { filename });
+        }
+    }
+    return chunks;
+}
+
+/// Returns the (floor, overlay, block) IDs of the Mindustry tile which are part of the map's terrain.
+/// Spawns are invisible and buildings become entities, so those are replaced by air.
+fn terrain_ids(layers: &MapLayers, idx: usize) -> (u16, u16, u16) {
+    let mut overlay = layers.overlay[idx];
+    if content::is_spawn(layers.name(overlay)) {
+        overlay = 0;
+    }
+    let mut block = layers.block[idx];
+    if !content::is_static_block(layers.name(block)) {
+        block = 0;
+    }
+    return (layers.floor[idx], overlay, block);
+}
+
+/// Derives a prefix for the map's GBFS filenames from its name.
+fn filename_base(name: &str) -> String {
+    return name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .take(MAX_FILENAME_BASE_LEN)
+        .collect();
+}
+
+/// Draws Mindustry tiles and converts them to GBA tiles, reusing the result for identical tiles.
+struct BlockRenderer<'a> {
+    sprites: &'a mut SpriteLibrary,
+    tileset: Tileset,
+    /// Maps (floor, overlay, block) to the resulting screen entries, where only one of floor
+    /// and overlay/block is set.
+    rendered: HashMap<(u16, u16, u16), BlockEntries>,
+    animations: Vec<TileAnimation>,
+    /// Tile data of all frames of the animations
+    animation_frames: Vec<u8>,
+    /// Maps (floor, overlay, block) to the tile's color on the minimap
+    minimap_colors: HashMap<(u16, u16, u16), u16>,
+}
+
+/// Screen entries of the 2x2 tiles a Mindustry tile is drawn as, in left-to-right, top-to-bottom order.
+type BlockEntries = [u16; TILES_PER_BLOCK * TILES_PER_BLOCK];
+
+impl<'a> BlockRenderer<'a> {
+    /// Returns the screen entries of the Mindustry tile's floor and overlay layers.
+    fn render(
+        &mut self,
+        layers: &MapLayers,
+        idx: usize,
+    ) -> Result<(BlockEntries, BlockEntries), TileError> {
+        let (floor, overlay, block) = terrain_ids(layers, idx);
+        return Ok((
+            self.render_ids(layers, (floor, 0, 0))?,
+            self.render_ids(layers, (0, overlay, block))?,
+        ));
+    }
+
+    /// Returns the average color of the Mindustry tile's terrain, as shown on the minimap.
+    fn minimap_color(&mut self, layers: &MapLayers, idx: usize) -> u16 {
+        let ids = terrain_ids(layers, idx);
+        if let Some(color) = self.minimap_colors.get(&ids) {
+            return *color;
+        }
+
+        let (floor, overlay, block) = ids;
+        let names: Vec<&str> = [floor, overlay, block]
+            .iter()
+            .filter(|id| **id != 0)
+            .map(|id| layers.name(*id))
+            .collect();
+        let img = self.draw(&names, None);
+        let mut sums = [0_usize; 3];
+        let mut count = 0;
+        for y in 0..img.height {
+            for x in 0..img.width {
+                if let Some(color) = to_gba_color(img.get(x, y)) {
+                    for (channel, sum) in sums.iter_mut().enumerate() {
+                        *sum += ((color >> (channel * 5)) & 0x1F) as usize;
+                    }
+                    count += 1;
+                }
+            }
+        }
+        let color = sums
+            .iter()
+            .enumerate()
+            .map(|(channel, sum)| ((sum / count.max(1)) as u16) << (channel * 5))
+            .sum();
+        self.minimap_colors.insert(ids, color);
+        return color;
+    }
+
+    /// Draws the given floor, overlay and block on top of each other, with air being transparent.
+    /// If one of them is animated, all frames of the animation are drawn.
+    fn render_ids(
+        &mut self,
+        layers: &MapLayers,
+        ids: (u16, u16, u16),
+    ) -> Result<BlockEntries, TileError> {
+        if let Some(entries) = self.rendered.get(&ids) {
+            return Ok(*entries);
+        }
+
+        let (floor, overlay, block) = ids;
+        // ID 0 is always air
+        let names: Vec<&str> = [floor, overlay, block]
+            .iter()
+            .filter(|id| **id != 0)
+            .map(|id| layers.name(*id))
+            .collect();
+        let mut entries: BlockEntries = [BLANK_ENTRY; TILES_PER_BLOCK * TILES_PER_BLOCK];
+        match names.iter().find_map(|name| content::animation(name)) {
+            Some(animation) => {
+                let frames: Vec<Image> = (0..animation.frames.len())
+                    .map(|frame| self.draw(&names, Some((animation, frame))))
+                    .collect();
+                let mut tiles = Vec::new();
+                let mut tile_frames = Vec::new();
+                for (i, entry) in entries.iter_mut().enumerate() {
+                    let x = (i % TILES_PER_BLOCK) * TILE_SIZE;
+                    let y = (i / TILES_PER_BLOCK) * TILE_SIZE;
+                    let (animated_entry, data) = self.tileset.add_animated_tile(&frames, x, y)?;
+                    *entry = animated_entry;
+                    if let Some(data) = data {
+                        // The lower 10 bits are the tile index
+                        tiles.push(animated_entry & 0x3FF);
+                        tile_frames.push(data);
+                    }
+                }
+                if !tiles.is_empty() {
+                    self.add_animation(animation, tiles, &tile_frames);
+                }
+            }
+            None => {
+                let img = self.draw(&names, None);
+                for (i, entry) in entries.iter_mut().enumerate() {
+                    let x = (i % TILES_PER_BLOCK) * TILE_SIZE;
+                    let y = (i / TILES_PER_BLOCK) * TILE_SIZE;
+                    *entry = self.tileset.add_tile(&img, x, y)?;
+                }
+            }
+        }
+        self.rendered.insert(ids, entries);
+        return Ok(entries);
+    }
+
+    /// Draws the given blocks on top of each other.
+    /// An animated block is drawn with the given frame of its animation, if any.
+    fn draw(&mut self, names: &[&str], frame: Option<(&Animation, usize)>) -> Image {
+        let size = TILES_PER_BLOCK * TILE_SIZE;
+        let mut img = Image::new(size, size);
+        for name in names {
+            let sprite = match frame {
+                Some((animation, frame)) if animation.block == *name => {
+                    let (sprite, dx) = animation.frames[frame];
+                    self.sprite(sprite, size).shifted(dx)
+                }
+                _ => self.sprite(name, size),
+            };
+            img.draw(&sprite, 0, 0);
+        }
+        return img;
+    }
+
+    /// Stores the frames of the animated tiles, which are given as the data of all frames per tile.
+    fn add_animation(&mut self, animation: &Animation, tiles: Vec<u16>, tile_frames: &[Vec<u8>]) {
+        let data_offset = self.animation_frames.len() / TILE_SIZE_BYTES_4BPP;
+        for frame in 0..animation.frames.len() {
+            for data in tile_frames {
+                self.animation_frames.extend_from_slice(
+                    &data[frame * TILE_SIZE_BYTES_4BPP..(frame + 1) * TILE_SIZE_BYTES_4BPP],
+                );
+            }
+        }
+        self.animations.push(TileAnimation {
+            tiles,
+            frames: animation.frames.len(),
+            frame_duration: animation.frame_duration,
+            data_offset,
+        });
+    }
+
+    /// Returns the block's sprite, scaled to the given size.
+    /// Mindustry's sprites are 32x32 pixels per tile, so the top-left tile of larger sprites is used.
+    fn sprite(&mut self, name: &str, size: usize) -> Image {
+        match self.sprites.block_sprite(name) {
+            Some(img) => {
+                return img
+                    .crop(0, 0, MINDUSTRY_TILE_SIZE, MINDUSTRY_TILE_SIZE)
+                    .scaled(size, size)
+            }
+            None => {
+                eprintln!("[MAP] No sprite for {}, substituting a solid color", name);
+                return Image::filled(size, size, placeholder_color(name));
+            }
+        }
+    }
+}
+
+/// Derives a stable color from a block's name, so that missing sprites are at least distinguishable.
+pub fn placeholder_color(name: &str) -> Rgba {
+    let hash = name.bytes().fold(0x811C_9DC5_u32, |h, b| {
+        (h ^ b as u32).wrapping_mul(0x0100_0193)
+    });
+    return [(hash >> 16) as u8, (hash >> 8) as u8, hash as u8, 255];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msav;
+
+    fn example_images() -> Vec<(&'static str, Image)> {
+        let mut ore = Image::new(32, 32);
+        ore.set(0, 0, [200, 100, 0, 255]);
+        return vec![
+            ("stone1", Image::filled(32, 32, [80, 80, 80, 255])),
+            ("sand1", Image::filled(32, 32, [200, 200, 0, 255])),
+            ("ore-copper1", ore),
+            ("rocks1", Image::filled(32, 32, [10, 10, 10, 255])),
+        ];
+    }
+
+    fn example_sprites() -> SpriteLibrary {
+        return SpriteLibrary::from_images(example_images());
+    }
+
+    #[test]
+    fn converts_example_map() {
+        let save = msav::decode(&msav::tests::example_save()).unwrap();
+        let converted = convert(&save, "fallback", &mut example_sprites()).unwrap();
+        let entry = &converted.entry;
+
+        assert_eq!(entry.name, "Test Map");
+        assert_eq!((entry.width, entry.height), (6, 4));
+        assert_eq!(entry.chunks.len(), 1);
+        assert_eq!(entry.chunks[0].filename, "test_map_0Map");
+        assert_eq!(entry.tiles, "test_mapTiles");
+        // The bottom row of the save is the top row of our map
+        assert_eq!(entry.spawns, [MapPoint { x: 2, y: 2 }]);
+        assert_eq!(
+            entry.derelicts,
+            [DerelictBlock {
+                block: "copper-wall".to_string(),
+                pos: MapPoint { x: 0, y: 0 }
+            }]
+        );
+
+        let files: HashMap<String, Vec<u8>> = converted.files.into_iter().collect();
+        assert_eq!(files["test_mapOres"], [0, 0, 0, 2, 0, 0]);
+        // Only the rocks are solid
+        assert_eq!(files["test_mapSolid"], [0, 0, 0, 0, 0, 1]);
+        assert_eq!(entry.minimap, "test_mapMini");
+        let minimap = &files["test_mapMini"];
+        // One color per Mindustry tile: stone, sand, stone with ore and stone with rocks
+        assert_eq!(minimap.len(), 3 * 2);
+        assert_eq!(minimap[0], minimap[1]);
+        assert_eq!(minimap[0], minimap[4]);
+        assert_ne!(minimap[0], minimap[2]);
+        assert_ne!(minimap[0], minimap[3]);
+        assert_ne!(minimap[0], minimap[5]);
+        let palette = &files["test_mapMiniPal"];
+        assert_eq!(
+            palette.len(),
+            2 * (*minimap.iter().max().unwrap() as usize + 1)
+        );
+        assert_eq!(
+            u16::from_le_bytes([
+                palette[minimap[0] as usize * 2],
+                palette[minimap[0] as usize * 2 + 1]
+            ]),
+            to_gba_color([80, 80, 80, 255]).unwrap()
+        );
+        assert_eq!(entry.overlay_chunks[0].filename, "test_map_0Ovl");
+        let entry_at = |chunk: &str, x: usize, y: usize| {
+            let chunk = &files[chunk];
+            assert_eq!(chunk.len(), CHUNK_SIZE * CHUNK_SIZE * 2);
+            u16::from_le_bytes([
+                chunk[(x + y * CHUNK_SIZE) * 2],
+                chunk[(x + y * CHUNK_SIZE) * 2 + 1],
+            ])
+        };
+        let floor_at = |x: usize, y: usize| entry_at("test_map_0Map", x, y);
+        let overlay_at = |x: usize, y: usize| entry_at("test_map_0Ovl", x, y);
+        // Stone, sand and padding differ, while ores and rocks only show up in the overlay
+        let stone = floor_at(0, 0);
+        assert_ne!(stone, floor_at(4, 0));
+        assert_eq!(stone, floor_at(0, 2));
+        assert_eq!(stone, floor_at(1, 3));
+        assert_eq!(stone, floor_at(4, 2));
+        assert_eq!(floor_at(6, 0), BLANK_ENTRY);
+        assert_eq!(floor_at(0, 4), BLANK_ENTRY);
+
+        assert_eq!(overlay_at(0, 0), BLANK_ENTRY);
+        assert_eq!(overlay_at(4, 0), BLANK_ENTRY);
+        let ore = overlay_at(0, 2);
+        assert_ne!(ore, BLANK_ENTRY);
+        assert_ne!(ore, stone);
+        assert_ne!(overlay_at(4, 2), BLANK_ENTRY);
+        assert_ne!(overlay_at(4, 2), ore);
+    }
+
+    #[test]
+    fn animates_tiles() {
+        let mut save = msav::decode(&msav::tests::example_save()).unwrap();
+        // Turn the sand tile into water
+        let sand = save
+            .map
+            .block_names
+            .iter()
+            .position(|n| n == "sand")
+            .unwrap();
+        save.map.block_names[sand] = "water".to_string();
+        // A single stripe, so that every tile of the block is different in some frames
+        let mut water = Image::filled(32, 32, [0, 0, 200, 255]);
+        for y in 0..32 {
+            water.set(0, y, [0, 100, 255, 255]);
+        }
+        let mut images = example_images();
+        images.push(("water", water));
+        let mut sprites = SpriteLibrary::from_images(images);
+        let converted = convert(&save, "fallback", &mut sprites).unwrap();
+        let entry = &converted.entry;
+
+        assert_eq!(entry.animations.len(), 1);
+        let animation = &entry.animations[0];
+        assert_eq!(animation.frames, 4);
+        assert_eq!(animation.data_offset, 0);
+        // The stripe moves through all 4 tiles of the block
+        assert_eq!(animation.tiles.len(), 4);
+
+        let files: HashMap<String, Vec<u8>> = converted.files.into_iter().collect();
+        assert_eq!(entry.animated_tiles, "test_mapAnim");
+        assert_eq!(
+            files["test_mapAnim"].len(),
+            4 * animation.tiles.len() * TILE_SIZE_BYTES_4BPP
+        );
+        let chunk = &files["test_map_0Map"];
+        let water_entry = u16::from_le_bytes([chunk[4 * 2], chunk[4 * 2 + 1]]);
+        assert!(animation.tiles.contains(&(water_entry & 0x3FF)));
+    }
+
+    #[test]
+    fn shortens_filenames() {
+        assert_eq!(filename_base("Frozen Forest!"), "frozen_fores");
+    }
+}
@@ -0,0 +1,447 @@
+//! Decoder for Mindustry's `.msav` save format, which is also used for maps.
+//!
+//! Only the parts needed for importing maps are decoded: the metadata, the content header
+//! (which maps numeric IDs to content names) and the map region with the floor, overlay and block layers.
+//! Everything after the map region (entities etc.) is ignored.
+//!
+//! The layout of the format is taken from Mindustry's `SaveVersion.java`.
+//! Save versions 1 to 4 don't record whether a block has a tile entity, which means we have to guess
+//! that from the block's name (see `content::is_static_block`).
+//! From version 5 onward that information is stored in the file.
+
+use crate::content;
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt;
+use std::io::{self, Read};
+
+use flate2::read::ZlibDecoder;
+
+/// The ordinal of the "block" content type in Mindustry's `ContentType` enum.
+const CONTENT_TYPE_BLOCK: u8 = 1;
+/// Newest save version we know how to read.
+const MAX_SUPPORTED_VERSION: i32 = 7;
+
+/// Things that may go wrong while decoding a save file.
+#[derive(Debug)]
+pub enum MsavError {
+    /// The file couldn't be inflated.
+    Decompress(io::Error),
+    /// The file doesn't start with the `MSAV` magic.
+    BadMagic,
+    /// The save was written by a version of Mindustry we don't know how to read.
+    UnsupportedVersion(i32),
+    /// The file ended in the middle of a structure.
+    UnexpectedEof,
+    /// A tile references a block ID that's missing from the content header.
+    UnknownBlock(u16),
+}
+
+impl fmt::Display for MsavError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use MsavError::*;
+        match self {
+            Decompress(err) => write!(f, "MsavError: Failed to decompress save: {}", err),
+            BadMagic => write!(f, "MsavError: File is not a Mindustry save"),
+            UnsupportedVersion(version) => {
+                write!(f, "MsavError: Unsupported save version {}", version)
+            }
+            UnexpectedEof => write!(f, "MsavError: Save ended unexpectedly"),
+            UnknownBlock(id) => write!(f, "MsavError: Block ID {} not in content header", id),
+        }
+    }
+}
+
+impl std::error::Error for MsavError {}
+
+/// A decoded save file.
+#[derive(Debug, Clone)]
+pub struct Save {
+    pub version: i32,
+    /// Map metadata, such as the map's name and author.
+    pub meta: HashMap<String, String>,
+    pub map: MapLayers,
+}
+
+/// The tile layers of a map.
+///
+/// All layers are indexed by `x + y * width`.
+/// Like in Mindustry, `y = 0` is the bottom row of the map.
+#[derive(Debug, Clone)]
+pub struct MapLayers {
+    pub width: usize,
+    pub height: usize,
+    /// Block ID of the floor of each tile.
+    pub floor: Vec<u16>,
+    /// Block ID of the overlay (ores, spawns, decals) of each tile. Air if there is none.
+    pub overlay: Vec<u16>,
+    /// Block ID of the block on each tile. Air if there is none.
+    pub block: Vec<u16>,
+    /// Whether the tile is the center of a building, as opposed to terrain or a part of a larger block.
+    pub building_center: Vec<bool>,
+    /// Maps block IDs to their content names.
+    pub block_names: Vec<String>,
+}
+
+impl MapLayers {
+    /// Returns the content name of the block with the given ID.
+    pub fn name(&self, id: u16) -> &str {
+        return &self.block_names[id as usize];
+    }
+
+    /// Returns the index of the given tile in the layer vectors.
+    pub fn index(&self, x: usize, y: usize) -> usize {
+        return x + y * self.width;
+    }
+}
+
+/// Decompresses and decodes a `.msav` file.
+pub fn decode(compressed: &[u8]) -> Result<Save, MsavError> {
+    let mut data = Vec::new();
+    ZlibDecoder::new(compressed)
+        .read_to_end(&mut data)
+        .map_err(MsavError::Decompress)?;
+    return decode_uncompressed(&data);
+}
+
+/// Decodes an already inflated save.
+pub fn decode_uncompressed(data: &[u8]) -> Result<Save, MsavError> {
+    let mut r = Reader::new(data);
+    if r.bytes(4)? != b"MSAV" {
+        return Err(MsavError::BadMagic);
+    }
+    let version = r.i32()?;
+    if !(1..=MAX_SUPPORTED_VERSION).contains(&version) {
+        return Err(MsavError::UnsupportedVersion(version));
+    }
+
+    // Each region is prefixed by its length, which we don't need
+    r.i32()?;
+    let meta = read_meta(&mut r)?;
+    r.i32()?;
+    let block_names = read_content_header(&mut r)?;
+    r.i32()?;
+    let map = read_map(&mut r, version, block_names)?;
+
+    return Ok(Save { version, meta, map });
+}
+
+fn read_meta(r: &mut Reader) -> Result<HashMap<String, String>, MsavError> {
+    let num_entries = r.i16()?;
+    let mut meta = HashMap::new();
+    for _ in 0..num_entries {
+        let key = r.utf()?;
+        let value = r.utf()?;
+        meta.insert(key, value);
+    }
+    return Ok(meta);
+}
+
+/// Reads the content header, returning the names of all blocks in ID order.
+fn read_content_header(r: &mut Reader) -> Result<Vec<String>, MsavError> {
+    let mut block_names = Vec::new();
+    let mapped = r.u8()?;
+    for _ in 0..mapped {
+        let content_type = r.u8()?;
+        let total = r.i16()?;
+        for _ in 0..total {
+            let name = r.utf()?;
+            if content_type == CONTENT_TYPE_BLOCK {
+                block_names.push(name);
+            }
+        }
+    }
+    return Ok(block_names);
+}
+
+fn read_map(
+    r: &mut Reader,
+    version: i32,
+    block_names: Vec<String>,
+) -> Result<MapLayers, MsavError> {
+    let width = r.u16()? as usize;
+    let height = r.u16()? as usize;
+    let num_tiles = width * height;
+    let check_id = |id: u16| -> Result<u16, MsavError> {
+        if (id as usize) < block_names.len() {
+            Ok(id)
+        } else {
+            Err(MsavError::UnknownBlock(id))
+        }
+    };
+
+    // Floors and overlays are run-length encoded together
+    let mut floor = vec![0; num_tiles];
+    let mut overlay = vec![0; num_tiles];
+    let mut i = 0;
+    while i < num_tiles {
+        let floor_id = check_id(r.u16()?)?;
+        let overlay_id = check_id(r.u16()?)?;
+        let consecutives = r.u8()? as usize;
+        let end = (i + 1 + consecutives).min(num_tiles);
+        floor[i..end].fill(floor_id);
+        overlay[i..end].fill(overlay_id);
+        i += 1 + consecutives;
+    }
+
+    // Blocks are run-length encoded as well, except for those which have a tile entity
+    let mut block = vec![0; num_tiles];
+    let mut building_center = vec![false; num_tiles];
+    let mut i = 0;
+    while i < num_tiles {
+        let block_id = check_id(r.u16()?)?;
+        block[i] = block_id;
+
+        let mut had_entity = false;
+        let mut is_center = true;
+        let mut had_data = false;
+        if version >= 7 {
+            let packed = r.u8()?;
+            had_entity = packed & 1 != 0;
+            had_data = packed & 2 != 0;
+            if had_entity {
+                is_center = r.bool()?;
+            }
+        } else if version >= 5 {
+            had_entity = r.bool()?;
+            if had_entity {
+                is_center = r.bool()?;
+            }
+        } else if !content::is_static_block(&block_names[block_id as usize]) {
+            had_entity = true;
+        }
+
+        if had_entity {
+            if is_center {
+                building_center[i] = true;
+                r.chunk()?;
+            }
+            i += 1;
+        } else if had_data {
+            r.u8()?;
+            i += 1;
+        } else {
+            let consecutives = r.u8()? as usize;
+            let end = (i + 1 + consecutives).min(num_tiles);
+            block[i..end].fill(block_id);
+            i += 1 + consecutives;
+        }
+    }
+
+    return Ok(MapLayers {
+        width,
+        height,
+        floor,
+        overlay,
+        block,
+        building_center,
+        block_names,
+    });
+}
+
+/// Reads big-endian values the way Java's `DataInputStream` writes them.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Reader<'a> {
+        return Reader { data, pos: 0 };
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], MsavError> {
+        if self.pos + len > self.data.len() {
+            return Err(MsavError::UnexpectedEof);
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        return Ok(bytes);
+    }
+
+    fn u8(&mut self) -> Result<u8, MsavError> {
+        return Ok(self.bytes(1)?[0]);
+    }
+
+    fn bool(&mut self) -> Result<bool, MsavError> {
+        return Ok(self.u8()? != 0);
+    }
+
+    fn u16(&mut self) -> Result<u16, MsavError> {
+        return Ok(u16::from_be_bytes(self.bytes(2)?.try_into().unwrap()));
+    }
+
+    fn i16(&mut self) -> Result<i16, MsavError> {
+        return Ok(i16::from_be_bytes(self.bytes(2)?.try_into().unwrap()));
+    }
+
+    fn i32(&mut self) -> Result<i32, MsavError> {
+        return Ok(i32::from_be_bytes(self.bytes(4)?.try_into().unwrap()));
+    }
+
+    /// Skips a chunk prefixed by its length as an unsigned short.
+    fn chunk(&mut self) -> Result<&'a [u8], MsavError> {
+        let len = self.u16()? as usize;
+        return self.bytes(len);
+    }
+
+    /// Reads a string in Java's modified UTF-8, prefixed by its length in bytes.
+    fn utf(&mut self) -> Result<String, MsavError> {
+        let len = self.u16()? as usize;
+        return Ok(decode_modified_utf8(self.bytes(len)?));
+    }
+}
+
+/// Decodes Java's modified UTF-8, which encodes NUL as 2 bytes and
+/// supplementary characters as surrogate pairs.
+fn decode_modified_utf8(bytes: &[u8]) -> String {
+    let mut utf16: Vec<u16> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i] as u16;
+        let cont = |offset: usize| bytes.get(i + offset).map_or(0, |c| (*c & 0x3F) as u16);
+        if b & 0x80 == 0 {
+            utf16.push(b);
+            i += 1;
+        } else if b & 0xE0 == 0xC0 {
+            utf16.push(((b & 0x1F) << 6) | cont(1));
+            i += 2;
+        } else {
+            utf16.push(((b & 0x0F) << 12) | (cont(1) << 6) | cont(2));
+            i += 3;
+        }
+    }
+    return String::from_utf16_lossy(&utf16);
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    /// Writes values the way Java's `DataOutputStream` does, for constructing test saves.
+    #[derive(Default)]
+    pub(crate) struct Writer(pub Vec<u8>);
+
+    impl Writer {
+        pub fn u8(&mut self, v: u8) -> &mut Self {
+            self.0.push(v);
+            self
+        }
+        pub fn u16(&mut self, v: u16) -> &mut Self {
+            self.0.extend_from_slice(&v.to_be_bytes());
+            self
+        }
+        pub fn i32(&mut self, v: i32) -> &mut Self {
+            self.0.extend_from_slice(&v.to_be_bytes());
+            self
+        }
+        pub fn utf(&mut self, s: &str) -> &mut Self {
+            self.u16(s.len() as u16);
+            self.0.extend_from_slice(s.as_bytes());
+            self
+        }
+    }
+
+    /// Builds a compressed version 2 save of a 3x2 map.
+    ///
+    /// Floors are all "stone" except for one "sand" tile, there's a copper ore, a spawn,
+    /// a static rock wall and a derelict copper wall with a tile entity.
+    pub(crate) fn example_save() -> Vec<u8> {
+        let names = [
+            "air",
+            "spawn",
+            "stone",
+            "sand",
+            "ore-copper",
+            "rocks",
+            "copper-wall",
+        ];
+        let mut w = Writer::default();
+        w.0.extend_from_slice(b"MSAV");
+        w.i32(2);
+        // Metadata
+        w.i32(0).u16(1).utf("name").utf("Test Map");
+        // Content header
+        w.i32(0).u8(2);
+        w.u8(0).u16(1).utf("copper");
+        w.u8(CONTENT_TYPE_BLOCK).u16(names.len() as u16);
+        for name in names.iter() {
+            w.utf(name);
+        }
+        // Map region
+        w.i32(0).u16(3).u16(2);
+        // Floors: stone/ore-copper, stone/spawn, then 4 stone tiles, the last one being sand
+        w.u16(2).u16(4).u8(0);
+        w.u16(2).u16(1).u8(0);
+        w.u16(2).u16(0).u8(2);
+        w.u16(3).u16(0).u8(0);
+        // Blocks: 2 air, rocks, copper wall (with entity), 2 air
+        w.u16(0).u8(1);
+        w.u16(5).u8(0);
+        w.u16(6).u16(3).u8(0).u8(0).u8(0);
+        w.u16(0).u8(1);
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&w.0).unwrap();
+        return encoder.finish().unwrap();
+    }
+
+    #[test]
+    fn decodes_example_save() {
+        let save = decode(&example_save()).unwrap();
+        assert_eq!(save.version, 2);
+        assert_eq!(save.meta["name"], "Test Map");
+
+        let map = &save.map;
+        assert_eq!((map.width, map.height), (3, 2));
+        let floors: Vec<&str> = map.floor.iter().map(|id| map.name(*id)).collect();
+        assert_eq!(
+            floors,
+            ["stone", "stone", "stone", "stone", "stone", "sand"]
+        );
+        let overlays: Vec<&str> = map.overlay.iter().map(|id| map.name(*id)).collect();
+        assert_eq!(
+            overlays,
+            ["ore-copper", "spawn", "air", "air", "air", "air"]
+        );
+        let blocks: Vec<&str> = map.block.iter().map(|id| map.name(*id)).collect();
+        assert_eq!(blocks, ["air", "air", "rocks", "copper-wall", "air", "air"]);
+        assert_eq!(
+            map.building_center,
+            [false, false, false, true, false, false]
+        );
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(matches!(
+            decode_uncompressed(b"NOPE\0\0\0\x02"),
+            Err(MsavError::BadMagic)
+        ));
+        assert!(matches!(
+            decode_uncompressed(b"MSAV\0\0\0\x63"),
+            Err(MsavError::UnsupportedVersion(99))
+        ));
+        assert!(matches!(
+            decode_uncompressed(b"MSAV\0\0\0\x02\0"),
+            Err(MsavError::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn decodes_modified_utf8() {
+        assert_eq!(decode_modified_utf8(b"abc"), "abc");
+        assert_eq!(decode_modified_utf8(&[0xC0, 0x80]), "\0");
+        assert_eq!(decode_modified_utf8(&[0xC3, 0xA4]), "ä");
+        // U+1F600 as a surrogate pair, each half encoded separately
+        assert_eq!(
+            decode_modified_utf8(&[0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80]),
+            "\u{1F600}"
+        );
+    }
+}
@@ -0,0 +1,444 @@
+//! The complete asset pipeline, building the game's asset archive and manifest from Mindustry's
+//! and our own assets.
+//!
+//! All paths are relative to the repository root, which is where `cargo make assets` runs the pipeline.
+//! Files are packed sorted by name, so the same inputs always result in the same archive.
+
+use crate::buildings;
+use crate::compression;
+use crate::font;
+use crate::gbfs::{self, GbfsFile, MAX_NAME_LEN};
+use crate::image::Image;
+use crate::manifest;
+use crate::map;
+use crate::msav;
+use crate::sound;
+use crate::sprite_meta::{self, SpriteMeta};
+use crate::sprite_tiles::{self, SharedPalette};
+use crate::sprites;
+
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Directories containing sprites to be processed
+const SPRITES_IN_DIRS: [&str; 2] = ["Mindustry/core/assets-raw/sprites/", "assets"];
+/// Directories to ignore when converting sprites (for example, because they contain huge zone maps we don't need)
+const SPRITES_IGNORE_SUBDIRS: [&str; 4] = ["zones", "editor", "ui", "effects"];
+/// Subdirectories of the first sprite directory containing sprites which need to be rescaled
+/// (halved in resolution) in order to fit well on a GBA screen
+const SPRITES_RESIZE_SUBDIRS: [&str; 4] = ["blocks", "mechs", "walls", "items"];
+/// Name of the palette shared by all 8bpp sprites
+const SPRITE_PALETTE_NAME: &str = "sprite_sharedPal";
+/// Maximum number of colors in the shared sprite palette, excluding the transparent one.
+/// The palette mustn't take up more than the first 8 palette banks, so that the rest is left for 4bpp sprites.
+const SPRITE_PALETTE_MAX_COLORS: usize = 8 * 16 - 1;
+
+/// This is a temporary workaround for running out of palette space.
+/// Every sprite the game refers to through the asset manifest has to be listed here,
+/// or the game won't compile.
+const CURRENTLY_USED_SPRITES: [&str; 23] = [
+    "container.png",
+    "copper-wall.png",
+    "cursor.png",
+    "dart-ship.png",
+    "mechanical-drill.png",
+    "mechanical-drill-rotator.png",
+    "mechanical-drill-top.png",
+    "item-blast-compound.png",
+    "item-coal.png",
+    "item-copper.png",
+    "item-graphite.png",
+    "item-lead.png",
+    "item-metaglass.png",
+    "item-phase-fabric.png",
+    "item-plastanium.png",
+    "item-pyratite.png",
+    "item-sand.png",
+    "item-scrap.png",
+    "item-silicon.png",
+    "item-spore-pod.png",
+    "item-surge-alloy.png",
+    "item-thorium.png",
+    "item-titanium.png",
+];
+
+/// Sprites which the game animates, with how many pixels down the sprite is moved in each frame.
+/// Moving the sprite lets the player's ship hover without needing a sprite per frame.
+const ANIMATED_SPRITES: [(&str, &[usize]); 1] = [("dart-ship.png", &[0, 1, 2, 1])];
+
+/// Directories containing sounds to be included in the archive
+// TODO: Add Mindustry/core/assets/music/ once we figure out how to make it fit
+const SOUND_IN_DIRS: [&str; 1] = ["Mindustry/core/assets/sounds/"];
+/// Directory containing Mindustry's .msav maps
+const MAPS_IN_DIR: &str = "Mindustry/core/assets/maps/";
+/// Font used by the text engine
+const TTF_FONT_PATH: &str = "Px437_IBM_BIOS.ttf";
+/// Files ending with these suffixes are compressed.
+/// Palettes and sprite metadata are tiny and therefore left alone, map data is compressed when converting it.
+const COMPRESSED_SUFFIXES: [&str; 1] = ["Tiles"];
+
+/// Path to the final archive
+const OUT_PATH: &str = "assets.gbfs";
+/// Path to the generated asset manifest, which the game uses to refer to assets
+const MANIFEST_PATH: &str = "src/assets/manifest.rs";
+
+/// The files which end up in the archive.
+#[derive(Debug, Default)]
+pub struct ArchiveContents {
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl ArchiveContents {
+    /// Adds a file to the archive.
+    /// Fails if a file with the same name already exists or the name doesn't fit into GBFS.
+    pub fn add(&mut self, name: String, data: Vec<u8>) -> Result<(), Box<dyn Error>> {
+        if name.len() > MAX_NAME_LEN {
+            return Err(gbfs::GbfsError::NameTooLong(name).into());
+        }
+        if self.files.contains_key(&name) {
+            return Err(format!("Multiple assets are named {}", name).into());
+        }
+        self.files.insert(name, data);
+        return Ok(());
+    }
+
+    /// Compresses the files whose names end with one of the suffixes, if that saves space.
+    pub fn compress(&mut self, suffixes: &[&str]) {
+        for (name, data) in self.files.iter_mut() {
+            if suffixes.iter().any(|suffix| name.ends_with(suffix))
+                && !compression::is_compressed(data)
+            {
+                let compressed = compression::compress(data);
+                println!(
+                    "[COMPRESS] {}: {} -> {} bytes",
+                    name,
+                    data.len(),
+                    compressed.len()
+                );
+                *data = compressed;
+            }
+        }
+    }
+
+    /// Returns the files sorted by name.
+    pub fn files(&self) -> impl Iterator<Item = (&String, &Vec<u8>)> {
+        return self.files.iter();
+    }
+
+    /// Packs the files into a GBFS archive.
+    pub fn to_archive(&self) -> Result<Vec<u8>, gbfs::GbfsError> {
+        let files: Vec<GbfsFile> = self
+            .files
+            .iter()
+            .map(|(name, data)| GbfsFile {
+                name: name.clone(),
+                data,
+            })
+            .collect();
+        return gbfs::write(&files);
+    }
+}
+
+/// Runs the entire pipeline, writing the archive and the manifest.
+pub fn run() -> Result<(), Box<dyn Error>> {
+    let mut contents = ArchiveContents::default();
+    add_font(&mut contents, Path::new(TTF_FONT_PATH))?;
+    let sound_dirs: Vec<PathBuf> = SOUND_IN_DIRS.iter().map(PathBuf::from).collect();
+    add_sounds(&mut contents, &sound_dirs)?;
+    add_sprites(&mut contents)?;
+    let map_paths = find_files(Path::new(MAPS_IN_DIR), &[], |path| {
+        path.extension().is_some_and(|ext| ext == "msav")
+    })?;
+    convert_maps(Path::new(SPRITES_IN_DIRS[0]), &map_paths, &mut contents)?;
+    contents.compress(&COMPRESSED_SUFFIXES);
+
+    let archive = contents.to_archive()?;
+    fs::write(OUT_PATH, &archive)?;
+    println!(
+        "[ARCHIVE] Wrote {} files to {}",
+        contents.files.len(),
+        OUT_PATH
+    );
+    fs::create_dir_all(Path::new(MANIFEST_PATH).parent().unwrap())?;
+    fs::write(MANIFEST_PATH, manifest::generate(&gbfs::read(&archive)?)?)?;
+    println!("[MANIFEST] Wrote {}", MANIFEST_PATH);
+    return Ok(());
+}
+
+/// Converts all given maps and the buildings which can be placed on them,
+/// adding their files, `maps.json` and `buildings.json`.
+/// Map data is compressed where that saves space.
+pub fn convert_maps(
+    sprite_dir: &Path,
+    paths: &[PathBuf],
+    contents: &mut ArchiveContents,
+) -> Result<(), Box<dyn Error>> {
+    let mut sprites = sprites::SpriteLibrary::from_dir(sprite_dir)?;
+    let mut maps = map::Maps::default();
+
+    for path in paths {
+        let save =
+            msav::decode(&fs::read(path)?).map_err(|err| format!("{}: {}", path.display(), err))?;
+        println!(
+            "[MAP] Converting {} (save version {})",
+            path.display(),
+            save.version
+        );
+        let fallback_name = path.file_stem().unwrap().to_string_lossy();
+        let converted = map::convert(&save, &fallback_name, &mut sprites)?;
+        for (name, data) in &converted.files {
+            contents
+                .add(name.clone(), compression::compress(data))
+                .map_err(|err| format!("Map {}: {}", converted.entry.name, err))?;
+        }
+        maps.maps.push(converted.entry);
+    }
+
+    contents.add("maps.json".to_string(), serde_json::to_vec(&maps)?)?;
+
+    let converted = buildings::convert(&mut sprites)?;
+    for (name, data) in converted.files {
+        contents.add(name, compression::compress(&data))?;
+    }
+    contents.add(
+        "buildings.json".to_string(),
+        serde_json::to_vec(&converted.buildings)?,
+    )?;
+    return Ok(());
+}
+
+/// Adds the text engine's font.
+fn add_font(contents: &mut ArchiveContents, ttf_path: &Path) -> Result<(), Box<dyn Error>> {
+    let font = font::convert(&fs::read(ttf_path)?)?;
+    println!(
+        "[FONT] Converted {} characters from {}",
+        font.chars.chars().count(),
+        ttf_path.display()
+    );
+    contents.add("font_chars.txt".to_string(), font.chars.into_bytes())?;
+    contents.add("fontTiles".to_string(), font.tiles)?;
+    contents.add("font_sharedPal".to_string(), font.palette)?;
+    return Ok(());
+}
+
+/// Adds all Ogg Vorbis and WAV sounds in the given directories, converted into the format the mixer plays.
+fn add_sounds(contents: &mut ArchiveContents, dirs: &[PathBuf]) -> Result<(), Box<dyn Error>> {
+    for dir in dirs {
+        let paths = find_files(dir, &[], |path| {
+            path.extension()
+                .is_some_and(|ext| ext == "ogg" || ext == "wav")
+        })?;
+        for path in paths {
+            let data = fs::read(&path)?;
+            let audio = if path.extension().unwrap() == "ogg" {
+                sound::decode_ogg(&data)?
+            } else {
+                sound::decode_wav(&data)?
+            };
+            println!("[SOUND] Converting {}", path.display());
+            let name = format!("{}.wav", path.file_stem().unwrap().to_string_lossy());
+            contents.add(name, sound::to_gba_wav(&audio))?;
+        }
+    }
+    return Ok(());
+}
+
+/// Adds the sprites used by the game, together with their palettes and metadata.
+///
+/// Sprites with at most 15 colors are converted to 4bpp with a palette bank of their own,
+/// which halves the amount of VRAM they need. All others are 8bpp and share a palette.
+fn add_sprites(contents: &mut ArchiveContents) -> Result<(), Box<dyn Error>> {
+    let mut sprites = Vec::new();
+    for dir in &SPRITES_IN_DIRS {
+        let paths = find_files(Path::new(dir), &SPRITES_IGNORE_SUBDIRS, |path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            CURRENTLY_USED_SPRITES.contains(&name.as_ref())
+        })?;
+        for path in paths {
+            let mut img = Image::load_png(&path)?;
+            let resize = SPRITES_RESIZE_SUBDIRS
+                .iter()
+                .any(|subdir| path.starts_with(Path::new(SPRITES_IN_DIRS[0]).join(subdir)));
+            if resize {
+                img = img.scaled(img.width / 2, img.height / 2);
+            }
+            match sprite_tiles::pad_to_sprite_size(&img) {
+                Some(padded) => {
+                    let frames = sprite_frames(&path, &padded);
+                    sprites.push((path, frames));
+                }
+                None => eprintln!("[SPRITES] {} is too large, skipping", path.display()),
+            }
+        }
+    }
+
+    let shared_images: Vec<Image> = sprites
+        .iter()
+        .flat_map(|(_, frames)| frames)
+        .filter(|img| !sprite_tiles::fits_into_palbank(img))
+        .cloned()
+        .collect();
+    let shared_palette =
+        SharedPalette::with_max_colors(&shared_images, 8, SPRITE_PALETTE_MAX_COLORS);
+    contents.add(SPRITE_PALETTE_NAME.to_string(), shared_palette.to_bytes())?;
+    for (path, frames) in &sprites {
+        println!("[SPRITES] Converting {}", path.display());
+        let tiles_name = sprite_tiles_name(path);
+        // Frames only differ in where the sprite is, so they all have the same colors
+        let own_palette = if sprite_tiles::fits_into_palbank(&frames[0]) {
+            Some(SharedPalette::for_images(frames, 4))
+        } else {
+            None
+        };
+        let meta = SpriteMeta {
+            width: frames[0].width.try_into()?,
+            height: frames[0].height.try_into()?,
+            bpp: if own_palette.is_some() { 4 } else { 8 },
+            frames: frames.len().try_into()?,
+        };
+        contents.add(
+            sprite_meta::meta_name(&tiles_name).unwrap(),
+            meta.to_bytes().to_vec(),
+        )?;
+        if let Some(palette) = &own_palette {
+            // The game loads whole palette banks
+            let mut palette_bytes = palette.to_bytes();
+            palette_bytes.resize(16 * 2, 0);
+            contents.add(
+                sprite_meta::palette_name(&tiles_name).unwrap(),
+                palette_bytes,
+            )?;
+        }
+        let palette = own_palette.as_ref().unwrap_or(&shared_palette);
+        // Frames are stored one after another
+        let tiles = frames.iter().flat_map(|img| palette.tiles(img)).collect();
+        contents.add(tiles_name, tiles)?;
+    }
+    return Ok(());
+}
+
+/// Returns the frames of the given sprite, which has been padded to a hardware sprite size.
+/// Sprites which aren't animated consist of a single frame.
+fn sprite_frames(path: &Path, img: &Image) -> Vec<Image> {
+    let name = path.file_name().unwrap().to_string_lossy();
+    let offsets = match ANIMATED_SPRITES.iter().find(|(sprite, _)| *sprite == name) {
+        Some((_, offsets)) => offsets,
+        None => return vec![img.clone()],
+    };
+    return offsets
+        .iter()
+        .map(|dy| {
+            // Anything moved past the bottom edge is cut off
+            let mut frame = Image::new(img.width, img.height);
+            frame.draw(img, 0, *dy);
+            frame
+        })
+        .collect();
+}
+
+/// Returns the name of the file containing the tiles of the given sprite,
+/// which is the file name with everything except letters and digits replaced by underscores.
+fn sprite_tiles_name(path: &Path) -> String {
+    let stem: String = path
+        .file_stem()
+        .unwrap()
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    return format!("{}{}", stem, sprite_meta::TILES_SUFFIX);
+}
+
+/// Recursively finds all files below the directory for which the filter returns true, in sorted order.
+/// Subdirectories with one of the ignored names are skipped.
+fn find_files(
+    dir: &Path,
+    ignored_subdirs: &[&str],
+    filter: impl Fn(&Path) -> bool,
+) -> io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                let name = path.file_name().unwrap().to_string_lossy();
+                if !ignored_subdirs.contains(&name.as_ref()) {
+                    dirs.push(path);
+                }
+            } else if filter(&path) {
+                found.push(path);
+            }
+        }
+    }
+    found.sort();
+    return Ok(found);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_archive_deterministically() {
+        let mut a = ArchiveContents::default();
+        a.add("b".to_string(), vec![2; 100]).unwrap();
+        a.add("aTiles".to_string(), vec![0; 100]).unwrap();
+        assert!(a.add("b".to_string(), vec![]).is_err());
+        assert!(a.add("x".repeat(MAX_NAME_LEN + 1), vec![]).is_err());
+        a.compress(&["Tiles"]);
+
+        let mut b = ArchiveContents::default();
+        b.add("aTiles".to_string(), vec![0; 100]).unwrap();
+        b.add("b".to_string(), vec![2; 100]).unwrap();
+        b.compress(&["Tiles"]);
+        assert_eq!(a.to_archive().unwrap(), b.to_archive().unwrap());
+
+        let archive = a.to_archive().unwrap();
+        let files = gbfs::read(&archive).unwrap();
+        assert!(compression::is_compressed(files[0].data));
+        assert_eq!(compression::decompressed_len(files[0].data), 100);
+        assert_eq!(files[1].data, &[2; 100][..]);
+    }
+
+    #[test]
+    fn names_sprite_tiles() {
+        assert_eq!(
+            sprite_tiles_name(Path::new("blocks/item-blast-compound.png")),
+            "item_blast_compoundTiles"
+        );
+    }
+
+    #[test]
+    fn moves_animated_sprite_frames() {
+        let img = Image::filled(8, 8, [1, 2, 3, 255]);
+        let frames = sprite_frames(Path::new("a/copper-wall.png"), &img);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0], img);
+
+        let frames = sprite_frames(Path::new("a/dart-ship.png"), &img);
+        assert_eq!(frames.len(), ANIMATED_SPRITES[0].1.len());
+        assert_eq!(frames[2].get(0, 1)[3], 0);
+        assert_eq!(frames[2].get(0, 2), [1, 2, 3, 255]);
+    }
+
+    #[test]
+    fn finds_files() {
+        let dir = std::env::temp_dir().join(format!("asset-converter-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("keep")).unwrap();
+        fs::create_dir_all(dir.join("ui")).unwrap();
+        for file in &["b.png", "keep/a.png", "ui/c.png", "keep/d.txt"] {
+            fs::write(dir.join(file), []).unwrap();
+        }
+        let found = find_files(&dir, &["ui"], |path| {
+            path.extension().is_some_and(|ext| ext == "png")
+        })
+        .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(found, [dir.join("b.png"), dir.join("keep/a.png")]);
+    }
+}
@@ -0,0 +1,170 @@
+//! Conversion of sounds into the WAV files played by the game's mixer.
+//!
+//! The mixer only supports mono 8 bit PCM at a sample rate of 18157Hz, in a file with a plain 44 byte header.
+//! Because samples are read one word at a time, the sample data is padded to a multiple of 4 bytes.
+
+use std::io::Cursor;
+
+use lewton::inside_ogg::OggStreamReader;
+
+/// Sample rate expected by the mixer.
+pub const SAMPLE_RATE: u32 = 18157;
+/// Size of the WAV header written by `to_gba_wav`.
+const WAV_HEADER_LEN: usize = 44;
+/// Value of an 8 bit sample representing silence.
+const SILENCE: u8 = 0x80;
+
+/// Decoded audio with samples between -1 and 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Audio {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Samples of all channels, interleaved
+    pub samples: Vec<f32>,
+}
+
+/// Decodes an Ogg Vorbis file.
+pub fn decode_ogg(data: &[u8]) -> Result<Audio, lewton::VorbisError> {
+    let mut reader = OggStreamReader::new(Cursor::new(data))?;
+    let mut samples = Vec::new();
+    while let Some(packet) = reader.read_dec_packet_itl()? {
+        samples.extend(packet.iter().map(|s| *s as f32 / 32768.0));
+    }
+    return Ok(Audio {
+        sample_rate: reader.ident_hdr.audio_sample_rate,
+        channels: reader.ident_hdr.audio_channels as u16,
+        samples,
+    });
+}
+
+/// Decodes a WAV file.
+pub fn decode_wav(data: &[u8]) -> Result<Audio, hound::Error> {
+    let mut reader = hound::WavReader::new(Cursor::new(data))?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let max = (1u32 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / max))
+                .collect::<Result<_, _>>()?
+        }
+    };
+    return Ok(Audio {
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+        samples,
+    });
+}
+
+/// Mixes the audio down to mono and resamples it to the mixer's sample rate.
+pub fn to_gba_samples(audio: &Audio) -> Vec<u8> {
+    let channels = audio.channels.max(1) as usize;
+    let mono: Vec<f32> = audio
+        .samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+    if mono.is_empty() {
+        return Vec::new();
+    }
+
+    // Linear interpolation is good enough for the GBA's speaker
+    let len = (mono.len() as u64 * SAMPLE_RATE as u64 / audio.sample_rate as u64) as usize;
+    let step = audio.sample_rate as f64 / SAMPLE_RATE as f64;
+    return (0..len)
+        .map(|i| {
+            let pos = i as f64 * step;
+            let idx = pos as usize;
+            let next = mono[(idx + 1).min(mono.len() - 1)];
+            let frac = (pos - idx as f64) as f32;
+            let sample = mono[idx] * (1.0 - frac) + next * frac;
+            return ((sample * 127.0).round() as i32 + SILENCE as i32).clamp(0, 255) as u8;
+        })
+        .collect();
+}
+
+/// Converts the audio into a WAV file the mixer can play.
+pub fn to_gba_wav(audio: &Audio) -> Vec<u8> {
+    let mut samples = to_gba_samples(audio);
+    samples.resize(samples.len().div_ceil(4) * 4, SILENCE);
+
+    let mut wav = Vec::with_capacity(WAV_HEADER_LEN + samples.len());
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&((WAV_HEADER_LEN - 8 + samples.len()) as u32).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    // Size of the format chunk
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    // Channels
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    // Byte rate
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    // Block alignment
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    // Bits per sample
+    wav.extend_from_slice(&8u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&samples);
+    return wav;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_wav() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: SAMPLE_RATE * 2,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut input = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut input, spec).unwrap();
+        for i in 0..10 {
+            // Left and right cancel out in the first half
+            let left = if i < 5 { i16::MAX } else { 0 };
+            writer.write_sample(left).unwrap();
+            writer
+                .write_sample(if i < 5 { -i16::MAX } else { 0 })
+                .unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let audio = decode_wav(input.get_ref()).unwrap();
+        assert_eq!(audio.channels, 2);
+        assert_eq!(audio.samples.len(), 20);
+        assert_eq!(to_gba_samples(&audio), [SILENCE; 5]);
+
+        let wav = to_gba_wav(&audio);
+        assert_eq!(wav.len(), WAV_HEADER_LEN + 8);
+        // Read the header back the same way the game does
+        assert_eq!(u16::from_le_bytes([wav[20], wav[21]]), 1);
+        assert_eq!(u16::from_le_bytes([wav[22], wav[23]]), 1);
+        assert_eq!(
+            u32::from_le_bytes([wav[24], wav[25], wav[26], wav[27]]),
+            SAMPLE_RATE
+        );
+        assert_eq!(u16::from_le_bytes([wav[34], wav[35]]), 8);
+        assert_eq!(wav[WAV_HEADER_LEN..], [SILENCE; 8]);
+        let reread = decode_wav(&wav).unwrap();
+        assert_eq!(reread.sample_rate, SAMPLE_RATE);
+        assert_eq!(reread.channels, 1);
+    }
+
+    #[test]
+    fn resamples() {
+        let audio = Audio {
+            sample_rate: SAMPLE_RATE * 2,
+            channels: 1,
+            samples: vec![0.0, 0.5, 1.0, 0.5, -1.0, 0.0],
+        };
+        assert_eq!(to_gba_samples(&audio), [128, 255, 1]);
+    }
+}
@@ -0,0 +1,123 @@
+//! Sprite metadata, stored in GBFS as a sidecar next to each sprite's tiles.
+//!
+//! For a sprite whose tiles are stored in `{name}Tiles`, the metadata is stored in `{name}Meta`.
+//! It's 6 bytes long and consists of the little-endian `u16` width and height of a single frame in pixels,
+//! followed by the bits per pixel and the number of frames as `u8`s.
+//! Frames are stored one after another in the tile file.
+//!
+//! 4bpp sprites come with their own palette bank of 16 colors, which is stored in `{name}Pal`.
+
+use std::convert::TryInto;
+use std::fmt;
+
+/// Suffix of files containing sprite tiles.
+pub const TILES_SUFFIX: &str = "Tiles";
+/// Suffix of files containing sprite metadata.
+pub const META_SUFFIX: &str = "Meta";
+/// Suffix of files containing the palette bank of a 4bpp sprite.
+pub const PALETTE_SUFFIX: &str = "Pal";
+/// Size of the metadata in bytes.
+pub const META_LEN: usize = 6;
+
+/// Things that may go wrong while parsing metadata.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpriteMetaError {
+    /// The metadata doesn't have the expected length.
+    BadLength(usize),
+}
+
+impl fmt::Display for SpriteMetaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpriteMetaError::BadLength(len) => write!(
+                f,
+                "SpriteMetaError: Metadata should be {} bytes, but is {} bytes",
+                META_LEN, len
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SpriteMetaError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteMeta {
+    /// Width of a frame in pixels
+    pub width: u16,
+    /// Height of a frame in pixels
+    pub height: u16,
+    /// Bits per pixel, either 4 or 8
+    pub bpp: u8,
+    pub frames: u8,
+}
+
+impl SpriteMeta {
+    pub fn to_bytes(self) -> [u8; META_LEN] {
+        let mut bytes = [0; META_LEN];
+        bytes[0..2].copy_from_slice(&self.width.to_le_bytes());
+        bytes[2..4].copy_from_slice(&self.height.to_le_bytes());
+        bytes[4] = self.bpp;
+        bytes[5] = self.frames;
+        return bytes;
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<SpriteMeta, SpriteMetaError> {
+        if bytes.len() != META_LEN {
+            return Err(SpriteMetaError::BadLength(bytes.len()));
+        }
+        return Ok(SpriteMeta {
+            width: u16::from_le_bytes(bytes[0..2].try_into().unwrap()),
+            height: u16::from_le_bytes(bytes[2..4].try_into().unwrap()),
+            bpp: bytes[4],
+            frames: bytes[5],
+        });
+    }
+
+    /// Length of the sprite's tile data with all frames, in bytes.
+    pub fn data_len(&self) -> usize {
+        return self.width as usize * self.height as usize * self.bpp as usize / 8
+            * self.frames as usize;
+    }
+}
+
+/// Returns the name of the metadata file belonging to the given tile file,
+/// or `None` if the file isn't named like a sprite's tiles.
+pub fn meta_name(tiles_name: &str) -> Option<String> {
+    return tiles_name
+        .strip_suffix(TILES_SUFFIX)
+        .map(|base| format!("{}{}", base, META_SUFFIX));
+}
+
+/// Returns the name of the palette file belonging to the given tile file,
+/// or `None` if the file isn't named like a sprite's tiles.
+pub fn palette_name(tiles_name: &str) -> Option<String> {
+    return tiles_name
+        .strip_suffix(TILES_SUFFIX)
+        .map(|base| format!("{}{}", base, PALETTE_SUFFIX));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrips_metadata() {
+        let meta = SpriteMeta {
+            width: 32,
+            height: 16,
+            bpp: 8,
+            frames: 3,
+        };
+        assert_eq!(meta.to_bytes(), [32, 0, 16, 0, 8, 3]);
+        assert_eq!(SpriteMeta::from_bytes(&meta.to_bytes()), Ok(meta));
+        assert_eq!(meta.data_len(), 32 * 16 * 3);
+        assert_eq!(
+            SpriteMeta::from_bytes(&[1, 2]),
+            Err(SpriteMetaError::BadLength(2))
+        );
+        assert_eq!(meta_name("dart_shipTiles").unwrap(), "dart_shipMeta");
+        assert_eq!(meta_name("maps.json"), None);
+        assert_eq!(palette_name("dart_shipTiles").unwrap(), "dart_shipPal");
+        assert_eq!(palette_name("maps.json"), None);
+    }
+}
@@ -0,0 +1,216 @@
+//! Conversion of sprites into object tiles which share a single palette.
+//!
+//! Unlike map tiles (see `tiles`), all sprites of a scene share one palette with up to 255 colors (8bpp)
+//! or 15 colors (4bpp), plus the transparent color at index 0.
+//! If the sprites use more colors than that, the most similar ones are merged.
+//! A sprite with few enough colors can also get a 4bpp palette all to itself.
+//! Tiles are stored in the order expected by 1D object mapping, left-to-right and top-to-bottom.
+
+use crate::image::{to_gba_color, Image};
+use crate::tiles::{reduce_colors, TILE_SIZE};
+
+use std::collections::{BTreeSet, HashMap};
+
+/// Sizes supported by hardware sprites, from smallest to largest.
+const SPRITE_SIZES: [(usize, usize); 12] = [
+    (8, 8),
+    (8, 16),
+    (16, 8),
+    (16, 16),
+    (32, 8),
+    (8, 32),
+    (16, 32),
+    (32, 16),
+    (32, 32),
+    (64, 32),
+    (32, 64),
+    (64, 64),
+];
+
+/// Returns the smallest hardware sprite size the image fits into, if any.
+pub fn nearest_sprite_size(width: usize, height: usize) -> Option<(usize, usize)> {
+    return SPRITE_SIZES
+        .iter()
+        .find(|(w, h)| width <= *w && height <= *h)
+        .copied();
+}
+
+/// Pads the image with transparent pixels to the nearest hardware sprite size.
+/// The original image ends up in the top-left corner, because that's what sprite coordinates refer to.
+///
+/// Returns `None` if the image is too large for a hardware sprite.
+pub fn pad_to_sprite_size(img: &Image) -> Option<Image> {
+    let (width, height) = nearest_sprite_size(img.width, img.height)?;
+    return Some(img.crop(0, 0, width, height));
+}
+
+/// Returns whether the image has few enough colors to get a 4bpp palette bank of its own
+/// without merging any of them.
+pub fn fits_into_palbank(img: &Image) -> bool {
+    return unique_colors(std::slice::from_ref(img)).len() < 16;
+}
+
+/// Returns every non-transparent color the images use.
+fn unique_colors(images: &[Image]) -> BTreeSet<u16> {
+    return images
+        .iter()
+        .flat_map(|img| {
+            (0..img.height).flat_map(move |y| (0..img.width).map(move |x| img.get(x, y)))
+        })
+        .filter_map(to_gba_color)
+        .collect();
+}
+
+/// A palette shared by a set of sprites.
+#[derive(Debug, Clone)]
+pub struct SharedPalette {
+    bpp: u8,
+    /// Colors excluding the transparent one, which is always at index 0.
+    colors: Vec<u16>,
+    /// Maps every color used by the sprites to its palette index.
+    indices: HashMap<u16, u8>,
+}
+
+impl SharedPalette {
+    /// Builds a palette containing the colors of all given images.
+    /// `bpp` is either 4 or 8.
+    pub fn for_images(images: &[Image], bpp: u8) -> SharedPalette {
+        return SharedPalette::with_max_colors(images, bpp, (1 << bpp) - 1);
+    }
+
+    /// Builds a palette containing the colors of all given images, merging colors until
+    /// at most `max_colors` remain, not counting the transparent one.
+    /// `bpp` is either 4 or 8, and `max_colors` must fit into a palette with that many bits per pixel.
+    pub fn with_max_colors(images: &[Image], bpp: u8, max_colors: usize) -> SharedPalette {
+        assert!(bpp == 4 || bpp == 8, "Unsupported bpp {}", bpp);
+        assert!(max_colors < 1 << bpp, "Too many colors for {}bpp", bpp);
+        let unique = unique_colors(images);
+        let replacements = reduce_colors(&unique, max_colors);
+        let colors: Vec<u16> = unique
+            .iter()
+            .filter(|c| !replacements.contains_key(c))
+            .copied()
+            .collect();
+        let mut indices: HashMap<u16, u8> = colors
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (*c, (i + 1) as u8))
+            .collect();
+        for (replaced, kept) in &replacements {
+            indices.insert(*replaced, indices[kept]);
+        }
+        return SharedPalette {
+            bpp,
+            colors,
+            indices,
+        };
+    }
+
+    /// Returns the palette file, consisting of little-endian colors starting with the transparent one.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        return std::iter::once(0)
+            .chain(self.colors.iter().copied())
+            .flat_map(u16::to_le_bytes)
+            .collect();
+    }
+
+    /// Converts the image into tiles using this palette.
+    ///
+    /// The image's dimensions must be multiples of the tile size,
+    /// and it may only use colors which were present when the palette was built.
+    pub fn tiles(&self, img: &Image) -> Vec<u8> {
+        assert!(img.width.is_multiple_of(TILE_SIZE) && img.height.is_multiple_of(TILE_SIZE));
+        let mut data = Vec::with_capacity(img.width * img.height * self.bpp as usize / 8);
+        for tile_y in (0..img.height).step_by(TILE_SIZE) {
+            for tile_x in (0..img.width).step_by(TILE_SIZE) {
+                for y in tile_y..tile_y + TILE_SIZE {
+                    for x in tile_x..tile_x + TILE_SIZE {
+                        let idx = to_gba_color(img.get(x, y)).map_or(0, |c| self.indices[&c]);
+                        if self.bpp == 8 || x % 2 == 0 {
+                            data.push(idx);
+                        } else {
+                            // With 4bpp, the leftmost pixel is in the low nibble
+                            *data.last_mut().unwrap() |= idx << 4;
+                        }
+                    }
+                }
+            }
+        }
+        return data;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    #[test]
+    fn pads_sprites() {
+        assert_eq!(nearest_sprite_size(8, 8), Some((8, 8)));
+        assert_eq!(nearest_sprite_size(9, 7), Some((16, 8)));
+        assert_eq!(nearest_sprite_size(40, 20), Some((64, 32)));
+        assert_eq!(nearest_sprite_size(65, 1), None);
+
+        let padded = pad_to_sprite_size(&Image::filled(12, 12, RED)).unwrap();
+        assert_eq!((padded.width, padded.height), (16, 16));
+        assert_eq!(padded.get(11, 11), RED);
+        assert!(to_gba_color(padded.get(12, 12)).is_none());
+    }
+
+    #[test]
+    fn converts_tiles() {
+        let mut img = Image::filled(16, 8, RED);
+        img.set(1, 0, BLUE);
+        img.set(8, 0, [0, 0, 0, 0]);
+        let palette = SharedPalette::for_images(&[img.clone()], 8);
+        assert_eq!(palette.to_bytes(), [0, 0, 0x1F, 0, 0, 0x7C]);
+
+        let tiles = palette.tiles(&img);
+        assert_eq!(tiles.len(), 2 * 64);
+        assert_eq!(tiles[..3], [1, 2, 1]);
+        // The second tile starts with the transparent pixel
+        assert_eq!(tiles[64..66], [0, 1]);
+
+        let palette = SharedPalette::for_images(&[img.clone()], 4);
+        let tiles = palette.tiles(&img);
+        assert_eq!(tiles.len(), 2 * 32);
+        assert_eq!(tiles[0], 0x21);
+        assert_eq!(tiles[32], 0x10);
+    }
+
+    #[test]
+    fn checks_palbank_fit() {
+        let mut img = Image::new(8, 8);
+        for i in 0..15 {
+            img.set(i % 8, i / 8, [(i * 16) as u8, 0, 0, 255]);
+        }
+        assert!(fits_into_palbank(&img));
+        img.set(7, 7, [0, 255, 0, 255]);
+        assert!(!fits_into_palbank(&img));
+    }
+
+    #[test]
+    fn limits_colors() {
+        let mut img = Image::new(8, 8);
+        for i in 0..64 {
+            img.set(i % 8, i / 8, [(i * 4) as u8, 0, 0, 255]);
+        }
+        let palette = SharedPalette::with_max_colors(&[img.clone()], 8, 31);
+        assert_eq!(palette.to_bytes().len(), 32 * 2);
+        assert!(palette.tiles(&img).iter().all(|b| *b != 0 && *b < 32));
+    }
+
+    #[test]
+    fn merges_colors() {
+        let mut img = Image::new(8, 8);
+        for i in 0..64 {
+            img.set(i % 8, i / 8, [(i * 4) as u8, 0, 0, 255]);
+        }
+        let palette = SharedPalette::for_images(&[img.clone()], 4);
+        assert_eq!(palette.to_bytes().len(), 16 * 2);
+        assert!(palette.tiles(&img).iter().all(|b| b & 0xF != 0));
+    }
+}
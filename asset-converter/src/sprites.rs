@@ -0,0 +1,84 @@
+//! Lookup of Mindustry's block sprites by content name.
+
+use crate::content;
+use crate::image::Image;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Size of a map tile's sprite in Mindustry, in pixels.
+pub const MINDUSTRY_TILE_SIZE: usize = 32;
+
+/// A collection of sprites, found by file name.
+pub struct SpriteLibrary {
+    paths: HashMap<String, PathBuf>,
+    cache: HashMap<String, Option<Image>>,
+}
+
+impl SpriteLibrary {
+    /// Indexes all PNG files below the given directory.
+    pub fn from_dir(dir: &Path) -> io::Result<SpriteLibrary> {
+        let mut paths = HashMap::new();
+        let mut dirs = vec![dir.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            let mut entries: Vec<PathBuf> = fs::read_dir(&dir)?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<io::Result<_>>()?;
+            // Make the result independent of directory iteration order if names collide
+            entries.sort();
+            for path in entries {
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().is_some_and(|ext| ext == "png") {
+                    let stem = path.file_stem().unwrap().to_string_lossy().into_owned();
+                    paths.entry(stem).or_insert(path);
+                }
+            }
+        }
+        return Ok(SpriteLibrary {
+            paths,
+            cache: HashMap::new(),
+        });
+    }
+
+    /// Creates a library from images which are already in memory.
+    #[cfg(test)]
+    pub fn from_images(images: Vec<(&str, Image)>) -> SpriteLibrary {
+        return SpriteLibrary {
+            paths: HashMap::new(),
+            cache: images
+                .into_iter()
+                .map(|(name, img)| (name.to_string(), Some(img)))
+                .collect(),
+        };
+    }
+
+    /// Returns the sprite of a block, if one exists.
+    pub fn block_sprite(&mut self, name: &str) -> Option<Image> {
+        for candidate in content::sprite_candidates(name) {
+            if let Some(img) = self.load(&candidate) {
+                return Some(img);
+            }
+        }
+        return None;
+    }
+
+    fn load(&mut self, stem: &str) -> Option<Image> {
+        if !self.cache.contains_key(stem) {
+            let img = self
+                .paths
+                .get(stem)
+                .and_then(|path| match Image::load_png(path) {
+                    Ok(img) => Some(img),
+                    Err(err) => {
+                        eprintln!("[SPRITES] Failed to load {}: {}", path.display(), err);
+                        None
+                    }
+                });
+            self.cache.insert(stem.to_string(), img);
+        }
+        return self.cache[stem].clone();
+    }
+}
@@ -0,0 +1,373 @@
+//! Conversion of images into 4bpp background tiles which share a set of 16 color palette banks.
+//!
+//! Each 8x8 tile may only use the colors of a single palette bank, so colors are assigned to banks
+//! greedily in the order tiles are added.
+//! Identical tiles are only stored once.
+
+use crate::image::{to_gba_color, Image};
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+/// Edge length of a tile in pixels.
+pub const TILE_SIZE: usize = 8;
+/// Size of a single 4bpp tile in bytes.
+pub const TILE_SIZE_BYTES_4BPP: usize = TILE_SIZE * TILE_SIZE / 2;
+/// Number of colors in a palette bank, including the transparent color at index 0.
+pub const COLORS_PER_BANK: usize = 16;
+/// The screen entry of the fully transparent tile, which is always tile 0.
+pub const BLANK_ENTRY: u16 = 0;
+
+type TileData = [u8; TILE_SIZE_BYTES_4BPP];
+/// Colors of a tile's pixels in left-to-right, top-to-bottom order, `None` being transparent.
+type TilePixels = [Option<u16>; TILE_SIZE * TILE_SIZE];
+
+/// Things that may go wrong while building a tileset.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TileError {
+    /// The tileset would need more tiles than fit into the available space.
+    TooManyTiles(usize),
+}
+
+impl fmt::Display for TileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TileError::TooManyTiles(max) => {
+                write!(f, "TileError: More than {} unique tiles required", max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TileError {}
+
+/// A set of unique 4bpp tiles and the palette banks they use.
+#[derive(Debug, Clone)]
+pub struct Tileset {
+    max_banks: usize,
+    max_tiles: usize,
+    /// Colors of each bank, excluding the transparent color.
+    banks: Vec<Vec<u16>>,
+    tiles: Vec<TileData>,
+    lookup: HashMap<(usize, TileData), u16>,
+}
+
+impl Tileset {
+    /// Creates a tileset which may use up to `max_banks` palette banks and `max_tiles` tiles.
+    /// Tile 0 is always the blank tile.
+    pub fn new(max_banks: usize, max_tiles: usize) -> Tileset {
+        return Tileset {
+            max_banks,
+            max_tiles,
+            banks: Vec::new(),
+            tiles: vec![[0; TILE_SIZE_BYTES_4BPP]],
+            lookup: HashMap::new(),
+        };
+    }
+
+    /// Adds the 8x8 area of the image with the top-left corner at (x, y) to the tileset.
+    ///
+    /// Returns the screen entry referring to the tile, consisting of its tile index and palette bank.
+    pub fn add_tile(&mut self, img: &Image, x: usize, y: usize) -> Result<u16, TileError> {
+        let pixels = read_pixels(img, x, y);
+        if pixels.iter().all(Option::is_none) {
+            return Ok(BLANK_ENTRY);
+        }
+
+        let (bank, replacements) = self.bank_for(pixels.iter().flatten().copied().collect());
+        let data = self.encode(bank, &pixels, &replacements);
+
+        if let Some(entry) = self.lookup.get(&(bank, data)) {
+            return Ok(*entry);
+        }
+        let entry = self.push_tile(bank, data)?;
+        self.lookup.insert((bank, data), entry);
+        return Ok(entry);
+    }
+
+    /// Adds a tile which is animated, with the 8x8 area at (x, y) of each of the images being one frame.
+    /// All frames share a palette bank, and the tile is never reused for other tiles,
+    /// so that its graphics can be swapped out at runtime.
+    ///
+    /// Returns the screen entry referring to the tile (which contains the first frame),
+    /// and the tile data of all frames in order.
+    /// If all frames are identical, the tile is added like any other and no frames are returned.
+    pub fn add_animated_tile(
+        &mut self,
+        frames: &[Image],
+        x: usize,
+        y: usize,
+    ) -> Result<(u16, Option<Vec<u8>>), TileError> {
+        let frame_pixels: Vec<_> = frames.iter().map(|img| read_pixels(img, x, y)).collect();
+        if frame_pixels.iter().all(|pixels| *pixels == frame_pixels[0]) {
+            return Ok((self.add_tile(&frames[0], x, y)?, None));
+        }
+
+        let colors = frame_pixels.iter().flatten().flatten().copied().collect();
+        let (bank, replacements) = self.bank_for(colors);
+        let data: Vec<TileData> = frame_pixels
+            .iter()
+            .map(|pixels| self.encode(bank, pixels, &replacements))
+            .collect();
+        let entry = self.push_tile(bank, data[0])?;
+        return Ok((entry, Some(data.iter().flatten().copied().collect())));
+    }
+
+    /// Picks the palette bank for a tile with the given colors, merging similar colors if the tile has
+    /// too many to fit into a bank.
+    ///
+    /// Returns the bank and which colors have to be replaced by which.
+    fn bank_for(&mut self, unique: BTreeSet<u16>) -> (usize, HashMap<u16, u16>) {
+        let replacements = reduce_colors(&unique, COLORS_PER_BANK - 1);
+        let colors: Vec<u16> = unique
+            .iter()
+            .filter(|c| !replacements.contains_key(c))
+            .copied()
+            .collect();
+        return (self.choose_bank(&colors), replacements);
+    }
+
+    /// Converts the pixels of a tile to 4bpp tile data using the given bank.
+    fn encode(
+        &self,
+        bank: usize,
+        pixels: &TilePixels,
+        replacements: &HashMap<u16, u16>,
+    ) -> TileData {
+        let mut data: TileData = [0; TILE_SIZE_BYTES_4BPP];
+        for (i, px) in pixels.iter().enumerate() {
+            let idx = match px {
+                Some(color) => {
+                    let color = *replacements.get(color).unwrap_or(color);
+                    nearest_color_index(&self.banks[bank], color) + 1
+                }
+                None => 0,
+            };
+            // Leftmost pixel is in the low nibble
+            data[i / 2] |= (idx as u8) << ((i % 2) * 4);
+        }
+        return data;
+    }
+
+    /// Appends the tile to the tileset, returning the screen entry referring to it.
+    fn push_tile(&mut self, bank: usize, data: TileData) -> Result<u16, TileError> {
+        if self.tiles.len() >= self.max_tiles {
+            return Err(TileError::TooManyTiles(self.max_tiles));
+        }
+        let entry = (self.tiles.len() as u16) | ((bank as u16) << 12);
+        self.tiles.push(data);
+        return Ok(entry);
+    }
+
+    /// Picks the palette bank for a tile with the given colors, adding the colors to it if needed.
+    fn choose_bank(&mut self, colors: &[u16]) -> usize {
+        // Prefer the bank which requires the fewest additional colors
+        let mut best: Option<(usize, usize)> = None;
+        for (i, bank) in self.banks.iter().enumerate() {
+            let missing = colors.iter().filter(|c| !bank.contains(c)).count();
+            if bank.len() + missing < COLORS_PER_BANK && best.is_none_or(|(m, _)| missing < m) {
+                best = Some((missing, i));
+            }
+        }
+        if let Some((_, i)) = best {
+            for color in colors {
+                if !self.banks[i].contains(color) {
+                    self.banks[i].push(*color);
+                }
+            }
+            return i;
+        }
+        if self.banks.len() < self.max_banks {
+            self.banks.push(colors.to_vec());
+            return self.banks.len() - 1;
+        }
+
+        // All banks are full, so settle for the one approximating the colors best
+        let error = |bank: &Vec<u16>| -> u32 {
+            colors
+                .iter()
+                .map(|c| color_distance(bank[nearest_color_index(bank, *c)], *c))
+                .sum()
+        };
+        return (0..self.banks.len())
+            .min_by_key(|i| error(&self.banks[*i]))
+            .unwrap();
+    }
+
+    /// Number of tiles, including the blank one.
+    pub fn num_tiles(&self) -> usize {
+        return self.tiles.len();
+    }
+
+    /// Returns the tile graphics in the format expected by VRAM.
+    pub fn tile_data(&self) -> Vec<u8> {
+        return self.tiles.iter().flat_map(|t| t.iter().copied()).collect();
+    }
+
+    /// Returns the palette, starting at bank 0.
+    /// The first color of each bank is unused, as it's transparent.
+    pub fn palette(&self) -> Vec<u16> {
+        let mut palette = Vec::with_capacity(self.banks.len() * COLORS_PER_BANK);
+        for bank in &self.banks {
+            palette.push(0);
+            palette.extend_from_slice(bank);
+            palette.resize(palette.len() + COLORS_PER_BANK - 1 - bank.len(), 0);
+        }
+        return palette;
+    }
+}
+
+/// Returns the colors of the 8x8 area of the image with the top-left corner at (x, y).
+fn read_pixels(img: &Image, x: usize, y: usize) -> TilePixels {
+    let mut pixels = [None; TILE_SIZE * TILE_SIZE];
+    for ty in 0..TILE_SIZE {
+        for tx in 0..TILE_SIZE {
+            pixels[tx + ty * TILE_SIZE] = to_gba_color(img.get(x + tx, y + ty));
+        }
+    }
+    return pixels;
+}
+
+/// Squared euclidean distance between two GBA colors.
+fn color_distance(a: u16, b: u16) -> u32 {
+    return (0..3)
+        .map(|channel| {
+            let ca = ((a >> (channel * 5)) & 0x1F) as i32;
+            let cb = ((b >> (channel * 5)) & 0x1F) as i32;
+            ((ca - cb) * (ca - cb)) as u32
+        })
+        .sum();
+}
+
+/// Returns the index of the color in the palette closest to the given color.
+fn nearest_color_index(palette: &[u16], color: u16) -> usize {
+    return (0..palette.len())
+        .min_by_key(|i| color_distance(palette[*i], color))
+        .unwrap();
+}
+
+/// Repeatedly merges the two most similar colors until at most `max` remain.
+///
+/// Returns which colors have to be replaced by which.
+pub fn reduce_colors(colors: &BTreeSet<u16>, max: usize) -> HashMap<u16, u16> {
+    let mut remaining: Vec<u16> = colors.iter().copied().collect();
+    let mut replacements: HashMap<u16, u16> = HashMap::new();
+    while remaining.len() > max {
+        let mut closest = (u32::MAX, 0, 0);
+        for i in 0..remaining.len() {
+            for j in (i + 1)..remaining.len() {
+                let distance = color_distance(remaining[i], remaining[j]);
+                if distance < closest.0 {
+                    closest = (distance, i, j);
+                }
+            }
+        }
+        let (_, keep, drop) = closest;
+        let (kept, dropped) = (remaining[keep], remaining.remove(drop));
+        for target in replacements.values_mut() {
+            if *target == dropped {
+                *target = kept;
+            }
+        }
+        replacements.insert(dropped, kept);
+    }
+    return replacements;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    #[test]
+    fn dedups_tiles() {
+        let mut img = Image::filled(16, 8, RED);
+        img.set(9, 0, BLUE);
+        let mut tileset = Tileset::new(15, 512);
+        let a = tileset.add_tile(&img, 0, 0).unwrap();
+        let b = tileset.add_tile(&img, 8, 0).unwrap();
+        let c = tileset.add_tile(&img, 0, 0).unwrap();
+        assert_eq!(a, 1);
+        assert_eq!(b, 2);
+        assert_eq!(a, c);
+        assert_eq!(tileset.num_tiles(), 3);
+        assert_eq!(tileset.palette()[..3], [0, 0x001F, 0x7C00]);
+        // Pixel (1, 0) of the second tile is blue, which is palette index 2
+        assert_eq!(tileset.tile_data()[2 * 32], 0x21);
+    }
+
+    #[test]
+    fn adds_animated_tiles() {
+        let mut tileset = Tileset::new(15, 512);
+        let red = Image::filled(8, 8, RED);
+        let blue = Image::filled(8, 8, BLUE);
+        let plain = tileset.add_tile(&red, 0, 0).unwrap();
+
+        let (entry, frames) = tileset
+            .add_animated_tile(&[red.clone(), blue.clone()], 0, 0)
+            .unwrap();
+        // Animated tiles are never shared, even if a frame is identical to another tile
+        assert_ne!(entry, plain);
+        let frames = frames.unwrap();
+        assert_eq!(frames.len(), 2 * TILE_SIZE_BYTES_4BPP);
+        assert_eq!(frames[0], 0x11);
+        assert_eq!(frames[TILE_SIZE_BYTES_4BPP], 0x22);
+        assert_eq!(
+            tileset.tile_data()[(entry & 0x3FF) as usize * TILE_SIZE_BYTES_4BPP],
+            0x11
+        );
+        assert_eq!(tileset.add_tile(&red, 0, 0), Ok(plain));
+
+        // Identical frames don't need to be animated
+        assert_eq!(
+            tileset.add_animated_tile(&[red.clone(), red], 0, 0),
+            Ok((plain, None))
+        );
+        assert_eq!(tileset.num_tiles(), 3);
+    }
+
+    #[test]
+    fn blank_tiles_map_to_zero() {
+        let mut tileset = Tileset::new(15, 512);
+        assert_eq!(tileset.add_tile(&Image::new(8, 8), 0, 0), Ok(BLANK_ENTRY));
+        assert_eq!(tileset.num_tiles(), 1);
+    }
+
+    #[test]
+    fn spreads_colors_over_banks() {
+        // 2 tiles with 15 distinct colors each can't share a bank
+        let mut img = Image::new(16, 8);
+        for i in 0..15 {
+            img.set(i % 8, i / 8, [(i * 8) as u8, 0, 0, 255]);
+            img.set(8 + i % 8, i / 8, [0, (i * 8) as u8, 0, 255]);
+        }
+        let mut tileset = Tileset::new(15, 512);
+        let a = tileset.add_tile(&img, 0, 0).unwrap();
+        let b = tileset.add_tile(&img, 8, 0).unwrap();
+        assert_eq!(a >> 12, 0);
+        assert_eq!(b >> 12, 1);
+        assert_eq!(tileset.palette().len(), 32);
+    }
+
+    #[test]
+    fn reduces_colors_and_limits_tiles() {
+        let mut img = Image::new(8, 8);
+        for i in 0..64 {
+            img.set(i % 8, i / 8, [(i * 4) as u8, 0, 0, 255]);
+        }
+        let mut tileset = Tileset::new(1, 2);
+        tileset.add_tile(&img, 0, 0).unwrap();
+        assert_eq!(tileset.palette().len(), COLORS_PER_BANK);
+
+        // The same colors in reverse order make for a different tile
+        for i in 0..64 {
+            img.set(i % 8, i / 8, [((63 - i) * 4) as u8, 0, 0, 255]);
+        }
+        assert_eq!(
+            tileset.add_tile(&img, 0, 0),
+            Err(TileError::TooManyTiles(2))
+        );
+    }
+}
@@ -4,14 +4,6 @@ let
   niv = import sources.niv { inherit sources; };
   nixpkgs = import sources.nixpkgs { };
   shellPackages = packageList.nixPackages ++ [
-    # Dev tools for python scripts
-    nixpkgs.python38Packages.black
-    nixpkgs.python38Packages.flake8
-    nixpkgs.python38Packages.pydocstyle
-    nixpkgs.python38Packages.mypy
-    nixpkgs.python38Packages.ipython
-    nixpkgs.python38Packages.pylint
-
     # For running tests headlessly
     nixpkgs.mgba
     nixpkgs.xvfb_run
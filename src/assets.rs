@@ -0,0 +1,78 @@
+//! This module provides typed handles for the files in the asset filesystem.
+//!
+//! The handles themselves are generated into `assets/manifest.rs` by `cargo make assets`
+//! (see `asset-converter manifest`), so referring to an asset which doesn't exist is a compile error
+//! instead of a panic at runtime. Metadata such as the size of a sprite is stored alongside the file name.
+//!
+//! Files may be compressed, so their contents should be accessed through `load` and `load_into_vram`
+//! rather than `data`.
+
+use crate::sprite::HWSpriteSize;
+use crate::FS;
+
+use alloc::borrow::Cow;
+
+use byte_slice_cast::FromByteSlice;
+
+pub mod compression;
+
+// Not every asset in the archive is used by the game
+#[allow(dead_code)]
+mod manifest;
+pub use manifest::{files, palettes, sprites};
+
+/// A file in the asset filesystem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Asset {
+    /// Name of the file in GBFS
+    pub name: &'static str,
+    /// Position of the file in the GBFS directory
+    pub gbfs_index: usize,
+}
+
+impl Asset {
+    /// Returns the contents of the file as stored in the filesystem, which may be compressed.
+    pub fn data(&self) -> &'static [u8] {
+        return FS
+            .get_file_data_by_name(self.name)
+            .expect("Asset missing from filesystem, manifest is out of date");
+    }
+
+    /// Returns the decompressed contents of the file as `T`s, see `compression::load`.
+    pub fn load<T: FromByteSlice + Clone + Default>(&self) -> Cow<'static, [T]> {
+        return compression::load(self.data());
+    }
+
+    /// Returns the length of the file's decompressed contents in bytes.
+    pub fn decompressed_len(&self) -> usize {
+        return compression::decompressed_len(self.data());
+    }
+
+    /// Writes the decompressed contents of the file to VRAM, see `compression::load_into_vram`.
+    ///
+    /// # Safety
+    ///
+    /// `dest` must point into VRAM and there must be room for `decompressed_len()` bytes.
+    pub unsafe fn load_into_vram(&self, dest: *mut u32) {
+        compression::load_into_vram(self.data(), dest);
+    }
+}
+
+/// A hardware sprite's tiles, together with the metadata required to display them.
+#[derive(Debug, Clone, Copy)]
+pub struct SpriteAsset {
+    pub asset: Asset,
+    /// The sprite's metadata file, see `sprite::SpriteMeta`
+    pub meta: Asset,
+    pub size: HWSpriteSize,
+    /// The sprite's own palette bank of 16 colors if it's 4bpp.
+    /// 8bpp sprites use the shared sprite palette instead.
+    pub palette: Option<Asset>,
+}
+
+impl SpriteAsset {
+    /// Returns the sprite's tile data.
+    pub fn tiles(&self) -> Cow<'static, [u32]> {
+        return self.asset.load();
+    }
+}
@@ -0,0 +1,169 @@
+//! Decompression of assets compressed by the asset pipeline (see `asset-converter compress`).
+//!
+//! Compressed files start with the magic bytes "GBAZ", followed by LZ77 or RLE compressed data
+//! in the format expected by the BIOS, which does the actual decompression.
+//! Files without the magic are stored as-is, so every function here also accepts uncompressed files.
+
+use crate::interrupt;
+
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+
+use byte_slice_cast::{AsSliceOf, FromByteSlice};
+use gba::io::dma;
+
+use core::convert::TryInto;
+use core::mem;
+
+/// Magic bytes at the start of every compressed file.
+const MAGIC: &[u8; 4] = b"GBAZ";
+/// Size of the magic and the BIOS header in bytes.
+const HEADER_LEN: usize = 8;
+
+/// How a file is stored in the asset filesystem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Uncompressed,
+    Lz77,
+    Rle,
+}
+
+/// Returns how the given file contents are compressed.
+pub fn compression_of(data: &[u8]) -> Compression {
+    if data.len() < HEADER_LEN || !data.starts_with(MAGIC) {
+        return Compression::Uncompressed;
+    }
+    // The BIOS header stores the type in the upper nibble of the first byte
+    match data[MAGIC.len()] {
+        0x10 => return Compression::Lz77,
+        0x30 => return Compression::Rle,
+        other => panic!("Asset has unsupported compression type {:#x}", other),
+    }
+}
+
+/// Returns the length of the given file contents after decompression, in bytes.
+pub fn decompressed_len(data: &[u8]) -> usize {
+    if compression_of(data) == Compression::Uncompressed {
+        return data.len();
+    }
+    let header = u32::from_le_bytes(data[MAGIC.len()..HEADER_LEN].try_into().unwrap());
+    return (header >> 8) as usize;
+}
+
+/// Returns the decompressed file contents as `T`s.
+///
+/// Uncompressed files are borrowed straight from ROM, compressed ones are decompressed into EWRAM.
+/// Panics if the contents aren't made up of properly aligned `T`s.
+pub fn load<T: FromByteSlice + Clone + Default>(data: &'static [u8]) -> Cow<'static, [T]> {
+    let compression = compression_of(data);
+    if compression == Compression::Uncompressed {
+        return Cow::Borrowed(
+            data.as_slice_of::<T>()
+                .expect("Asset has wrong length or alignment for requested type"),
+        );
+    }
+    let len = decompressed_len(data);
+    assert_eq!(
+        len % mem::size_of::<T>(),
+        0,
+        "Decompressed asset has wrong length for requested type"
+    );
+    let mut buf: Vec<T> = vec![T::default(); len / mem::size_of::<T>()];
+    let src = bios_stream_ptr(data);
+    // The WRAM variants write bytewise, so any destination is fine
+    unsafe {
+        match compression {
+            Compression::Lz77 => lz77_uncomp_wram(src, buf.as_mut_ptr() as *mut u8),
+            Compression::Rle => rl_uncomp_wram(src, buf.as_mut_ptr() as *mut u8),
+            Compression::Uncompressed => unreachable!(),
+        }
+    }
+    return Cow::Owned(buf);
+}
+
+/// Writes the decompressed file contents to VRAM.
+///
+/// Uncompressed files are copied using DMA, compressed ones are decompressed by the BIOS directly into VRAM.
+///
+/// # Safety
+///
+/// `dest` must point into VRAM and there must be room for `decompressed_len(data)` bytes.
+pub unsafe fn load_into_vram(data: &'static [u8], dest: *mut u32) {
+    match compression_of(data) {
+        Compression::Uncompressed => {
+            let words = data
+                .as_slice_of::<u32>()
+                .expect("Asset has wrong length or alignment for DMA");
+            // The vblank handler uses DMA3 as well, so it mustn't interrupt setting it up
+            interrupt::without_interrupts(|| {
+                dma::DMA3::set_source(words.as_ptr());
+                dma::DMA3::set_dest(dest);
+                dma::DMA3::set_count(words.len().try_into().unwrap());
+                dma::DMA3::set_control(
+                    dma::DMAControlSetting::new()
+                        .with_enabled(true)
+                        .with_use_32bit(true),
+                );
+            });
+        }
+        // VRAM can't be written bytewise, so the VRAM variants have to be used
+        Compression::Lz77 => lz77_uncomp_vram(bios_stream_ptr(data), dest as *mut u16),
+        Compression::Rle => rl_uncomp_vram(bios_stream_ptr(data), dest as *mut u16),
+    }
+}
+
+/// Returns a pointer to the BIOS header of a compressed file.
+fn bios_stream_ptr(data: &'static [u8]) -> *const u8 {
+    let ptr = data[MAGIC.len()..].as_ptr();
+    // The BIOS requires the source to be word aligned
+    assert_eq!(ptr as usize % 4, 0, "Compressed asset is not word aligned");
+    return ptr;
+}
+
+/// BIOS function 0x11, LZ77UnCompWram.
+unsafe fn lz77_uncomp_wram(src: *const u8, dest: *mut u8) {
+    asm!(
+        "swi 0x11",
+        inout("r0") src => _,
+        inout("r1") dest => _,
+        lateout("r2") _,
+        lateout("r3") _,
+        options(nostack)
+    );
+}
+
+/// BIOS function 0x12, LZ77UnCompVram.
+unsafe fn lz77_uncomp_vram(src: *const u8, dest: *mut u16) {
+    asm!(
+        "swi 0x12",
+        inout("r0") src => _,
+        inout("r1") dest => _,
+        lateout("r2") _,
+        lateout("r3") _,
+        options(nostack)
+    );
+}
+
+/// BIOS function 0x14, RLUnCompWram.
+unsafe fn rl_uncomp_wram(src: *const u8, dest: *mut u8) {
+    asm!(
+        "swi 0x14",
+        inout("r0") src => _,
+        inout("r1") dest => _,
+        lateout("r2") _,
+        lateout("r3") _,
+        options(nostack)
+    );
+}
+
+/// BIOS function 0x15, RLUnCompVram.
+unsafe fn rl_uncomp_vram(src: *const u8, dest: *mut u16) {
+    asm!(
+        "swi 0x15",
+        inout("r0") src => _,
+        inout("r1") dest => _,
+        lateout("r2") _,
+        lateout("r3") _,
+        options(nostack)
+    );
+}
@@ -0,0 +1,258 @@
+use super::BGTileAllocError;
+use crate::assets::{compression, Asset};
+use crate::debug_log::*;
+use crate::interrupt;
+use crate::shared_constants::{CHARBLOCK_SIZE_BYTES, TILE_SIZE_BYTES_4BPP};
+
+use core::convert::TryInto;
+use core::hash::{Hash, Hasher};
+use core::ptr;
+
+use alloc::vec::Vec;
+use gba::io::dma;
+use gba::vram::VRAM_BASE_USIZE;
+use twox_hash::XxHash64;
+
+/// Number of 4bpp tiles which fit into a single charblock.
+/// Screenblock entries can't address any more than that anyway.
+pub const TILES_PER_CHARBLOCK: usize = CHARBLOCK_SIZE_BYTES / TILE_SIZE_BYTES_4BPP;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TileBlockState {
+    Unused,
+    Used,
+    Continue,
+}
+
+/// An allocator for managing the 4bpp tiles of a single background charblock.
+///
+/// Tiles which are identical to tiles already in the charblock won't be loaded again in order to
+/// save VRAM, instead reference counting is used to keep the number of tiles down.
+/// # Safety
+///
+/// This assumes that it's in complete control over the charblock's tiles.
+/// Any usage of the charblock without going through the allocations handed out by this struct is UB.
+///
+/// Using more than one BGTileAllocator per charblock is also UB, use the allocators in `bg_tiles` instead.
+pub struct BGTileAllocator {
+    /// Charblock whose tiles are managed
+    charblock: usize,
+    /// List of tiles in the charblock, as well as how many allocations use that particular tile.
+    allocation_map: [(u16, TileBlockState); TILES_PER_CHARBLOCK],
+    /// Hashes of the tile data in VRAM, together with the tile it starts at and its number of tiles.
+    /// There are only ever a few of these, so a list is good enough.
+    allocation_hashes: Vec<(u64, usize, usize)>,
+}
+
+/// A set of contiguous tiles allocated in a charblock.
+/// Has to be given back to the allocator it came from once it's no longer needed.
+#[derive(Debug)]
+pub struct BGTileAllocation {
+    /// Index of the first tile in the charblock
+    pub first_tile: u16,
+    /// Number of tiles allocated
+    pub num_tiles: usize,
+    data_hash: u64,
+}
+
+impl BGTileAllocator {
+    /// Create a new allocator for the given charblock, with all tiles free.
+    pub const fn new(charblock: usize) -> BGTileAllocator {
+        return BGTileAllocator {
+            charblock,
+            allocation_map: [(0, TileBlockState::Unused); TILES_PER_CHARBLOCK],
+            allocation_hashes: Vec::new(),
+        };
+    }
+
+    /// Allocate the tiles stored in the given asset, which may be compressed.
+    pub fn alloc_from_fs(&mut self, tiles: &Asset) -> Result<BGTileAllocation, BGTileAllocError> {
+        return self.alloc_from_file(tiles.data());
+    }
+
+    /// Allocate the tiles stored in the given file contents, which may be compressed
+    /// (see `assets::compression`).
+    pub fn alloc_from_file(
+        &mut self,
+        data: &'static [u8],
+    ) -> Result<BGTileAllocation, BGTileAllocError> {
+        let len = compression::decompressed_len(data);
+        if len % TILE_SIZE_BYTES_4BPP != 0 {
+            return Err(BGTileAllocError::IncompleteTiles(len));
+        }
+        return self.alloc(&compression::load::<u32>(data));
+    }
+
+    /// Allocate the given 4bpp tiles in the charblock, copying them into VRAM
+    /// unless identical tiles are already present.
+    pub fn alloc(&mut self, tiles: &[u32]) -> Result<BGTileAllocation, BGTileAllocError> {
+        let len = tiles.len() * 4;
+        if len == 0 || len % TILE_SIZE_BYTES_4BPP != 0 {
+            return Err(BGTileAllocError::IncompleteTiles(len));
+        }
+        let num_tiles = len / TILE_SIZE_BYTES_4BPP;
+
+        // A fresh hasher has to be used every time, otherwise the hash depends on previous allocations
+        let mut hasher = XxHash64::default();
+        tiles.hash(&mut hasher);
+        let data_hash = hasher.finish();
+
+        // Different tiles may have the same hash, and animations change tiles after they were allocated,
+        // so only the tiles actually in VRAM are shared
+        let present_tile = self
+            .allocation_hashes
+            .iter()
+            .find(|(hash, first_tile, len)| {
+                *hash == data_hash && *len == num_tiles && self.are_tiles_at(tiles, *first_tile)
+            })
+            .map(|(_, first_tile, _)| *first_tile);
+        let first_tile = match present_tile {
+            Some(first_tile) => {
+                debug_log!(
+                    Subsystems::BGTiles,
+                    "Tiles already present at tile #{}, not actually allocating",
+                    first_tile
+                );
+                first_tile
+            }
+            None => {
+                let first_tile = self.find_contiguous_free_tiles(num_tiles)?;
+                debug_log!(
+                    Subsystems::BGTiles,
+                    "Allocating {} tiles starting at tile #{} in charblock {}",
+                    num_tiles,
+                    first_tile,
+                    self.charblock
+                );
+                self.dma_copy_tiles(tiles, first_tile);
+                self.allocation_hashes
+                    .push((data_hash, first_tile, num_tiles));
+                first_tile
+            }
+        };
+
+        // Increase the refcount of all tiles belonging to the allocation
+        for i in 0..num_tiles {
+            let state = if i == 0 {
+                TileBlockState::Used
+            } else {
+                TileBlockState::Continue
+            };
+            self.allocation_map[first_tile + i].0 += 1;
+            self.allocation_map[first_tile + i].1 = state;
+        }
+        return Ok(BGTileAllocation {
+            first_tile: first_tile.try_into().unwrap(),
+            num_tiles,
+            data_hash,
+        });
+    }
+
+    /// Drop the given allocation.
+    /// Note that the tiles still exist in VRAM until overwritten, so screenblock entries referring to them
+    /// should be removed first.
+    pub fn free(&mut self, allocation: BGTileAllocation) {
+        let first_tile = allocation.first_tile as usize;
+        for i in first_tile..first_tile + allocation.num_tiles {
+            self.allocation_map[i].0 -= 1;
+            if self.allocation_map[i].0 == 0 {
+                self.allocation_map[i].1 = TileBlockState::Unused;
+            }
+        }
+        if self.allocation_map[first_tile].1 == TileBlockState::Unused {
+            debug_log!(Subsystems::BGTiles, "Refcount reached 0, freeing tiles");
+            self.allocation_hashes
+                .retain(|(hash, tile, _)| *hash != allocation.data_hash || *tile != first_tile);
+        }
+    }
+
+    /// Overwrites tiles of the allocation in VRAM, starting `offset` tiles into it.
+    /// Every screenblock entry referring to the tiles shows the new graphics at once, which is useful for animations.
+    ///
+    /// Identical allocations share their tiles, so this changes the tiles for all of them.
+    /// Later allocations of the tiles the allocation was made with get tiles of their own.
+    pub fn write_tiles(&self, allocation: &BGTileAllocation, offset: usize, tiles: &[u32]) {
+        let num_tiles = tiles.len() * 4 / TILE_SIZE_BYTES_4BPP;
+        if offset + num_tiles > allocation.num_tiles {
+            panic!(
+                "Attempt to write tiles {}..{} of an allocation with {} tiles",
+                offset,
+                offset + num_tiles,
+                allocation.num_tiles
+            );
+        }
+        self.write_tiles_at(allocation.first_tile as usize + offset, tiles);
+    }
+
+    /// Overwrites tiles in VRAM, starting at the given tile of the charblock.
+    /// Like `write_tiles()`, but for users which can't hold on to their allocation, such as vblank handlers.
+    /// They have to make sure the tiles are still allocated.
+    pub fn write_tiles_at(&self, first_tile: usize, tiles: &[u32]) {
+        let num_tiles = tiles.len() * 4 / TILE_SIZE_BYTES_4BPP;
+        if first_tile + num_tiles > TILES_PER_CHARBLOCK {
+            panic!(
+                "Attempt to write tiles {}..{} of a charblock",
+                first_tile,
+                first_tile + num_tiles
+            );
+        }
+        self.dma_copy_tiles(tiles, first_tile);
+    }
+
+    /// Returns how many tiles of the charblock aren't used by any allocation.
+    #[cfg(test)]
+    pub fn num_free_tiles(&self) -> usize {
+        return self
+            .allocation_map
+            .iter()
+            .filter(|(_, state)| *state == TileBlockState::Unused)
+            .count();
+    }
+
+    /// Return the index of the first tile of the first free area in the charblock
+    /// with sufficient space.
+    fn find_contiguous_free_tiles(&self, num_tiles: usize) -> Result<usize, BGTileAllocError> {
+        let mut free_tiles = 0;
+        for (i, (_refcount, state)) in self.allocation_map.iter().enumerate() {
+            if *state == TileBlockState::Unused {
+                free_tiles += 1;
+                if free_tiles >= num_tiles {
+                    return Ok(i + 1 - num_tiles);
+                }
+            } else {
+                free_tiles = 0;
+            }
+        }
+        return Err(BGTileAllocError::CharblockFull(num_tiles));
+    }
+
+    /// Returns whether the given tiles are in the charblock, starting at the given tile.
+    fn are_tiles_at(&self, tiles: &[u32], first_tile: usize) -> bool {
+        let base_addr = VRAM_BASE_USIZE
+            + self.charblock * CHARBLOCK_SIZE_BYTES
+            + first_tile * TILE_SIZE_BYTES_4BPP;
+        return tiles.iter().enumerate().all(|(i, word)| {
+            // Safety: Within the charblock, as the tiles were allocated there
+            let vram_word = unsafe { ptr::read_volatile((base_addr + i * 4) as *const u32) };
+            return vram_word == *word;
+        });
+    }
+
+    /// Copies the tiles into the charblock using DMA, starting at the given tile.
+    fn dma_copy_tiles(&self, tiles: &[u32], first_tile: usize) {
+        let dest_addr = VRAM_BASE_USIZE
+            + self.charblock * CHARBLOCK_SIZE_BYTES
+            + first_tile * TILE_SIZE_BYTES_4BPP;
+        // The vblank handler uses DMA3 as well, so it mustn't interrupt setting it up
+        interrupt::without_interrupts(|| unsafe {
+            dma::DMA3::set_source(tiles.as_ptr());
+            dma::DMA3::set_dest(dest_addr as *mut u32);
+            dma::DMA3::set_count(tiles.len().try_into().unwrap());
+            dma::DMA3::set_control(
+                dma::DMAControlSetting::new()
+                    .with_enabled(true)
+                    .with_use_32bit(true),
+            );
+        });
+    }
+}
@@ -0,0 +1,112 @@
+use super::*;
+use crate::shared_constants::TEXT_CHARBLOCK;
+use crate::test::test;
+
+use alloc::vec::Vec;
+
+// Generic test setup code
+// The text charblock is used because the tests don't display anything anyway
+#[cfg(test)]
+fn test_setup() -> BGTileAllocator {
+    return BGTileAllocator::new(TEXT_CHARBLOCK);
+}
+
+/// Returns `num_tiles` tiles filled with the given value.
+#[cfg(test)]
+fn tiles(num_tiles: usize, value: u32) -> Vec<u32> {
+    return vec![value; num_tiles * 8];
+}
+
+/// Ensure that identical tiles are only allocated once and freed once they're no longer used
+#[test_case]
+fn test_bg_tile_alloc_dedup() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let first = alloc.alloc(&tiles(4, 1)).unwrap();
+            let second = alloc.alloc(&tiles(4, 1)).unwrap();
+            assert_eq!(first.first_tile, second.first_tile);
+            assert_eq!(alloc.num_free_tiles(), TILES_PER_CHARBLOCK - 4);
+
+            let different = alloc.alloc(&tiles(4, 2)).unwrap();
+            assert_eq!(different.first_tile, 4);
+
+            alloc.free(first);
+            assert_eq!(alloc.num_free_tiles(), TILES_PER_CHARBLOCK - 8);
+            alloc.free(second);
+            assert_eq!(alloc.num_free_tiles(), TILES_PER_CHARBLOCK - 4);
+            // The freed tiles aren't deduplicated against anymore
+            let third = alloc.alloc(&tiles(2, 3)).unwrap();
+            let fourth = alloc.alloc(&tiles(4, 1)).unwrap();
+            assert_eq!(third.first_tile, 0);
+            assert_eq!(fourth.first_tile, 8);
+        },
+        "test_bg_tile_alloc_dedup",
+        "ensure identical background tiles are deduplicated and refcounted",
+    );
+}
+
+/// Ensure that tiles which were overwritten since they were allocated aren't deduplicated against
+#[test_case]
+fn test_bg_tile_alloc_no_dedup_changed_tiles() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let animated = alloc.alloc(&tiles(4, 1)).unwrap();
+            alloc.write_tiles(&animated, 2, &tiles(1, 5));
+            let original = alloc.alloc(&tiles(4, 1)).unwrap();
+            assert_eq!(original.first_tile, 4);
+            // Only tiles with the same length are shared
+            let shorter = alloc.alloc(&tiles(2, 1)).unwrap();
+            assert_eq!(shorter.first_tile, 8);
+
+            alloc.free(animated);
+            let duplicate = alloc.alloc(&tiles(4, 1)).unwrap();
+            assert_eq!(duplicate.first_tile, 4);
+            assert_eq!(alloc.num_free_tiles(), TILES_PER_CHARBLOCK - 6);
+        },
+        "test_bg_tile_alloc_no_dedup_changed_tiles",
+        "ensure background tiles which were overwritten aren't deduplicated against",
+    );
+}
+
+/// Ensure that the charblock can be filled, but not overfilled
+#[test_case]
+fn test_bg_tile_alloc_fill_charblock() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let first = alloc.alloc(&tiles(TILES_PER_CHARBLOCK - 1, 1)).unwrap();
+            alloc.alloc(&tiles(1, 2)).unwrap();
+            assert_eq!(alloc.num_free_tiles(), 0);
+            assert_eq!(
+                alloc.alloc(&tiles(1, 3)).unwrap_err(),
+                BGTileAllocError::CharblockFull(1)
+            );
+            alloc.free(first);
+            assert_eq!(alloc.alloc(&tiles(1, 3)).unwrap().first_tile, 0);
+        },
+        "test_bg_tile_alloc_fill_charblock",
+        "ensure all tiles of a charblock can be allocated, but no more",
+    );
+}
+
+/// Ensure that tile data has to consist of whole tiles
+#[test_case]
+fn test_bg_tile_alloc_incomplete_tiles() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            assert_eq!(
+                alloc.alloc(&[0; 7]).unwrap_err(),
+                BGTileAllocError::IncompleteTiles(28)
+            );
+            assert_eq!(
+                alloc.alloc(&[]).unwrap_err(),
+                BGTileAllocError::IncompleteTiles(0)
+            );
+        },
+        "test_bg_tile_alloc_incomplete_tiles",
+        "ensure background tile data has to consist of whole tiles",
+    );
+}
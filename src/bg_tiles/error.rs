@@ -0,0 +1,26 @@
+/// The error returned in background tile allocation-related failure cases.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BGTileAllocError {
+    /// There's no contiguous free area in the charblock that's large enough (tiles requested).
+    CharblockFull(usize),
+    /// The tile data is empty or isn't made up of whole 4bpp tiles (length in bytes).
+    IncompleteTiles(usize),
+}
+
+impl core::fmt::Display for BGTileAllocError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        use BGTileAllocError::*;
+        match self {
+            CharblockFull(num_tiles) => write!(
+                f,
+                "BGTileAllocError: No contiguous free area for {} tiles available in charblock",
+                num_tiles
+            ),
+            IncompleteTiles(len) => write!(
+                f,
+                "BGTileAllocError: Tile data of {} bytes doesn't consist of one or more whole 4bpp tiles",
+                len
+            ),
+        }
+    }
+}
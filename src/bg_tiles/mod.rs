@@ -0,0 +1,26 @@
+//! This module manages the background tiles stored in VRAM charblocks.
+//! The interface is allocator-like, with the ability to allocate and free sets of tiles,
+//! so that several users (e.g. the map and the buildings drawn into it) can share a charblock.
+//!
+//! Tiles are 4bpp and addressed by their index in the charblock, which is what screenblock entries refer to.
+//! Users have to add the `first_tile` of their allocation to the tile IDs in their screenblock entries.
+//!
+//! Works the same way as the sprite allocator (see `sprite`), including deduplication of identical tiles.
+
+mod bg_tile_alloc;
+mod error;
+pub use bg_tile_alloc::{BGTileAllocation, BGTileAllocator, TILES_PER_CHARBLOCK};
+pub use error::BGTileAllocError;
+#[cfg(test)]
+mod bg_tile_alloc_test;
+
+use crate::shared_constants::{BACKGROUND_CHARBLOCK, TEXT_CHARBLOCK};
+
+use spinning_top::{const_spinlock, Spinlock};
+
+/// Allocator for the charblock shared by the map and the buildings drawn into it.
+pub static MAP_TILES: Spinlock<BGTileAllocator> =
+    const_spinlock(BGTileAllocator::new(BACKGROUND_CHARBLOCK));
+/// Allocator for the charblock shared by all text engines.
+pub static TEXT_TILES: Spinlock<BGTileAllocator> =
+    const_spinlock(BGTileAllocator::new(TEXT_CHARBLOCK));
@@ -8,6 +8,8 @@ use alloc::boxed::Box;
 pub struct BuilderComponent<'a> {
     pub buildable: Option<Box<&'a dyn Buildable>>,
     pub pos: Option<Position>,
+    /// Whether the building under the builder should be deconstructed
+    pub deconstruct: bool,
 }
 
 impl<'a> BuilderComponent<'a> {
@@ -15,6 +17,7 @@ impl<'a> BuilderComponent<'a> {
         return BuilderComponent {
             buildable: None,
             pos: None,
+            deconstruct: false,
         };
     }
 }
@@ -0,0 +1,22 @@
+/// This component makes an entity unable to move through solid terrain and buildings.
+/// The entity's axis-aligned bounding box is given relative to its position, in pixels.
+#[derive(Clone, Debug)]
+pub(crate) struct ColliderComponent {
+    /// Distance of the bounding box's top-left corner from the entity's position
+    pub offset_x: u32,
+    pub offset_y: u32,
+    /// Size of the bounding box
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ColliderComponent {
+    pub fn new(offset_x: u32, offset_y: u32, width: u32, height: u32) -> ColliderComponent {
+        return ColliderComponent {
+            offset_x,
+            offset_y,
+            width,
+            height,
+        };
+    }
+}
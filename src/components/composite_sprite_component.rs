@@ -0,0 +1,69 @@
+use crate::assets::SpriteAsset;
+use crate::sprite::{HWSpriteAllocError, HWSpriteAllocator, HWSpriteHandle};
+
+use alloc::vec::Vec;
+
+/// One of the sprites making up a `CompositeSpriteComponent`.
+pub(crate) struct SpritePart {
+    pub handle: HWSpriteHandle,
+    /// Offset of the part's top-left corner from the entity's position, in pixels
+    pub offset_x: u16,
+    pub offset_y: u16,
+}
+
+/// An ECS component for entities which are drawn with several sprites,
+/// e.g. blocks larger than the largest hardware sprite, or drills with a head on top of their base.
+/// The `MovementSystem` moves, hides and shows all parts as a unit, like the sprite of a `SpriteComponent`.
+/// The sprites are freed once the component is dropped.
+pub(crate) struct CompositeSpriteComponent {
+    parts: Vec<SpritePart>,
+}
+
+impl CompositeSpriteComponent {
+    /// Creates a component without any parts.
+    pub fn new() -> CompositeSpriteComponent {
+        return CompositeSpriteComponent { parts: Vec::new() };
+    }
+
+    /// Allocates the given sprite as another part, at the given offset from the entity's position.
+    /// The part is culled until the `MovementSystem` moves the whole entity onto the screen.
+    ///
+    /// Parts added later are drawn on top of earlier ones. They get a lower culling priority for that,
+    /// which puts them into OAM first.
+    pub fn add_part(
+        &mut self,
+        alloc: &mut HWSpriteAllocator,
+        sprite: &SpriteAsset,
+        offset_x: u16,
+        offset_y: u16,
+    ) -> Result<(), HWSpriteAllocError> {
+        let handle = alloc.alloc_from_fs(sprite)?;
+        handle.set_visibility(true);
+        handle.set_offscreen(true);
+        handle.set_culling_priority(
+            HWSpriteHandle::DEFAULT_CULLING_PRIORITY.saturating_sub(self.parts.len() as u8),
+        );
+        self.parts.push(SpritePart {
+            handle,
+            offset_x,
+            offset_y,
+        });
+        return Ok(());
+    }
+
+    /// Returns the parts in the order they were added.
+    pub fn parts(&self) -> &[SpritePart] {
+        return &self.parts;
+    }
+
+    /// Returns the width and height of the area covered by all parts, starting at the entity's position.
+    pub fn size_in_px(&self) -> (u16, u16) {
+        return self.parts.iter().fold((0, 0), |(width, height), part| {
+            let (part_width, part_height) = part.handle.sprite_size.to_size_in_px();
+            return (
+                width.max(part.offset_x + part_width),
+                height.max(part.offset_y + part_height),
+            );
+        });
+    }
+}
@@ -1,18 +1,26 @@
 //! This module contains ECS components.
 mod builder_component;
+mod collider_component;
 pub mod component_utils;
+mod composite_sprite_component;
 mod input_component;
 mod inventory_component;
 mod item_source_component;
 pub mod miner_component;
 mod movement_component;
 mod position_component;
+pub mod sprite_animation_component;
+#[cfg(test)]
+mod sprite_animation_component_test;
 mod sprite_component;
 pub(crate) use builder_component::BuilderComponent;
+pub(crate) use collider_component::ColliderComponent;
+pub(crate) use composite_sprite_component::CompositeSpriteComponent;
 pub(crate) use input_component::InputComponent;
 pub(crate) use inventory_component::InventoryComponent;
 pub(crate) use item_source_component::ItemSourceComponent;
 pub(crate) use miner_component::MinerComponent;
 pub(crate) use movement_component::MovementComponent;
 pub(crate) use position_component::PositionComponent;
+pub(crate) use sprite_animation_component::SpriteAnimationComponent;
 pub(crate) use sprite_component::SpriteComponent;
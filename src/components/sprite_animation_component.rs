@@ -0,0 +1,69 @@
+//! Component for entities whose sprite cycles through the frames of a sprite sheet.
+
+use crate::sprite::HWSpriteHandle;
+
+/// What happens once the last frame of an animation has been shown.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum AnimationMode {
+    /// Start over at the first frame
+    Loop,
+    /// Stay on the last frame
+    Once,
+}
+
+/// An animation of the entity's `SpriteComponent`, which is stepped by the `animation_system`.
+/// The sprite has to be allocated with all frames of its sprite sheet, see `HWSpriteHandle::set_frame()`.
+/// The number of frames is always taken from the sprite, so the two can't disagree.
+pub(crate) struct SpriteAnimationComponent {
+    /// How many ticks each frame is shown for
+    ticks_per_frame: u8,
+    mode: AnimationMode,
+    /// Frame which is currently shown
+    frame: usize,
+    /// How many ticks the current frame has been shown for
+    ticks_in_frame: u8,
+}
+
+impl SpriteAnimationComponent {
+    /// Creates an animation of the given sprite which starts at the first frame.
+    /// Returns None if the sprite has no frames to show.
+    pub fn new(
+        sprite: &HWSpriteHandle,
+        ticks_per_frame: u8,
+        mode: AnimationMode,
+    ) -> Option<SpriteAnimationComponent> {
+        if sprite.num_frames() == 0 {
+            return None;
+        }
+        return Some(SpriteAnimationComponent {
+            ticks_per_frame,
+            mode,
+            frame: 0,
+            ticks_in_frame: 0,
+        });
+    }
+
+    /// Advances the animation of a sprite with the given number of frames by one tick.
+    /// Returns the frame to show if it changed.
+    pub fn tick(&mut self, num_frames: usize) -> Option<usize> {
+        if self.is_finished(num_frames) {
+            return None;
+        }
+        self.ticks_in_frame += 1;
+        if self.ticks_in_frame < self.ticks_per_frame {
+            return None;
+        }
+        self.ticks_in_frame = 0;
+        self.frame = match self.mode {
+            AnimationMode::Loop => (self.frame + 1) % num_frames,
+            AnimationMode::Once => self.frame + 1,
+        };
+        return Some(self.frame);
+    }
+
+    /// Whether a one-shot animation of a sprite with the given number of frames has reached its last frame.
+    /// Looping animations never finish.
+    pub fn is_finished(&self, num_frames: usize) -> bool {
+        return self.mode == AnimationMode::Once && self.frame + 1 >= num_frames;
+    }
+}
@@ -0,0 +1,60 @@
+use super::sprite_animation_component::*;
+use crate::assets::palettes;
+use crate::sprite::{HWSpriteAllocator, HWSpriteSize};
+use crate::test::test;
+
+fn test_setup() -> HWSpriteAllocator {
+    crate::interrupt::init();
+    let pal = palettes::SPRITE_SHARED.load::<u16>();
+    let sprite_allocator = HWSpriteAllocator::new(&pal);
+    sprite_allocator.init();
+    return sprite_allocator;
+}
+
+/// Ensure looping animations start over after the last frame
+#[test_case]
+fn test_looping_animation() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            // Three 4bpp 8x8 frames
+            let sprite = alloc
+                .alloc_4bpp(&[3; 24], HWSpriteSize::EightByEight, &[0; 16])
+                .unwrap();
+            let mut animation =
+                SpriteAnimationComponent::new(&sprite, 2, AnimationMode::Loop).unwrap();
+            let frames: [Option<usize>; 8] =
+                [None, Some(1), None, Some(2), None, Some(0), None, Some(1)];
+            for frame in frames.iter() {
+                assert_eq!(animation.tick(sprite.num_frames()), *frame);
+            }
+            assert!(!animation.is_finished(sprite.num_frames()));
+        },
+        "test_looping_animation",
+        "ensure looping animations start over after the last frame",
+    );
+}
+
+/// Ensure one-shot animations stop at the last frame
+#[test_case]
+fn test_one_shot_animation() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let sprite = alloc
+                .alloc_4bpp(&[3; 24], HWSpriteSize::EightByEight, &[0; 16])
+                .unwrap();
+            let mut animation =
+                SpriteAnimationComponent::new(&sprite, 1, AnimationMode::Once).unwrap();
+            assert_eq!(animation.tick(sprite.num_frames()), Some(1));
+            assert!(!animation.is_finished(sprite.num_frames()));
+            assert_eq!(animation.tick(sprite.num_frames()), Some(2));
+            assert!(animation.is_finished(sprite.num_frames()));
+            for _ in 0..4 {
+                assert_eq!(animation.tick(sprite.num_frames()), None);
+            }
+        },
+        "test_one_shot_animation",
+        "ensure one-shot animations stop at the last frame",
+    );
+}
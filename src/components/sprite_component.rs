@@ -1,5 +1,7 @@
-use crate::sprite::{HWSpriteAllocator, HWSpriteHandle, HWSpriteSize};
+use crate::assets::SpriteAsset;
+use crate::sprite::{HWSpriteAllocator, HWSpriteHandle};
 /// An ECS component which controls the on-screen sprite of the entity.
+/// The sprite is freed once the component is dropped, e.g. when the entity is despawned.
 pub(crate) struct SpriteComponent {
     handle: HWSpriteHandle,
 }
@@ -9,15 +11,12 @@ impl SpriteComponent {
     /// The sprite allocator is expected to be initialized.
     pub fn with_pos(
         alloc: &mut HWSpriteAllocator,
-        sprite_filename: &str,
-        sprite_size: HWSpriteSize,
+        sprite: &SpriteAsset,
         x_pos: u16,
         y_pos: u16,
         is_visible: bool,
     ) -> SpriteComponent {
-        let sprite_handle = alloc
-            .alloc_from_fs_file(sprite_filename, sprite_size)
-            .unwrap();
+        let sprite_handle = alloc.alloc_from_fs(sprite).unwrap();
         sprite_handle.set_x_pos(x_pos);
         sprite_handle.set_y_pos(y_pos);
         sprite_handle.set_visibility(is_visible);
@@ -16,6 +16,7 @@ pub enum Subsystems {
     Main,
     Entity,
     HWSprite,
+    BGTiles,
     InputSystem,
     InventorySystem,
     MovementSystem,
@@ -25,6 +26,7 @@ pub enum Subsystems {
     Menu,
     Interrupt,
     Sound,
+    Spatial,
 }
 
 impl Subsystems {
@@ -35,6 +37,7 @@ impl Subsystems {
             Main => "MAIN",
             Entity => "ENTITY",
             HWSprite => "HW SPRITE",
+            BGTiles => "BG TILES",
             InputSystem => "INPUT SYSTEM",
             InventorySystem => "INVENTORY SYSTEM",
             MovementSystem => "MOVEMENT SYSTEM",
@@ -44,6 +47,7 @@ impl Subsystems {
             Menu => "MENU",
             Interrupt => "INTERRUPT",
             Sound => "SOUND",
+            Spatial => "SPATIAL",
         }
     }
 }
@@ -1,3 +1,4 @@
+use crate::map::{Building, Map};
 use crate::shared_types::Position;
 use crate::sprite::HWSpriteAllocator;
 
@@ -5,13 +6,19 @@ use tiny_ecs::{ECSError, Entities};
 
 /// All objects that can be built in the world.
 pub trait Buildable {
-    /// Creates a new instance of the entity in the world.
+    /// Creates a new instance of the entity at the given position, which is on the tile grid.
+    /// Buildings don't move, so they're drawn into the map rather than using a sprite.
+    /// Only parts drawn on top of that, like the head of a drill, are sprites.
     ///
     /// Returns the ECS ID of the constructed entity.
     fn build(
         &self,
         pos: Position,
         entities: &mut Entities,
+        map: &mut Map,
         sprite_alloc: &mut HWSpriteAllocator,
     ) -> Result<usize, ECSError>;
+
+    /// The building which is drawn into the map by `build()`.
+    fn building(&self) -> Building;
 }
@@ -1,25 +1,15 @@
-use crate::components::{InventoryComponent, PositionComponent, SpriteComponent};
+use crate::components::{InventoryComponent, PositionComponent};
 use crate::debug_log::*;
+use crate::map::{Building, Map};
 use crate::shared_types::*;
 
-use crate::sprite::{HWSpriteAllocator, HWSpriteSize};
 use tiny_ecs::{ECSError, Entities};
 
 /// Adds a container to the ECS.
-pub(crate) fn add_container(
-    entities: &mut Entities,
-    sprite_alloc: &mut HWSpriteAllocator,
-) -> Result<usize, ECSError> {
+pub(crate) fn add_container(entities: &mut Entities, map: &mut Map) -> Result<usize, ECSError> {
+    map.place_building(Building::Container, 128, 128);
     let entity_id = entities
         .new_entity()
-        .with(SpriteComponent::with_pos(
-            sprite_alloc,
-            "containerTiles",
-            HWSpriteSize::ThirtyTwoByThirtyTwo,
-            128,
-            128,
-            true
-        ))?
         .with(PositionComponent::with_pos((
             Coordinate::from_num(128),
             Coordinate::from_num(128),
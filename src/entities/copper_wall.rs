@@ -1,7 +1,8 @@
-use crate::components::{PositionComponent, SpriteComponent};
+use crate::components::PositionComponent;
 use crate::debug_log::*;
+use crate::map::{Building, Map};
 use crate::shared_types::*;
-use crate::sprite::{HWSpriteAllocator, HWSpriteSize};
+use crate::sprite::HWSpriteAllocator;
 
 use tiny_ecs::{ECSError, Entities};
 
@@ -13,22 +14,20 @@ impl super::Buildable for CopperWall {
         &self,
         pos: Position,
         entities: &mut Entities,
-        sprite_alloc: &mut HWSpriteAllocator,
+        map: &mut Map,
+        _sprite_alloc: &mut HWSpriteAllocator,
     ) -> Result<usize, ECSError> {
+        map.place_building(Building::CopperWall, pos.0.to_num(), pos.1.to_num());
         let entity_id = entities
             .new_entity()
-            .with(SpriteComponent::with_pos(
-                sprite_alloc,
-                "copper_wallTiles",
-                HWSpriteSize::SixteenBySixteen,
-                pos.0.to_num(),
-                pos.1.to_num(),
-                true,
-            ))?
             .with(PositionComponent::with_pos(pos))?
             .finalise()?;
         debug_log!(Subsystems::Entity, "Created copper wall");
 
         return Ok(entity_id);
     }
+
+    fn building(&self) -> Building {
+        return Building::CopperWall;
+    }
 }
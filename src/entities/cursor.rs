@@ -1,7 +1,8 @@
+use crate::assets::sprites;
 use crate::components::SpriteComponent;
 use crate::debug_log::*;
 use crate::shared_constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
-use crate::sprite::{HWSpriteAllocator, HWSpriteSize};
+use crate::sprite::HWSpriteAllocator;
 use tiny_ecs::{ECSError, Entities};
 
 /// Middle of the screen should be middle of sprite as well
@@ -13,17 +14,16 @@ pub fn add_cursor(
     entities: &mut Entities,
     sprite_alloc: &mut HWSpriteAllocator,
 ) -> Result<usize, ECSError> {
-    let entity_id = entities
-        .new_entity()
-        .with(SpriteComponent::with_pos(
-            sprite_alloc,
-            "cursorTiles",
-            HWSpriteSize::SixteenBySixteen,
-            INITIAL_CURSOR_ONSCREEN_POS_X,
-            INITIAL_CURSOR_ONSCREEN_POS_Y,
-            false,
-        ))?
-        .finalise()?;
+    let mut sprite_component = SpriteComponent::with_pos(
+        sprite_alloc,
+        &sprites::CURSOR,
+        INITIAL_CURSOR_ONSCREEN_POS_X,
+        INITIAL_CURSOR_ONSCREEN_POS_Y,
+        false,
+    );
+    // Neither must the cursor, while building
+    sprite_component.get_handle().set_culling_priority(0);
+    let entity_id = entities.new_entity().with(sprite_component)?.finalise()?;
     debug_log!(Subsystems::Entity, "Created cursor");
 
     return Ok(entity_id);
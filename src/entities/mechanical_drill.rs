@@ -1,11 +1,13 @@
 use super::Buildable;
+use crate::assets::sprites;
 use crate::components::miner_component::MiningProgress;
 use crate::components::ItemSourceComponent;
-use crate::components::{MinerComponent, PositionComponent, SpriteComponent};
+use crate::components::{CompositeSpriteComponent, MinerComponent, PositionComponent};
 use crate::debug_log::*;
 use crate::item::Item;
+use crate::map::{Building, Map};
 use crate::shared_types::*;
-use crate::sprite::{HWSpriteAllocator, HWSpriteSize};
+use crate::sprite::HWSpriteAllocator;
 
 use tiny_ecs::{ECSError, Entities};
 
@@ -31,19 +33,26 @@ impl Buildable for MechanicalDrill {
         &self,
         pos: Position,
         entities: &mut Entities,
+        map: &mut Map,
         sprite_alloc: &mut HWSpriteAllocator,
     ) -> Result<usize, ECSError> {
+        map.place_building(
+            Building::MechanicalDrill,
+            pos.0.ceil().to_num(),
+            pos.1.ceil().to_num(),
+        );
+        // The map only contains the drill's base, its head is drawn on top of it
+        let mut sprite_component = CompositeSpriteComponent::new();
+        sprite_component
+            .add_part(sprite_alloc, &sprites::MECHANICAL_DRILL_ROTATOR, 0, 0)
+            .expect("Failed to allocate mechanical drill rotator");
+        sprite_component
+            .add_part(sprite_alloc, &sprites::MECHANICAL_DRILL_TOP, 0, 0)
+            .expect("Failed to allocate mechanical drill top");
         let entity_id = entities
             .new_entity()
-            .with(SpriteComponent::with_pos(
-                sprite_alloc,
-                "mechanical_drillTiles",
-                HWSpriteSize::ThirtyTwoByThirtyTwo,
-                pos.0.ceil().to_num(),
-                pos.1.ceil().to_num(),
-                true,
-            ))?
             .with(PositionComponent::with_pos(pos))?
+            .with(sprite_component)?
             // TODO: Correct resource type and speed
             .with(MinerComponent::new(
                 Item::Copper,
@@ -58,4 +67,8 @@ impl Buildable for MechanicalDrill {
 
         return Ok(entity_id);
     }
+
+    fn building(&self) -> Building {
+        return Building::MechanicalDrill;
+    }
 }
@@ -1,16 +1,21 @@
+use crate::assets::sprites;
+use crate::components::sprite_animation_component::AnimationMode;
 use crate::components::{
-    InputComponent, InventoryComponent, MovementComponent, PositionComponent, SpriteComponent,
+    ColliderComponent, InputComponent, InventoryComponent, MovementComponent, PositionComponent,
+    SpriteAnimationComponent, SpriteComponent,
 };
 use crate::debug_log::*;
 use crate::shared_constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
 use crate::shared_types::Coordinate;
-use crate::sprite::{HWSpriteAllocator, HWSpriteSize};
+use crate::sprite::HWSpriteAllocator;
 use tiny_ecs::{ECSError, Entities};
 
 /// Middle of the screen should be middle of sprite as well
 pub const INITIAL_PLAYER_ONSCREEN_POS_X: u16 = (SCREEN_WIDTH / 2 - 32 / 2) as u16;
 pub const INITIAL_PLAYER_ONSCREEN_POS_Y: u16 = (SCREEN_HEIGHT / 2 - 32 / 2) as u16;
 const PLAYER_INVENTORY_CAPACITY: usize = 64;
+/// How many ticks each frame of the ship hovering up and down is shown for
+const PLAYER_HOVER_TICKS_PER_FRAME: u8 = 12;
 /// Adds a player to the ECS.
 /// The player accepts user input and the camera stays centered on it's sprite.
 pub fn add_player(
@@ -20,19 +25,30 @@ pub fn add_player(
     let mut movement_component = MovementComponent::new();
     movement_component.input_controlled = true;
     movement_component.keep_camera_centered_on = true;
+    let mut sprite_component = SpriteComponent::with_pos(
+        sprite_alloc,
+        &sprites::DART_SHIP,
+        INITIAL_PLAYER_ONSCREEN_POS_X,
+        INITIAL_PLAYER_ONSCREEN_POS_Y,
+        true,
+    );
+    // The player must never be the sprite left out when too many are on screen
+    sprite_component.get_handle().set_culling_priority(0);
+    let animation_component = SpriteAnimationComponent::new(
+        sprite_component.get_handle(),
+        PLAYER_HOVER_TICKS_PER_FRAME,
+        AnimationMode::Loop,
+    )
+    .unwrap();
     let entity_id = entities
         .new_entity()
-        .with(SpriteComponent::with_pos(
-            sprite_alloc,
-            "dart_shipTiles",
-            HWSpriteSize::ThirtyTwoByThirtyTwo,
-            INITIAL_PLAYER_ONSCREEN_POS_X,
-            INITIAL_PLAYER_ONSCREEN_POS_Y,
-            true,
-        ))?
+        .with(sprite_component)?
+        .with(animation_component)?
         .with(movement_component)?
         .with(InputComponent::new())?
         .with(InventoryComponent::new(PLAYER_INVENTORY_CAPACITY))?
+        // The ship doesn't fill its whole 32x32 sprite, so it may get a bit closer to walls than that
+        .with(ColliderComponent::new(4, 4, 24, 24))?
         // Place player in the middle of the screen
         .with(PositionComponent::with_pos((
             Coordinate::from_num(INITIAL_PLAYER_ONSCREEN_POS_X),
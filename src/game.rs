@@ -1,26 +1,31 @@
+use crate::assets::palettes;
 use crate::components::{component_utils::*, *};
 use crate::debug_log::*;
 use crate::entities;
-use crate::entities::{cursor, player};
-use crate::map::{Map, Maps};
+use crate::interrupt;
+use crate::map::{Camera, Map, Maps};
+use crate::shared_types::Coordinate;
+use crate::spatial::SpatialHash;
 use crate::sprite::HWSpriteAllocator;
 use crate::systems::{
-    building_system, item_movement_system, mining_system, InputSystem, MovementSystem,
+    animation_system, building_system, item_movement_system, mining_system, InputSystem,
+    MovementSystem,
 };
 use crate::window::Window;
 
-use crate::FS;
-
 use alloc::{boxed::Box, vec::Vec};
 
 use gba::io::display::{DISPCNT, VBLANK_SCANLINE, VCOUNT};
 use tiny_ecs::Entities;
 
+/// How long the camera shows where enemies spawn when the game starts, in frames.
+const SPAWN_PAN_DURATION: u32 = 120;
+
 /// Data which is needed to perform game mode switches.
 #[derive(Copy, Clone, Debug)]
 struct ModePersist {
-    map_scroll_pos_x: u32,
-    map_scroll_pos_y: u32,
+    camera_center_x: u32,
+    camera_center_y: u32,
 }
 
 #[derive(Debug, PartialEq)]
@@ -36,6 +41,9 @@ enum GameMode {
 pub(crate) struct Game {
     sprite_alloc: HWSpriteAllocator,
     map: Box<Map>,
+    camera: Camera,
+    // Index of entities by position, see `spatial`
+    spatial: SpatialHash,
     entities: Entities,
     player_id: usize,
     cursor_id: usize,
@@ -49,6 +57,8 @@ impl Game {
     pub(crate) fn run(&mut self) {
         loop {
             self.update();
+            // Sprites changed by this frame's update are shown during the next vblank
+            self.sprite_alloc.update_oam();
             // For now, tick once every vblank
             // TODO: More power efficiency w/ interrupt
             while VCOUNT.read() >= VBLANK_SCANLINE {}
@@ -59,12 +69,12 @@ impl Game {
     pub fn init() -> Game {
         debug_log!(Subsystems::Game, "Loading game data from FS");
 
+        // Sprite changes are copied into OAM by a vblank handler
+        interrupt::init();
+
         // Initialize hardware sprite management
         debug_log!(Subsystems::Game, "Initializing sprite allocator");
-        let mut sprite_allocator = HWSpriteAllocator::new(
-            &FS.get_file_data_by_name_as_u16_slice("sprite_sharedPal")
-                .unwrap(),
-        );
+        let mut sprite_allocator = HWSpriteAllocator::new(&palettes::SPRITE_SHARED.load::<u16>());
         sprite_allocator.init();
 
         // Stop blanking the screen so that menus are visible
@@ -104,11 +114,20 @@ impl Game {
         player_sprite_handle.set_visibility(true);
         drop(components);
 
+        // The camera eases after the player, but first shows them where enemies are going to come from
+        let mut camera = Camera::new();
+        camera.set_smoothing(Some(Camera::PLAYER_SMOOTHING));
+        if let Some((spawn_x, spawn_y)) = map.get_enemy_spawns().next() {
+            camera.pan_to(spawn_x, spawn_y, SPAWN_PAN_DURATION);
+        }
+
         debug_log!(Subsystems::Game, "Init done. Starting game loop");
 
         return Game {
             sprite_alloc: sprite_allocator,
             map,
+            camera,
+            spatial: SpatialHash::new(),
             entities: e,
             player_id,
             cursor_id,
@@ -121,20 +140,30 @@ impl Game {
 
     fn update(&mut self) {
         // Process player input
-        let start_pressed = self
+        let buttons = self
             .input_system
             .tick(&mut self.entities, &self.live_entity_ids)
             .expect("Failed to tick input system");
         // Start is the button for switching between game modes
-        if start_pressed {
+        if buttons.start_pressed {
             self.toggle_game_mode();
         }
+        // Select is the button for opening the minimap
+        if buttons.select_pressed {
+            self.show_minimap();
+        }
 
         // Simulate all game systems
         if self.game_mode == GameMode::TimeRunning {
             // Simulate
-            MovementSystem::tick(&mut self.entities, &self.live_entity_ids, &mut self.map)
-                .expect("Failed to tick movement system");
+            MovementSystem::tick(
+                &mut self.entities,
+                &self.live_entity_ids,
+                &mut self.map,
+                &mut self.camera,
+                &mut self.spatial,
+            )
+            .expect("Failed to tick movement system");
 
             // Update miners
             mining_system::tick(&mut self.entities, &self.live_entity_ids);
@@ -142,18 +171,66 @@ impl Game {
             // Perform inventory transfers
             item_movement_system::tick(&mut self.entities);
 
+            // Step sprite animations
+            animation_system::tick(&mut self.entities, &self.live_entity_ids);
+
         // Only simulate systems needed for moving the cursor and building
         } else {
-            MovementSystem::tick(&mut self.entities, &self.live_entity_ids, &mut self.map)
-                .expect("Failed to tick movement system");
+            MovementSystem::tick(
+                &mut self.entities,
+                &self.live_entity_ids,
+                &mut self.map,
+                &mut self.camera,
+                &mut self.spatial,
+            )
+            .expect("Failed to tick movement system");
             building_system::tick(
                 &mut self.entities,
                 &mut self.live_entity_ids,
+                &mut self.map,
+                &mut self.spatial,
                 &mut self.sprite_alloc,
             );
         }
     }
 
+    /// Show the minimap, and move the camera to the position the player picks on it, if any.
+    /// The camera is moved by switching to cursor mode and putting the cursor there,
+    /// as the camera always has to follow the player otherwise.
+    fn show_minimap(&mut self) {
+        let (player_x, player_y) = self
+            .entities
+            .borrow::<PositionComponent>()
+            .unwrap()
+            .get(self.player_id)
+            .unwrap()
+            .floor();
+        let minimap = self.map.render_minimap(player_x, player_y);
+        let mut window = Window::new();
+        window.show();
+        let target = window.make_minimap(&minimap, self.camera.center(), &mut self.sprite_alloc);
+        drop(window);
+
+        if let Some((target_x, target_y)) = target {
+            debug_log!(
+                Subsystems::Game,
+                "Moving camera to {} {}",
+                target_x,
+                target_y
+            );
+            if self.game_mode == GameMode::TimeRunning {
+                self.toggle_game_mode();
+            }
+            let mut positions = self.entities.borrow_mut::<PositionComponent>().unwrap();
+            let cursor_position = positions.get_mut(self.cursor_id).unwrap();
+            cursor_position.0 = (
+                Coordinate::from_num(target_x),
+                Coordinate::from_num(target_y),
+            );
+            self.camera.jump_to(&mut self.map, target_x, target_y);
+        }
+    }
+
     /// Switch between game modes.
     fn toggle_game_mode(&mut self) {
         use GameMode::*;
@@ -195,25 +272,26 @@ impl Game {
                 .unwrap();
 
                 // We want to restore the map view to the player's position
-                // once cursor mode is left. Therefore, we have to store the current position of the map.
-                let (map_scroll_pos_x, map_scroll_pos_y) = self.map.get_top_left_corner_coords();
+                // once cursor mode is left. Therefore, we have to store the current position of the camera.
+                let (camera_center_x, camera_center_y) = self.camera.center();
 
                 self.mode_persist = Some(ModePersist {
-                    map_scroll_pos_x,
-                    map_scroll_pos_y,
+                    camera_center_x,
+                    camera_center_y,
                 });
+                // The map scrolls underneath the cursor, which stays in the middle of the screen
+                self.camera.set_dead_zone(0, 0);
+                self.camera.set_smoothing(None);
 
                 // The cursor is unique in that it can build.
                 self.entities
                     .add_component(self.cursor_id, BuilderComponent::new())
                     .unwrap();
 
-                // Recenter cursor on the screen
+                // The cursor's sprite is moved onscreen by the movement system from now on
                 let mut sprite_components = self.entities.borrow_mut::<SpriteComponent>().unwrap();
                 let cursor_sprite_component = sprite_components.get_mut(self.cursor_id).unwrap();
                 let handle = cursor_sprite_component.get_handle();
-                handle.set_x_pos(cursor::INITIAL_CURSOR_ONSCREEN_POS_X);
-                handle.set_y_pos(cursor::INITIAL_CURSOR_ONSCREEN_POS_Y);
                 handle.set_visibility(true);
             }
             TimeStopped => {
@@ -249,18 +327,18 @@ impl Game {
                     .rm_component::<BuilderComponent>(self.cursor_id)
                     .unwrap();
 
-                // Recenter player on the screen
-                let mut sprite_components = self.entities.borrow_mut::<SpriteComponent>().unwrap();
-                let player_sprite_component = sprite_components.get_mut(self.cursor_id).unwrap();
-                let handle = player_sprite_component.get_handle();
-                handle.set_x_pos(player::INITIAL_PLAYER_ONSCREEN_POS_X);
-                handle.set_y_pos(player::INITIAL_PLAYER_ONSCREEN_POS_Y);
-
-                // Scroll map back to the player's position
+                // Move the camera back to the player's position
                 let mode_persist = self.mode_persist.unwrap();
-                self.map
-                    .scroll_abs(mode_persist.map_scroll_pos_x, mode_persist.map_scroll_pos_y);
+                self.camera.jump_to(
+                    &mut self.map,
+                    mode_persist.camera_center_x,
+                    mode_persist.camera_center_y,
+                );
+                self.camera
+                    .set_dead_zone(Camera::DEFAULT_DEAD_ZONE.0, Camera::DEFAULT_DEAD_ZONE.1);
+                self.camera.set_smoothing(Some(Camera::PLAYER_SMOOTHING));
                 // Cursor has to be made invisible again
+                let mut sprite_components = self.entities.borrow_mut::<SpriteComponent>().unwrap();
                 let cursor_sprite_component = sprite_components.get_mut(self.cursor_id).unwrap();
                 let handle = cursor_sprite_component.get_handle();
                 handle.set_visibility(false);
@@ -17,7 +17,21 @@ use gba::io::irq::*;
 /// This also must have a static lifetime because we don't know how long an
 /// ISR will be relevant.
 static mut TIMER1_HANDLER: Option<&'static dyn Fn()> = None;
-static mut VBLANK_HANDLER: Option<&'static dyn Fn()> = None;
+static mut VBLANK_HANDLERS: [Option<&'static dyn Fn()>; NUM_VBLANK_SLOTS] =
+    [None; NUM_VBLANK_SLOTS];
+
+/// Users of the vblank interrupt, each of which may register a handler of its own.
+/// The handlers run in this order, so ones which have to finish early during vblank should come first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VBlankSlot {
+    /// Copying the shadow OAM, which has to be done before the screen is drawn again
+    Sprites = 0,
+    /// Swapping the graphics of animated map tiles, which has to happen before the screen is drawn as well
+    MapTiles = 1,
+    /// Switching and refilling the sound buffers
+    Sound = 2,
+}
+const NUM_VBLANK_SLOTS: usize = 3;
 
 /// Whether an ISR is currently active for `timer1`.
 pub fn timer1_isr_active() -> bool {
@@ -52,41 +66,58 @@ pub fn set_timer1_handler(f: Option<&'static dyn Fn()>) {
     IE.write(flags);
 }
 
-/// Whether an ISR is currently active for `vblank`.
-pub fn vblank_isr_active() -> bool {
-    return IE.read().vblank();
+/// Whether an ISR is currently active for `vblank` in the given slot.
+pub fn vblank_isr_active(slot: VBlankSlot) -> bool {
+    unsafe {
+        return VBLANK_HANDLERS[slot as usize].is_some();
+    }
 }
 
-/// Enable receiving interrupts when `vblank` occurs.
+/// Enable receiving interrupts when `vblank` occurs, running the handler in the given slot.
 ///
 /// Pass `None` as the handler function to disable again.
+/// Interrupts stay enabled as long as any slot has a handler.
 ///
 /// Will panic if interrupts are disabled (meaning you didn't call `init()` first).
-pub fn set_vblank_handler(f: Option<&'static dyn Fn()>) {
+pub fn set_vblank_handler(slot: VBlankSlot, f: Option<&'static dyn Fn()>) {
     if IME.read() == IrqEnableSetting::IRQ_NO {
         panic!("Enable interrupts by calling init() before registering an ISR");
     }
-    unsafe {
-        VBLANK_HANDLER = f;
-    }
+    let any_handler = unsafe {
+        VBLANK_HANDLERS[slot as usize] = f;
+        VBLANK_HANDLERS.iter().any(|handler| handler.is_some())
+    };
+    debug_log!(Interrupt, "Setting vblank handler for {:?}", slot);
 
     // Enable/disable receiving vblank interrupts
     let mut flags = IE.read();
-    match f {
-        Some(_) => {
-            debug_log!(Interrupt, "Enabling handler for vblank");
-            flags = flags.with_vblank(true);
-            display::DISPSTAT.write(display::DISPSTAT.read().with_vblank_irq_enable(true));
-        }
-        None => {
-            debug_log!(Interrupt, "Disabling handler for vblank");
-            flags = flags.with_vblank(false);
-            display::DISPSTAT.write(display::DISPSTAT.read().with_vblank_irq_enable(false));
-        }
+    if any_handler {
+        debug_log!(Interrupt, "Enabling handler for vblank");
+        flags = flags.with_vblank(true);
+        display::DISPSTAT.write(display::DISPSTAT.read().with_vblank_irq_enable(true));
+    } else {
+        debug_log!(Interrupt, "Disabling handler for vblank");
+        flags = flags.with_vblank(false);
+        display::DISPSTAT.write(display::DISPSTAT.read().with_vblank_irq_enable(false));
     }
     IE.write(flags);
 }
 
+/// Runs the given function without any interrupts occurring in between,
+/// which is needed when setting up hardware that ISRs use as well (such as DMA3).
+///
+/// Interrupts are enabled again afterwards only if they were enabled before.
+pub fn without_interrupts<F, R>(func: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let previous = IME.read();
+    IME.write(IrqEnableSetting::IRQ_NO);
+    let result = func();
+    IME.write(previous);
+    return result;
+}
+
 /// Initializes the module.
 ///
 /// Repeated calls will have no effect.
@@ -112,9 +143,11 @@ extern "C" fn irq_handler(flags: IrqFlags) {
     }
     if flags.vblank() {
         unsafe {
-            match VBLANK_HANDLER {
-                Some(handler) => handler(),
-                None => panic!("Received vblank interrupt even though no handler has been registered. Have you registered your handler with the interrupt module?"),
+            if VBLANK_HANDLERS.iter().all(|handler| handler.is_none()) {
+                panic!("Received vblank interrupt even though no handler has been registered. Have you registered your handler with the interrupt module?");
+            }
+            for handler in VBLANK_HANDLERS.iter().flatten() {
+                handler();
             }
         }
     }
@@ -1,4 +1,4 @@
-use crate::sprite::HWSpriteSize;
+use crate::assets::{sprites, SpriteAsset};
 
 use core::fmt;
 
@@ -25,31 +25,44 @@ pub enum Item {
     Pyratite,
     Metaglass,
 }
-// All item sprites must be 16x16 in size.
-pub const ITEM_SPRITE_SIZE: HWSpriteSize = HWSpriteSize::SixteenBySixteen;
 
 impl Item {
-    /// Returns the filename of the item's sprite.
+    /// Returns the item mined from a map tile, given the tile's value in the map's ore layer.
+    /// A value of 0 means there is no ore, all other values are the item's index plus one.
+    pub fn from_ore_layer_value(value: u8) -> Option<Item> {
+        use Item::*;
+        match value {
+            1 => Some(Scrap),
+            2 => Some(Copper),
+            3 => Some(Lead),
+            4 => Some(Graphite),
+            5 => Some(Coal),
+            6 => Some(Titanium),
+            7 => Some(Thorium),
+            _ => None,
+        }
+    }
 
-    pub fn to_sprite_name(self) -> &'static str {
+    /// Returns the item's sprite.
+    pub fn to_sprite(self) -> SpriteAsset {
         use Item::*;
         match self {
-            Scrap => "item_scrapTiles",
-            Copper => "item_copperTiles",
-            Lead => "item_leadTiles",
-            Graphite => "item_graphiteTiles",
-            BlastCompound => "item_blast_compoundTiles",
-            Coal => "item_coalTiles",
-            Metaglass => "item_metaglassTiles",
-            PhaseFabric => "item_phase_fabricTiles",
-            Plastanium => "item_plastaniumTiles",
-            Pyratite => "item_pyratiteTiles",
-            Sand => "item_sandTiles",
-            Silicon => "item_siliconTiles",
-            SporePod => "item_spore_podTiles",
-            SurgeAlloy => "item_surge_alloyTiles",
-            Thorium => "item_thoriumTiles",
-            Titanium => "item_titaniumTiles",
+            Scrap => sprites::ITEM_SCRAP,
+            Copper => sprites::ITEM_COPPER,
+            Lead => sprites::ITEM_LEAD,
+            Graphite => sprites::ITEM_GRAPHITE,
+            BlastCompound => sprites::ITEM_BLAST_COMPOUND,
+            Coal => sprites::ITEM_COAL,
+            Metaglass => sprites::ITEM_METAGLASS,
+            PhaseFabric => sprites::ITEM_PHASE_FABRIC,
+            Plastanium => sprites::ITEM_PLASTANIUM,
+            Pyratite => sprites::ITEM_PYRATITE,
+            Sand => sprites::ITEM_SAND,
+            Silicon => sprites::ITEM_SILICON,
+            SporePod => sprites::ITEM_SPORE_POD,
+            SurgeAlloy => sprites::ITEM_SURGE_ALLOY,
+            Thorium => sprites::ITEM_THORIUM,
+            Titanium => sprites::ITEM_TITANIUM,
         }
     }
 }
@@ -14,6 +14,8 @@
 #![test_runner(crate::test::test_runner)]
 #![reexport_test_harness_main = "test_main"]
 #![feature(const_in_array_repeat_expressions)]
+// Needed to call BIOS functions
+#![feature(asm)]
 // Nice-to-have features
 #![feature(try_trait)]
 // Disable a bunch of clippy lints I disagree with
@@ -29,12 +31,14 @@ use gbfs_rs::GBFSFilesystem;
 
 extern crate arrayref;
 
+mod assets;
 mod components;
 #[cfg(test)]
 mod test;
 #[macro_use]
 mod debug_log;
 mod atomics;
+mod bg_tiles;
 mod entities;
 mod ewram_alloc;
 mod game;
@@ -44,6 +48,7 @@ mod map;
 mod shared_constants;
 mod shared_types;
 mod sound;
+mod spatial;
 mod sprite;
 mod systems;
 mod text;
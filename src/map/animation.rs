@@ -0,0 +1,162 @@
+//! Animated map tiles, like flowing water.
+//!
+//! Instead of changing the tilemaps, the graphics of the animated tiles are swapped out in the charblock,
+//! so that every instance of a tile on screen animates at once, no matter how much of the map is loaded.
+//! This happens in a vblank handler, so that the tiles never change while the screen is drawn.
+//! The frames are generated by the `asset-converter` tool and described by the map's entry in `maps.json`.
+
+use crate::bg_tiles::{BGTileAllocation, MAP_TILES};
+use crate::interrupt::{self, VBlankSlot};
+use crate::shared_constants::TILE_SIZE_BYTES_4BPP;
+
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+
+use serde::Deserialize;
+use spinning_top::{const_spinlock, Spinlock};
+
+/// Number of u32s making up a single 4bpp tile.
+const TILE_SIZE_IN_U32: usize = TILE_SIZE_BYTES_4BPP / 4;
+
+/// Animations of the map which is currently loaded, advanced by the vblank handler,
+/// together with how often animations were started, which identifies their owner.
+/// Only locked briefly outside of it, so that the handler rarely finds it locked.
+static CURRENT_ANIMATIONS: Spinlock<(u32, Option<AnimatedTiles>)> = const_spinlock((0, None));
+
+/// Identifies the animations started by a single call to `start_animating()`,
+/// so that only their owner can stop them.
+#[must_use]
+#[derive(Debug)]
+pub(crate) struct AnimationToken(u32);
+
+/// Tiles of the map's tileset which are animated together.
+#[derive(Deserialize, Clone, Debug)]
+pub struct TileAnimation {
+    // indices of the animated tiles in the map's tileset
+    tiles: Vec<u16>,
+    // number of frames
+    frames: usize,
+    // how many vblanks each frame is shown for
+    frame_duration: u8,
+    // index of the first tile of the first frame in the animated tiles file
+    // Each frame consists of one tile per entry of `tiles`, followed by the next frame.
+    data_offset: usize,
+}
+
+/// All animations of a map, together with the graphics of their frames.
+#[derive(Debug)]
+pub(crate) struct AnimatedTiles {
+    animations: Vec<TileAnimation>,
+    // Tiles of all frames, see `TileAnimation::data_offset`
+    frames: Cow<'static, [u32]>,
+    // Index of the map's first tile in the charblock, set once the animations are started
+    first_tile: usize,
+    // Number of vblanks since the animations were started
+    ticks: u32,
+}
+
+impl AnimatedTiles {
+    /// Creates the map's animations, with `frames` being the decompressed contents of its animated tiles file.
+    /// The tiles initially show the first frame.
+    pub fn new(animations: Vec<TileAnimation>, frames: Cow<'static, [u32]>) -> AnimatedTiles {
+        for animation in &animations {
+            let end = animation.data_offset + animation.frames * animation.tiles.len();
+            if end * TILE_SIZE_IN_U32 > frames.len() {
+                panic!(
+                    "Animation frames missing! Expected at least {} tiles, got {}",
+                    end,
+                    frames.len() / TILE_SIZE_IN_U32
+                );
+            }
+        }
+        return AnimatedTiles {
+            animations,
+            frames,
+            first_tile: 0,
+            ticks: 0,
+        };
+    }
+
+    /// Advances all animations by one vblank, copying the graphics of every tile whose frame changes into
+    /// the map's tiles.
+    /// Has to be called during vblank, so that the change doesn't happen in the middle of drawing the screen.
+    fn tick(&mut self) {
+        if self.animations.is_empty() {
+            return;
+        }
+        // Someone is allocating tiles right now, so try again during the next vblank
+        let allocator = match MAP_TILES.try_lock() {
+            Some(allocator) => allocator,
+            None => return,
+        };
+        self.ticks = self.ticks.wrapping_add(1);
+        for animation in &self.animations {
+            let frame_duration = animation.frame_duration.max(1) as u32;
+            if self.ticks % frame_duration != 0 {
+                continue;
+            }
+            let frame = (self.ticks / frame_duration) as usize % animation.frames;
+            let frame_start = animation.data_offset + frame * animation.tiles.len();
+            for (i, tile) in animation.tiles.iter().enumerate() {
+                let start = (frame_start + i) * TILE_SIZE_IN_U32;
+                allocator.write_tiles_at(
+                    self.first_tile + *tile as usize,
+                    &self.frames[start..start + TILE_SIZE_IN_U32],
+                );
+            }
+        }
+    }
+}
+
+/// Starts animating the map's tiles during each vblank, replacing the animations of the previous map.
+/// `map_tiles` are the map's tiles, which have to stay allocated until `stop_animating()` is called
+/// with the returned token.
+pub(crate) fn start_animating(
+    mut animated_tiles: AnimatedTiles,
+    map_tiles: &BGTileAllocation,
+) -> AnimationToken {
+    for animation in &animated_tiles.animations {
+        for tile in &animation.tiles {
+            if *tile as usize >= map_tiles.num_tiles {
+                panic!(
+                    "Animated tile {} is not part of the map's {} tiles",
+                    tile, map_tiles.num_tiles
+                );
+            }
+        }
+    }
+    animated_tiles.first_tile = map_tiles.first_tile as usize;
+    let mut current = CURRENT_ANIMATIONS.lock();
+    let owner = current.0.wrapping_add(1);
+    *current = (owner, Some(animated_tiles));
+    drop(current);
+    if !interrupt::vblank_isr_active(VBlankSlot::MapTiles) {
+        interrupt::set_vblank_handler(VBlankSlot::MapTiles, Some(&animate_on_vblank));
+    }
+    return AnimationToken(owner);
+}
+
+/// Stops animating the map's tiles, so that they can be freed.
+/// Does nothing if another map's animations replaced them in the meantime.
+pub(crate) fn stop_animating(token: &AnimationToken) {
+    let mut current = CURRENT_ANIMATIONS.lock();
+    if current.0 == token.0 {
+        current.1 = None;
+    }
+}
+
+/// Returns whether any map's tiles are animated right now.
+#[cfg(test)]
+pub(crate) fn is_animating() -> bool {
+    return CURRENT_ANIMATIONS.lock().1.is_some();
+}
+
+/// Advances the animations of the current map, if any.
+fn animate_on_vblank() {
+    // The map is being switched right now, so skip this vblank
+    if let Some(mut animations) = CURRENT_ANIMATIONS.try_lock() {
+        if let Some(animated_tiles) = animations.1.as_mut() {
+            animated_tiles.tick();
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use super::animation::*;
+use crate::bg_tiles::MAP_TILES;
+use crate::test::test;
+
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+
+/// Ensure that a map's animations can only be stopped by their owner,
+/// so that dropping a map after loading the next one keeps the new map animated
+#[test_case]
+fn test_stop_animating_only_own_animations() {
+    test(
+        &|| {
+            let tiles = MAP_TILES.lock().alloc(&[0; 8]).unwrap();
+            let old_map =
+                start_animating(AnimatedTiles::new(Vec::new(), Cow::Borrowed(&[])), &tiles);
+            let new_map =
+                start_animating(AnimatedTiles::new(Vec::new(), Cow::Borrowed(&[])), &tiles);
+            stop_animating(&old_map);
+            assert!(is_animating());
+            stop_animating(&new_map);
+            assert!(!is_animating());
+            MAP_TILES.lock().free(tiles);
+        },
+        "test_stop_animating_only_own_animations",
+        "ensure a map's animations can only be stopped by their owner",
+    );
+}
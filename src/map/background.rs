@@ -1,80 +1,206 @@
 use core::convert::TryInto;
+use core::hash::BuildHasherDefault;
 
+use alloc::borrow::Cow;
 use alloc::vec::Vec;
 
-use gba::io::{background, display, dma};
-use gba::{palram, vram, Color};
+use gba::io::{background, display};
+use gba::vram::SCREEN_BASE_BLOCKS;
+use gba::{palram, Color};
 
+use crate::assets::compression;
+use crate::bg_tiles::{BGTileAllocation, MAP_TILES};
 use crate::shared_constants::*;
+use crate::shared_types::Background;
+
+use hashbrown::HashMap;
+use twox_hash::XxHash64;
+
+/// Edge length of the hardware tilemap in tiles, when using 4 screenblocks.
+pub(super) const HW_MAP_LENGTH_IN_TILES: usize = 2 * BACKING_MAP_LENGTH_IN_TILES;
+/// How many decompressed backing tilemaps are kept around.
+/// The visible area never spans more than 2x2 backing tilemaps, so this avoids decompressing
+/// the same tilemap over and over while scrolling.
+const TILEMAP_CACHE_SIZE: usize = 4;
+/// Screenblock entry used for areas outside of the map, relative to the map's tiles.
+const BLANK_ENTRY: u16 = 0;
+/// Bits of a screenblock entry containing the tile ID.
+const TILE_ID_MASK: u16 = 0x3FF;
 
 /// By default, the GBA only allows up to 32x32 tiles per screenblock.
-/// However, the hardware supports using adjacent screenblocks to produce up to 64x64 tile maps.
-/// We exploit that here by wrapping the scroll control registers so that we know when the edge of
-/// the screenblock is about to come into view, and can seamlessly swap out the screenblock in the direction
-/// the player's heading.
+/// However, the hardware supports using adjacent screenblocks to produce up to 64x64 tile maps,
+/// which is more than enough to cover the screen.
+/// We use these 64x64 tiles as a ring buffer: every map tile has a fixed place in the hardware tilemap
+/// (its coordinates modulo 64), and since the hardware wraps around at the edges, the scroll registers
+/// can simply be set to the map coordinates modulo 512.
+/// Whenever new rows or columns of tiles come into view, only those are written to VRAM.
 ///
 /// This way, we can create the illusion of an arbitrarily sized background.
 ///
+/// The map consists of two such layers: the floor on BG0 and a mostly transparent overlay on BG1,
+/// which contains ores and decorations and can be changed at runtime.
+/// Both layers use the same tiles and palette.
+/// The tiles are allocated in the map's charblock, so the tile IDs of all entries are offset by where
+/// the tiles ended up.
+///
 /// # Safety
 ///
 /// This code assumes that it's in sole control of the display control register's background and size settings,
-/// as well as all charblocks and screenblocks in VRAM and background PALRAM.
-#[derive(Debug, Clone)]
+/// as well as the map's screenblocks in VRAM and background PALRAM.
+#[derive(Debug)]
 pub(crate) struct LargeBackground {
-    backing_tilemaps: Vec<Vec<&'static [u8]>>, // 2D map of
+    // The map's tiles, only None while dropping
+    tiles: Option<BGTileAllocation>,
+    floor: StreamedLayer,
+    overlay: StreamedLayer,
+    // Size of the map, which may be smaller than the backing tilemaps as the last ones are padded
+    width_in_tiles: usize,
+    height_in_tiles: usize,
     // Absolute coordinates of the current top-left corner of the screen on the map.
     // Coordinate system starts at top-left (0,0) of the map.
     curr_x: u32,
     curr_y: u32,
-    // Store which backing tilemap is currently mapped to which screenblock
-    sb0_curr_backing: Option<(usize, usize)>,
-    sb1_curr_backing: Option<(usize, usize)>,
-    sb2_curr_backing: Option<(usize, usize)>,
-    sb3_curr_backing: Option<(usize, usize)>,
+    // Area of the map which is currently present in the hardware tilemaps
+    loaded_area: Option<TileArea>,
 }
 
-/// A 64x64 tile background is made up of 4 32x32 tile screenblocks.
-#[derive(Debug)]
-enum BGScreenblockSlots {
-    Zero,
-    One,
-    Two,
-    Three,
+/// A single background layer of the map.
+#[derive(Debug, Clone)]
+struct StreamedLayer {
+    bg: Background,
+    // First of the 4 screenblocks making up the hardware tilemap
+    screen_base_block: usize,
+    // Tile ID of the map's first tile in the charblock
+    first_tile: u16,
+    backing_tilemaps: Vec<Vec<&'static [u8]>>, // 2D map of possibly compressed tilemaps, indexed by [x][y]
+    // Decompressed backing tilemaps, most recently used first
+    tilemap_cache: Vec<((usize, usize), Cow<'static, [u16]>)>,
+    // Entries changed at runtime, which take precedence over the backing tilemaps
+    changed_entries: HashMap<(usize, usize), u16, BuildHasherDefault<XxHash64>>,
+}
+
+/// A rectangular area of the map, in tiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct TileArea {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl TileArea {
+    /// Returns the tiles which are (at least partially) visible if the top-left corner of the screen is
+    /// at the given pixel coordinates.
+    pub fn visible_from(x: u32, y: u32) -> TileArea {
+        let (x, y) = (x as usize, y as usize);
+        let first_x = x / TILE_SIZE_IN_PX;
+        let first_y = y / TILE_SIZE_IN_PX;
+        let last_x = (x + SCREEN_WIDTH - 1) / TILE_SIZE_IN_PX;
+        let last_y = (y + SCREEN_HEIGHT - 1) / TILE_SIZE_IN_PX;
+        return TileArea {
+            x: first_x,
+            y: first_y,
+            width: last_x - first_x + 1,
+            height: last_y - first_y + 1,
+        };
+    }
+
+    pub fn contains_column(&self, x: usize) -> bool {
+        return x >= self.x && x < self.x + self.width;
+    }
+
+    pub fn contains_row(&self, y: usize) -> bool {
+        return y >= self.y && y < self.y + self.height;
+    }
+
+    pub fn overlaps(&self, other: &TileArea) -> bool {
+        return self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height;
+    }
 }
 
 impl LargeBackground {
     /// Create a new `LargeBackground`.
     /// and initialize the backing backgrounds by writing data to VRAM/background PALRAM.
     ///
-    /// center_x and center_y are the coordinates for where to initially place the center of the displayed area.
-    /// The coordinate system starts at the top-left corner.
+    /// The backing tilemaps of both layers are indexed by [x][y] and all columns must have the same length,
+    /// but the map doesn't have to be square.
+    /// The map is `width_in_tiles` x `height_in_tiles` large, and the backing tilemaps must cover all of it.
+    /// The tiles and tilemaps may be compressed, in which case they're decompressed on their way into VRAM.
     pub(crate) fn init(
-        tiles: &'static [u32],
-        backing_tilemaps: Vec<Vec<&'static [u8]>>,
-        palette: &'static [u16],
+        tiles: &'static [u8],
+        width_in_tiles: usize,
+        height_in_tiles: usize,
+        floor_tilemaps: Vec<Vec<&'static [u8]>>,
+        overlay_tilemaps: Vec<Vec<&'static [u8]>>,
+        palette: &[u16],
     ) -> LargeBackground {
         // Ensure we have at least 1 backing tilemap
-        if backing_tilemaps.is_empty() {
+        if floor_tilemaps.is_empty() {
             panic!("No backing tilemaps supplied");
         }
 
-        if backing_tilemaps[0].is_empty() {
+        if floor_tilemaps[0].is_empty() {
             panic!("No backing tilemaps supplied");
         }
 
+        if floor_tilemaps
+            .iter()
+            .any(|column| column.len() != floor_tilemaps[0].len())
+        {
+            panic!("Backing tilemaps don't form a rectangle");
+        }
+
+        if overlay_tilemaps.len() != floor_tilemaps.len()
+            || overlay_tilemaps
+                .iter()
+                .any(|column| column.len() != floor_tilemaps[0].len())
+        {
+            panic!("Overlay tilemaps don't match the floor tilemaps");
+        }
+
+        if width_in_tiles > floor_tilemaps.len() * BACKING_MAP_LENGTH_IN_TILES
+            || height_in_tiles > floor_tilemaps[0].len() * BACKING_MAP_LENGTH_IN_TILES
+        {
+            panic!(
+                "Map of {}x{} tiles doesn't fit into the backing tilemaps",
+                width_in_tiles, height_in_tiles
+            );
+        }
+
+        // Load tiles into VRAM
+        let tiles = MAP_TILES
+            .lock()
+            .alloc_from_file(tiles)
+            .unwrap_or_else(|err| panic!("Couldn't load map tiles: {}", err));
+        let first_tile = tiles.first_tile;
+
         let mut lbg: LargeBackground = LargeBackground {
-            backing_tilemaps,
+            tiles: Some(tiles),
+            floor: StreamedLayer::new(
+                Background::Zero,
+                BACKGROUND_SCREEN_BASE_BLOCK,
+                first_tile,
+                floor_tilemaps,
+            ),
+            overlay: StreamedLayer::new(
+                Background::One,
+                BACKGROUND_OVERLAY_SCREEN_BASE_BLOCK,
+                first_tile,
+                overlay_tilemaps,
+            ),
+            width_in_tiles,
+            height_in_tiles,
             curr_x: 0,
             curr_y: 0,
-            sb0_curr_backing: None,
-            sb1_curr_backing: None,
-            sb2_curr_backing: None,
-            sb3_curr_backing: None,
+            loaded_area: None,
         };
 
         // Load palette into VRAM
-        if palette.len() > MAP_BG_PALETTE_END - MAP_BG_PALETTE_START {
-            panic!("Attempt to load BG palette that's too large: allowed size is {} entries, was {} entries", MAP_BG_PALETTE_END-MAP_BG_PALETTE_START, palette.len());
+        if palette.len() > MAP_BG_PALETTE_END - MAP_BG_PALETTE_START + 1 {
+            panic!("Attempt to load BG palette that's too large: allowed size is {} entries, was {} entries", MAP_BG_PALETTE_END-MAP_BG_PALETTE_START + 1, palette.len());
         }
         for (i, entry) in palette.iter().enumerate() {
             let idx = palram::index_palram_bg_4bpp(
@@ -84,108 +210,16 @@ impl LargeBackground {
             idx.write(Color(*entry));
         }
 
-        // Use DMA to load tiles into VRAM
-        // We only use charblock 0 for now.
-        if tiles.len() > (CHARBLOCK_SIZE_BYTES / 4) {
-            panic!(
-                "Too many tiles in charblock! Expected up to {}, got {}",
-                (CHARBLOCK_SIZE_BYTES / 4),
-                tiles.len()
-            );
-        }
-        unsafe {
-            dma::DMA3::set_source(tiles.as_ptr());
-            dma::DMA3::set_dest(
-                (vram::VRAM_BASE_USIZE + (BACKGROUND_CHARBLOCK * CHARBLOCK_SIZE_BYTES)) as *mut u32,
-            );
-            dma::DMA3::set_count(tiles.len().try_into().unwrap());
-            dma::DMA3::set_control(
-                dma::DMAControlSetting::new()
-                    .with_enabled(true)
-                    .with_use_32bit(true),
-            );
-        }
+        // Load the initially visible area
+        lbg.load_visible_area();
 
-        // Load the four top-left tilemaps into VRAM (if they exist)
-        lbg.load_backing_tilemap(BGScreenblockSlots::Zero, 0, 0);
-        if lbg.backing_tilemaps.len() > 1 {
-            lbg.load_backing_tilemap(BGScreenblockSlots::One, 1, 0);
-        }
-        if lbg.backing_tilemaps[0].len() > 1 {
-            lbg.load_backing_tilemap(BGScreenblockSlots::Two, 0, 1);
-        }
-        if lbg.backing_tilemaps[0].len() > 1 && lbg.backing_tilemaps.len() > 1 {
-            lbg.load_backing_tilemap(BGScreenblockSlots::Three, 1, 1);
-        }
-
-        // Enable BG0 (which we use)
-        let bg_settings = background::BackgroundControlSetting::new()
-            .with_char_base_block(BACKGROUND_CHARBLOCK.try_into().unwrap())
-            .with_screen_base_block(BACKGROUND_SCREEN_BASE_BLOCK.try_into().unwrap())
-            .with_is_8bpp(false)
-            .with_size(background::BGSize::Three)
-            .with_bg_priority(3);
-        background::BG0CNT.write(bg_settings);
-        let dispcnt = display::display_control();
-        display::set_display_control(dispcnt.with_bg0(true).with_force_vblank(false));
+        // Enable BG0 and BG1 (which we use), with the overlay in front of the floor
+        lbg.floor.enable(3);
+        lbg.overlay.enable(2);
 
         return lbg;
     }
 
-    /// Load the given backing tilemap into VRAM.
-    fn load_backing_tilemap(
-        &mut self,
-        slot: BGScreenblockSlots,
-        backing_map_x: usize,
-        backing_map_y: usize,
-    ) {
-        // Mark the screenblock as occupied
-        let screenblock_index: usize;
-        use BGScreenblockSlots::*;
-        match slot {
-            Zero => {
-                self.sb0_curr_backing = Some((backing_map_x, backing_map_y));
-                screenblock_index = BACKGROUND_SCREEN_BASE_BLOCK;
-            }
-
-            One => {
-                self.sb1_curr_backing = Some((backing_map_x, backing_map_y));
-                screenblock_index = BACKGROUND_SCREEN_BASE_BLOCK + 1;
-            }
-            Two => {
-                self.sb2_curr_backing = Some((backing_map_x, backing_map_y));
-                screenblock_index = BACKGROUND_SCREEN_BASE_BLOCK + 2;
-            }
-            Three => {
-                self.sb3_curr_backing = Some((backing_map_x, backing_map_y));
-                screenblock_index = BACKGROUND_SCREEN_BASE_BLOCK + 3;
-            }
-        }
-
-        let tilemap_ptr = self.backing_tilemaps[backing_map_x][backing_map_y].as_ptr();
-        // Ensure that the pointer is aligned
-        assert_eq!((tilemap_ptr as usize) % 4, 0);
-        // Use DMA to speed up loading
-        let dest_ptr =
-            (vram::VRAM_BASE_USIZE + (screenblock_index * SCREENBLOCK_SIZE_BYTES)) as *mut u32;
-        let num_u32s_to_copy: u16 = self.backing_tilemaps[backing_map_x][backing_map_y]
-            .len()
-            .try_into()
-            .unwrap();
-        // We checked alignment above
-        #[allow(clippy::cast_ptr_alignment)]
-        unsafe {
-            dma::DMA3::set_source(tilemap_ptr as *const u32);
-            dma::DMA3::set_dest(dest_ptr);
-            dma::DMA3::set_count(num_u32s_to_copy);
-            dma::DMA3::set_control(
-                dma::DMAControlSetting::new()
-                    .with_enabled(true)
-                    .with_use_32bit(true),
-            );
-        }
-    }
-
     /// Returns whether the given area is visible on screen right now.
     pub fn is_area_visible(
         &self,
@@ -203,127 +237,89 @@ impl LargeBackground {
     }
 
     /// Scroll the large background to an absolute position.
+    /// The position is clamped so that nothing outside of the map becomes visible.
     pub fn scroll_abs(&mut self, x_pos: u32, y_pos: u32) {
         // New coords of the top-left screen corner
+        let (x_pos, y_pos) = self.clamp_scroll(x_pos as i32, y_pos as i32);
         self.curr_x = x_pos;
         self.curr_y = y_pos;
-        // Load new backing tilemaps if needed
-        self.ensure_correct_backing_tilemaps_are_loaded();
+        // Load newly visible tiles
+        self.load_visible_area();
+        self.write_scroll_registers();
+    }
 
-        // Perform actual hardware scroll
-        background::BG0HOFS.write(self.curr_x.try_into().unwrap());
-        background::BG0VOFS.write(self.curr_y.try_into().unwrap());
+    /// Returns the scroll position closest to the given one which doesn't make any area outside of the map visible.
+    /// Maps smaller than the screen stay in the top-left corner.
+    pub fn clamp_scroll(&self, x: i32, y: i32) -> (u32, u32) {
+        let (width_in_px, height_in_px) = self.get_size_in_px();
+        return (
+            clamp_scroll_axis(x, width_in_px, SCREEN_WIDTH as u32),
+            clamp_scroll_axis(y, height_in_px, SCREEN_HEIGHT as u32),
+        );
     }
 
-    /// Scroll the large background by xy pixels.
-    /// If the indices are positive, scrolling happens down/to the right, if negative up/to the left.
-    /// Parts of the map are dynamically loaded and no longer visible parts vacated.
-    ///
-    /// If scrolling into an area of the map for which no map fragment exists, a panic will occur.
-    /// If scrolling into an area that would have negative absolute coordinates visible on screen, a
-    /// panic will occur.
-    /// The coordinates referenced in the panics are always related to the top-left corner of the displayed area.
-    pub fn scroll(&mut self, delta_x: i32, delta_y: i32) {
-        // New coords of the top-left screen corner
-        let new_x = self.curr_x as i32 + delta_x;
-        let new_y = self.curr_y as i32 + delta_y;
-        // Ensure no negative coordinates would be visible on screen
-        if new_x < 0 || new_y < 0 {
-            panic!("Attempt to scroll into negative coordinates");
-        }
-        self.curr_x = new_x.try_into().unwrap();
-        self.curr_y = new_y.try_into().unwrap();
-        // Load new backing tilemaps if needed
-        self.ensure_correct_backing_tilemaps_are_loaded();
-
-        // Perform actual hardware scroll
-        background::BG0HOFS.write(self.curr_x.try_into().unwrap());
-        background::BG0VOFS.write(self.curr_y.try_into().unwrap());
+    /// Returns the size of the map in pixels.
+    pub fn get_size_in_px(&self) -> (u32, u32) {
+        return (
+            (self.width_in_tiles * TILE_SIZE_IN_PX) as u32,
+            (self.height_in_tiles * TILE_SIZE_IN_PX) as u32,
+        );
     }
 
-    fn ensure_correct_backing_tilemaps_are_loaded(&mut self) {
-        // Calculate which backing tilemaps would be in view by scrolling
-        // by checking where the 4 corners of the screen would end up in.
-        let new_top_left_x: usize = self.curr_x.try_into().unwrap();
-        let new_top_left_y: usize = self.curr_y.try_into().unwrap();
-        let (
-            (new_top_right_x, new_top_right_y),
-            (new_bottom_right_x, new_bottom_right_y),
-            (new_bottom_left_x, new_bottom_left_y),
-        ) = coords_from_top_left_for_all_screen_corners(new_top_left_x, new_top_left_y);
-        let (new_top_left_backing_x, new_top_left_backing_y) =
-            coords_to_backing_tilemap_indices(new_top_left_x, new_top_left_y);
-        let (new_top_right_backing_x, new_top_right_backing_y) =
-            coords_to_backing_tilemap_indices(new_top_right_x, new_top_right_y);
-        let (new_bottom_right_backing_x, new_bottom_right_backing_y) =
-            coords_to_backing_tilemap_indices(new_bottom_right_x, new_bottom_right_y);
-        let (new_bottom_left_backing_x, new_bottom_left_backing_y) =
-            coords_to_backing_tilemap_indices(new_bottom_left_x, new_bottom_left_y);
-
-        // For each corner, check whether the backing tilemaps are already present in VRAM
-        // TODO: Ensure they're in the correct one of the four screenblocks
-        if self
-            .get_backing_tilemap_loaded_slot(new_top_left_backing_x, new_top_left_backing_y)
-            .is_none()
-        {
-            self.load_backing_tilemap(
-                BGScreenblockSlots::Zero,
-                new_top_left_backing_x,
-                new_top_left_backing_y,
-            );
-        }
-        if self
-            .get_backing_tilemap_loaded_slot(new_top_right_backing_x, new_top_right_backing_y)
-            .is_none()
-        {
-            self.load_backing_tilemap(
-                BGScreenblockSlots::One,
-                new_top_right_backing_x,
-                new_top_right_backing_y,
-            );
-        }
-        if self
-            .get_backing_tilemap_loaded_slot(new_bottom_right_backing_x, new_bottom_right_backing_y)
-            .is_none()
-        {
-            self.load_backing_tilemap(
-                BGScreenblockSlots::Three,
-                new_bottom_right_backing_x,
-                new_bottom_right_backing_y,
-            );
-        }
-        if self
-            .get_backing_tilemap_loaded_slot(new_bottom_left_backing_x, new_bottom_left_backing_y)
-            .is_none()
-        {
-            self.load_backing_tilemap(
-                BGScreenblockSlots::Two,
-                new_bottom_left_backing_x,
-                new_bottom_left_backing_y,
-            );
+    /// Returns the map's tiles in the charblock.
+    pub fn tiles(&self) -> &BGTileAllocation {
+        return self.tiles.as_ref().unwrap();
+    }
+
+    /// Returns the screenblock entry referring to the given entry's tile in the map's tileset
+    /// as it's stored in the charblock.
+    pub fn map_tile_entry(&self, entry: u16) -> u16 {
+        return self.floor.offset_entry(entry);
+    }
+
+    /// Changes the overlay's screenblock entry at the given tile coordinates.
+    /// The entry's tile ID refers to the whole charblock, see `map_tile_entry`.
+    /// The change is kept until the tile is changed again, even when it's scrolled out of view.
+    pub fn set_overlay_entry(&mut self, x: usize, y: usize, entry: u16) {
+        self.overlay.changed_entries.insert((x, y), entry);
+        // Tiles which aren't loaded right now get the new entry once they're streamed in
+        if let Some(loaded) = self.loaded_area {
+            if loaded.contains_column(x) && loaded.contains_row(y) {
+                self.overlay.write_hw_entry(x, y, entry);
+            }
         }
     }
 
-    fn get_backing_tilemap_loaded_slot(
-        &mut self,
-        backing_x: usize,
-        backing_y: usize,
-    ) -> Option<BGScreenblockSlots> {
-        match self.sb0_curr_backing {
-            Some((_, _)) => return Some(BGScreenblockSlots::Zero),
-            None => match self.sb1_curr_backing {
-                Some((_, _)) => return Some(BGScreenblockSlots::One),
-                None => match self.sb2_curr_backing {
-                    Some((_, _)) => return Some(BGScreenblockSlots::Two),
-                    None => match self.sb3_curr_backing {
-                        Some((_, _)) => return Some(BGScreenblockSlots::Three),
-                        None => return None,
-                    },
-                },
-            },
+    /// Undoes changes of the overlay's screenblock entry at the given tile coordinates,
+    /// so that it shows the map's own tile again.
+    pub fn reset_overlay_entry(&mut self, x: usize, y: usize) {
+        self.overlay.changed_entries.remove(&(x, y));
+        if let Some(loaded) = self.loaded_area {
+            if loaded.contains_column(x) && loaded.contains_row(y) {
+                let entry = self.overlay.get_map_entry(x, y);
+                self.overlay.write_hw_entry(x, y, entry);
+            }
         }
     }
 
+    /// Perform the actual hardware scroll.
+    fn write_scroll_registers(&self) {
+        // The hardware tilemap wraps around, so only the position within it matters
+        let hw_map_length_in_px = (HW_MAP_LENGTH_IN_TILES * TILE_SIZE_IN_PX) as u32;
+        let x = (self.curr_x % hw_map_length_in_px) as u16;
+        let y = (self.curr_y % hw_map_length_in_px) as u16;
+        self.floor.bg.set_scroll(x, y);
+        self.overlay.bg.set_scroll(x, y);
+    }
+
+    /// Writes all tiles which are visible now but weren't loaded before into the hardware tilemaps.
+    fn load_visible_area(&mut self) {
+        let visible = TileArea::visible_from(self.curr_x, self.curr_y);
+        self.floor.load_area(visible, self.loaded_area);
+        self.overlay.load_area(visible, self.loaded_area);
+        self.loaded_area = Some(visible);
+    }
+
     /// Returns the (x,y) coordinates of the top-left corner of the currently visible
     /// background area.
     pub fn get_top_left_corner_coords(&self) -> (u32, u32) {
@@ -331,29 +327,163 @@ impl LargeBackground {
     }
 }
 
-/// Looks up the indices of backing tilemap the coordinates belong to.
-fn coords_to_backing_tilemap_indices(x: usize, y: usize) -> (usize, usize) {
-    return (
-        (x / (BACKING_MAP_LENGTH_IN_TILES * TILE_SIZE_IN_PX)),
-        (y / (BACKING_MAP_LENGTH_IN_TILES * TILE_SIZE_IN_PX)),
-    );
+impl Drop for LargeBackground {
+    fn drop(&mut self) {
+        if let Some(tiles) = self.tiles.take() {
+            MAP_TILES.lock().free(tiles);
+        }
+    }
+}
+
+impl StreamedLayer {
+    fn new(
+        bg: Background,
+        screen_base_block: usize,
+        first_tile: u16,
+        backing_tilemaps: Vec<Vec<&'static [u8]>>,
+    ) -> StreamedLayer {
+        return StreamedLayer {
+            bg,
+            screen_base_block,
+            first_tile,
+            backing_tilemaps,
+            tilemap_cache: Vec::with_capacity(TILEMAP_CACHE_SIZE),
+            changed_entries: Default::default(),
+        };
+    }
+
+    /// Configures the layer's background with the given priority and makes it visible.
+    fn enable(&self, priority: u16) {
+        self.bg.write(
+            background::BackgroundControlSetting::new()
+                .with_char_base_block(BACKGROUND_CHARBLOCK.try_into().unwrap())
+                .with_screen_base_block(self.screen_base_block.try_into().unwrap())
+                .with_is_8bpp(false)
+                .with_size(background::BGSize::Three)
+                .with_bg_priority(priority),
+        );
+        self.bg.set_visible(true);
+        let dispcnt = display::display_control();
+        display::set_display_control(dispcnt.with_force_vblank(false));
+    }
+
+    /// Writes all tiles in `visible` which aren't in `loaded` into the hardware tilemap.
+    fn load_area(&mut self, visible: TileArea, loaded: Option<TileArea>) {
+        match loaded {
+            // Only stream in the rows and columns which came into view
+            Some(loaded) if loaded.overlaps(&visible) => {
+                for x in visible.x..visible.x + visible.width {
+                    if !loaded.contains_column(x) {
+                        self.load_column(x, visible.y, visible.height);
+                    }
+                }
+                for y in visible.y..visible.y + visible.height {
+                    if !loaded.contains_row(y) {
+                        self.load_row(y, visible.x, visible.width);
+                    }
+                }
+            }
+            // Nothing useful in VRAM yet, e.g. because we jumped to a different part of the map
+            _ => {
+                for y in visible.y..visible.y + visible.height {
+                    self.load_row(y, visible.x, visible.width);
+                }
+            }
+        }
+    }
+
+    /// Loads `width` tiles of row `y`, starting at column `first_x`.
+    fn load_row(&mut self, y: usize, first_x: usize, width: usize) {
+        for x in first_x..first_x + width {
+            let entry = self.get_map_entry(x, y);
+            self.write_hw_entry(x, y, entry);
+        }
+    }
+
+    /// Loads `height` tiles of column `x`, starting at row `first_y`.
+    fn load_column(&mut self, x: usize, first_y: usize, height: usize) {
+        for y in first_y..first_y + height {
+            let entry = self.get_map_entry(x, y);
+            self.write_hw_entry(x, y, entry);
+        }
+    }
+
+    /// Returns the screenblock entry of the map at the given tile coordinates.
+    fn get_map_entry(&mut self, x: usize, y: usize) -> u16 {
+        if let Some(entry) = self.changed_entries.get(&(x, y)) {
+            return *entry;
+        }
+        let backing_x = x / BACKING_MAP_LENGTH_IN_TILES;
+        let backing_y = y / BACKING_MAP_LENGTH_IN_TILES;
+        let entry = match self.get_backing_tilemap(backing_x, backing_y) {
+            Some(tilemap) => {
+                tilemap[(x % BACKING_MAP_LENGTH_IN_TILES)
+                    + (y % BACKING_MAP_LENGTH_IN_TILES) * BACKING_MAP_LENGTH_IN_TILES]
+            }
+            None => BLANK_ENTRY,
+        };
+        return self.offset_entry(entry);
+    }
+
+    /// Moves the tile ID of an entry of the backing tilemaps to where the map's tiles are in the charblock.
+    fn offset_entry(&self, entry: u16) -> u16 {
+        return (entry & !TILE_ID_MASK) | ((entry & TILE_ID_MASK) + self.first_tile);
+    }
+
+    /// Returns the decompressed backing tilemap, or None if it's outside of the map.
+    fn get_backing_tilemap(&mut self, backing_x: usize, backing_y: usize) -> Option<&[u16]> {
+        let data = *self.backing_tilemaps.get(backing_x)?.get(backing_y)?;
+        let cached = self
+            .tilemap_cache
+            .iter()
+            .position(|(pos, _)| *pos == (backing_x, backing_y));
+        match cached {
+            Some(0) => {}
+            Some(i) => {
+                let entry = self.tilemap_cache.remove(i);
+                self.tilemap_cache.insert(0, entry);
+            }
+            None => {
+                if self.tilemap_cache.len() == TILEMAP_CACHE_SIZE {
+                    self.tilemap_cache.pop();
+                }
+                self.tilemap_cache
+                    .insert(0, ((backing_x, backing_y), compression::load(data)));
+            }
+        }
+        return Some(&*self.tilemap_cache[0].1);
+    }
+
+    /// Writes the screenblock entry for the map tile at the given coordinates into the hardware tilemap.
+    fn write_hw_entry(&self, x: usize, y: usize, entry: u16) {
+        let (screenblock, index) = hw_entry_position(x, y);
+        // TODO: This cast should be abstracted away by the lib; submit a PR
+        unsafe {
+            SCREEN_BASE_BLOCKS
+                .index(self.screen_base_block + screenblock)
+                .cast::<u16>()
+                .offset(index as isize)
+                .write(entry);
+        }
+    }
+}
+
+/// Clamps the scroll position on one axis so that the screen stays within the map.
+pub(super) fn clamp_scroll_axis(pos: i32, map_length_in_px: u32, screen_length_in_px: u32) -> u32 {
+    let max = map_length_in_px.saturating_sub(screen_length_in_px);
+    return (pos.max(0) as u32).min(max);
 }
 
-/// Given the coordinates for the top-left corner of the visible area,
-/// calculate the coords for top-right, bottom-right, bottom-left.
-fn coords_from_top_left_for_all_screen_corners(
-    x: usize,
-    y: usize,
-) -> ((usize, usize), (usize, usize), (usize, usize)) {
-    let top_right_x = x + SCREEN_WIDTH;
-    let top_right_y = y;
-    let bottom_right_x = x + SCREEN_WIDTH;
-    let bottom_right_y = y + SCREEN_HEIGHT;
-    let bottom_left_x = x;
-    let bottom_left_y = y + SCREEN_HEIGHT;
-    return (
-        (top_right_x, top_right_y),
-        (bottom_right_x, bottom_right_y),
-        (bottom_left_x, bottom_left_y),
-    );
+/// Returns the screenblock (relative to the first of the 4) and the index within it that the map tile
+/// at the given coordinates is stored at.
+pub(super) fn hw_entry_position(x: usize, y: usize) -> (usize, usize) {
+    let hw_x = x % HW_MAP_LENGTH_IN_TILES;
+    let hw_y = y % HW_MAP_LENGTH_IN_TILES;
+    // The screenblocks are laid out as
+    // 0 1
+    // 2 3
+    let screenblock = hw_x / BACKING_MAP_LENGTH_IN_TILES + 2 * (hw_y / BACKING_MAP_LENGTH_IN_TILES);
+    let index = (hw_x % BACKING_MAP_LENGTH_IN_TILES)
+        + (hw_y % BACKING_MAP_LENGTH_IN_TILES) * BACKING_MAP_LENGTH_IN_TILES;
+    return (screenblock, index);
 }
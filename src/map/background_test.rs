@@ -0,0 +1,80 @@
+use super::background::*;
+use crate::shared_constants::{SCREEN_HEIGHT_TILES, SCREEN_WIDTH_TILES};
+use crate::test::test;
+
+/// Ensure the visible area includes partially visible tiles
+#[test_case]
+fn test_visible_area() {
+    test(
+        &|| {
+            let aligned = TileArea::visible_from(0, 0);
+            assert_eq!(aligned.width, SCREEN_WIDTH_TILES);
+            assert_eq!(aligned.height, SCREEN_HEIGHT_TILES);
+
+            let unaligned = TileArea::visible_from(3, 8 * 70 + 1);
+            assert_eq!(unaligned.x, 0);
+            assert_eq!(unaligned.y, 70);
+            assert_eq!(unaligned.width, SCREEN_WIDTH_TILES + 1);
+            assert_eq!(unaligned.height, SCREEN_HEIGHT_TILES + 1);
+        },
+        "test_visible_area",
+        "ensure the visible area includes partially visible tiles",
+    );
+}
+
+/// Ensure only the rows and columns which scrolled into view are considered new
+#[test_case]
+fn test_visible_area_scrolling() {
+    test(
+        &|| {
+            let before = TileArea::visible_from(16, 16);
+            let after = TileArea::visible_from(8, 24);
+            assert!(before.overlaps(&after));
+            assert!(!before.contains_column(after.x));
+            assert!(before.contains_column(after.x + 1));
+            assert!(before.contains_row(after.y));
+            assert!(!before.contains_row(after.y + after.height - 1));
+
+            let far_away = TileArea::visible_from(8 * 100, 0);
+            assert!(!before.overlaps(&far_away));
+        },
+        "test_visible_area_scrolling",
+        "ensure only newly visible rows and columns are streamed",
+    );
+}
+
+/// Ensure map tiles wrap around the 64x64 hardware tilemap
+#[test_case]
+fn test_hw_entry_position() {
+    test(
+        &|| {
+            assert_eq!(hw_entry_position(0, 0), (0, 0));
+            assert_eq!(hw_entry_position(33, 0), (1, 1));
+            assert_eq!(hw_entry_position(1, 34), (2, 2 * 32 + 1));
+            assert_eq!(hw_entry_position(63, 63), (3, 32 * 32 - 1));
+            // Tiles further than 64 tiles apart share a hardware entry
+            assert_eq!(
+                hw_entry_position(HW_MAP_LENGTH_IN_TILES + 5, 3 * HW_MAP_LENGTH_IN_TILES + 7),
+                hw_entry_position(5, 7)
+            );
+        },
+        "test_hw_entry_position",
+        "ensure map tiles wrap around the hardware tilemap",
+    );
+}
+
+/// Ensure the screen can't be scrolled past any edge of the map
+#[test_case]
+fn test_clamp_scroll() {
+    test(
+        &|| {
+            assert_eq!(clamp_scroll_axis(-5, 1000, 240), 0);
+            assert_eq!(clamp_scroll_axis(300, 1000, 240), 300);
+            assert_eq!(clamp_scroll_axis(761, 1000, 240), 760);
+            // Maps smaller than the screen can't be scrolled at all
+            assert_eq!(clamp_scroll_axis(10, 100, 160), 0);
+        },
+        "test_clamp_scroll",
+        "ensure the screen can't be scrolled past any edge of the map",
+    );
+}
@@ -0,0 +1,127 @@
+//! Buildings which are drawn into the map's overlay layer instead of using hardware sprites.
+//!
+//! This saves OAM entries for things that actually move, like units and items.
+//! The building tiles are generated by the `asset-converter` tool and described by `buildings.json`.
+//! They get their own palette bank and are allocated in the map's charblock alongside the map's tiles.
+
+use crate::assets::{files, palettes};
+use crate::bg_tiles::{BGTileAllocation, MAP_TILES};
+use crate::shared_constants::*;
+
+use core::str;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use gba::{palram, Color};
+use serde::Deserialize;
+
+/// Buildings which can be drawn as tiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Building {
+    CopperWall,
+    MechanicalDrill,
+    Container,
+}
+
+impl Building {
+    /// Returns Mindustry's name for the building, which it's listed under in `buildings.json`.
+    fn mindustry_name(self) -> &'static str {
+        use Building::*;
+        match self {
+            CopperWall => return "copper-wall",
+            MechanicalDrill => return "mechanical-drill",
+            Container => return "container",
+        }
+    }
+
+    /// Whether units can't move through the building.
+    pub fn is_solid(self) -> bool {
+        use Building::*;
+        match self {
+            CopperWall | MechanicalDrill | Container => return true,
+        }
+    }
+}
+
+/// Top-level struct of `buildings.json`.
+#[derive(Deserialize)]
+struct BuildingsFile {
+    buildings: Vec<BuildingEntry>,
+}
+
+/// Describes how a single building is drawn.
+#[derive(Deserialize, Clone, Debug)]
+struct BuildingEntry {
+    // Mindustry's name for the building
+    name: String,
+    // width and height in tiles
+    size: usize,
+    // screenblock entries in left-to-right, top-to-bottom order, referring to the building tiles
+    entries: Vec<u16>,
+}
+
+/// The building tiles as loaded into the map's charblock.
+#[derive(Debug)]
+pub(super) struct BuildingTileset {
+    // Only None while dropping
+    tiles: Option<BGTileAllocation>,
+    buildings: Vec<BuildingEntry>,
+}
+
+impl BuildingTileset {
+    /// Loads the building tiles into the map's charblock
+    /// and their palette into the buildings' palette bank.
+    pub fn load() -> BuildingTileset {
+        let description = files::BUILDINGS_JSON.load::<u8>();
+        let description: BuildingsFile =
+            serde_json::from_str(str::from_utf8(&description).unwrap()).unwrap();
+
+        let tiles = MAP_TILES
+            .lock()
+            .alloc_from_fs(&files::BUILDING_TILES)
+            .unwrap_or_else(|err| panic!("Couldn't load building tiles: {}", err));
+        let first_tile = tiles.first_tile;
+
+        let palette = palettes::BUILDING.load::<u16>();
+        for (i, entry) in palette.iter().enumerate() {
+            let idx = palram::index_palram_bg_4bpp(
+                ((BUILDING_BG_PALETTE_START + i) / 16) as u8,
+                ((BUILDING_BG_PALETTE_START + i) % 16) as u8,
+            );
+            idx.write(Color(*entry));
+        }
+
+        // Point the entries at where the tiles ended up
+        let palbank = (BUILDING_BG_PALETTE_START / 16) as u16;
+        let mut buildings = description.buildings;
+        for building in buildings.iter_mut() {
+            for entry in building.entries.iter_mut() {
+                *entry = ((*entry & 0x3FF) + first_tile) | (palbank << 12);
+            }
+        }
+        return BuildingTileset {
+            tiles: Some(tiles),
+            buildings,
+        };
+    }
+
+    /// Returns the edge length of the building in tiles and its screenblock entries,
+    /// in left-to-right, top-to-bottom order.
+    pub fn get(&self, building: Building) -> (usize, &[u16]) {
+        let entry = self
+            .buildings
+            .iter()
+            .find(|entry| entry.name == building.mindustry_name())
+            .expect("Building has no tiles");
+        return (entry.size, &entry.entries);
+    }
+}
+
+impl Drop for BuildingTileset {
+    fn drop(&mut self) {
+        if let Some(tiles) = self.tiles.take() {
+            MAP_TILES.lock().free(tiles);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use super::map::Map;
+
+use crate::shared_constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use fixed::{types::extra::U8, FixedI32};
+
+/// Position of the camera on the map.
+/// It's fractional so that the camera can ease towards its destination, and signed because it may
+/// want to go past the map's top-left corner before being clamped.
+pub(super) type CameraCoordinate = FixedI32<U8>;
+
+/// Decides which part of the map is visible, by scrolling the map.
+///
+/// The camera follows a target position, usually the center of the entity which has
+/// `MovementComponent::keep_camera_centered_on` set.
+/// The target may move within a dead zone around the middle of the screen without the camera moving,
+/// and once it leaves the dead zone, the camera either follows at once or eases towards it.
+/// The map is never scrolled past its edges, so near them the target moves on screen instead.
+///
+/// For a while, the camera can also be pointed at something else (e.g. where enemies spawn),
+/// after which it returns to its target.
+#[derive(Debug)]
+pub struct Camera {
+    /// Position on the map which is in the middle of the screen, in pixels
+    center: (CameraCoordinate, CameraCoordinate),
+    /// Position the camera follows, in pixels
+    target: Option<(u32, u32)>,
+    /// Position the camera looks at instead of the target, and for how many more frames it does so
+    temporary_target: Option<((u32, u32), u32)>,
+    /// Size of the area around the middle of the screen the target can move in without the camera following, in pixels
+    dead_zone: (u32, u32),
+    /// The camera moves by 1/n of the remaining distance to its destination each frame.
+    /// If None, it moves there at once.
+    smoothing: Option<u32>,
+}
+
+impl Camera {
+    /// Default (width, height) of the dead zone in pixels, a fifth of the screen.
+    pub const DEFAULT_DEAD_ZONE: (u32, u32) = (48, 32);
+    /// Smoothing divisor used while following the player, see `set_smoothing()`.
+    pub const PLAYER_SMOOTHING: u32 = 8;
+
+    /// Create a camera looking at the map's top-left corner, without a target.
+    pub fn new() -> Camera {
+        return Camera {
+            center: (
+                CameraCoordinate::from_num(SCREEN_WIDTH / 2),
+                CameraCoordinate::from_num(SCREEN_HEIGHT / 2),
+            ),
+            target: None,
+            temporary_target: None,
+            dead_zone: Camera::DEFAULT_DEAD_ZONE,
+            smoothing: None,
+        };
+    }
+
+    /// Set the size of the dead zone, in pixels.
+    /// A size of 0 keeps the target in the middle of the screen at all times.
+    pub fn set_dead_zone(&mut self, width: u32, height: u32) {
+        self.dead_zone = (width, height);
+    }
+
+    /// Make the camera ease towards its destination by moving 1/`divisor` of the remaining distance each frame.
+    /// If None, the camera moves there at once.
+    pub fn set_smoothing(&mut self, divisor: Option<u32>) {
+        assert!(divisor != Some(0), "Camera smoothing divisor must not be 0");
+        self.smoothing = divisor;
+    }
+
+    /// Set the position (in pixels) the camera follows.
+    /// Has to be called whenever the target moves.
+    pub fn follow(&mut self, x: u32, y: u32) {
+        self.target = Some((x, y));
+    }
+
+    /// Point the camera at the given position (in pixels) for the given number of frames,
+    /// after which it goes back to following its target.
+    pub fn pan_to(&mut self, x: u32, y: u32, duration_in_frames: u32) {
+        self.temporary_target = Some(((x, y), duration_in_frames));
+    }
+
+    /// Move the camera so that the given position (in pixels) is in the middle of the screen right away,
+    /// as far as the map's edges allow.
+    pub fn jump_to(&mut self, map: &mut Map, x: u32, y: u32) {
+        self.scroll_to(
+            map,
+            CameraCoordinate::from_num(x),
+            CameraCoordinate::from_num(y),
+        );
+    }
+
+    /// Returns the position on the map (in pixels) which is in the middle of the screen.
+    pub fn center(&self) -> (u32, u32) {
+        return (self.center.0.to_num(), self.center.1.to_num());
+    }
+
+    /// Move the camera towards its destination, scrolling the map.
+    /// Has to be called once per frame, after the target has moved.
+    pub fn update(&mut self, map: &mut Map) {
+        let (x, y) = self.next_center();
+        self.scroll_to(map, x, y);
+    }
+
+    /// Returns where the middle of the screen should be this frame,
+    /// without taking the map's edges into account.
+    pub(super) fn next_center(&mut self) -> (CameraCoordinate, CameraCoordinate) {
+        let destination = match self.temporary_target {
+            Some(((x, y), frames_left)) => {
+                self.temporary_target = if frames_left > 1 {
+                    Some(((x, y), frames_left - 1))
+                } else {
+                    None
+                };
+                (CameraCoordinate::from_num(x), CameraCoordinate::from_num(y))
+            }
+            None => match self.target {
+                Some((x, y)) => (
+                    follow_axis(self.center.0, x, self.dead_zone.0),
+                    follow_axis(self.center.1, y, self.dead_zone.1),
+                ),
+                None => self.center,
+            },
+        };
+        return match self.smoothing {
+            Some(divisor) => (
+                self.center.0 + (destination.0 - self.center.0) / divisor as i32,
+                self.center.1 + (destination.1 - self.center.1) / divisor as i32,
+            ),
+            None => destination,
+        };
+    }
+
+    /// Scroll the map so that the given position is in the middle of the screen, as far as its edges allow.
+    fn scroll_to(&mut self, map: &mut Map, x: CameraCoordinate, y: CameraCoordinate) {
+        let half_screen_width = CameraCoordinate::from_num(SCREEN_WIDTH / 2);
+        let half_screen_height = CameraCoordinate::from_num(SCREEN_HEIGHT / 2);
+        let wanted_x: i32 = (x - half_screen_width).to_num();
+        let wanted_y: i32 = (y - half_screen_height).to_num();
+        let (scroll_x, scroll_y) = map.clamp_scroll(wanted_x, wanted_y);
+        if (scroll_x, scroll_y) != map.get_top_left_corner_coords() {
+            map.scroll_abs(scroll_x, scroll_y);
+        }
+
+        // The camera stops at the edges as well, so that it starts moving again as soon as the target turns around
+        self.center.0 = if scroll_x as i32 == wanted_x {
+            x
+        } else {
+            CameraCoordinate::from_num(scroll_x) + half_screen_width
+        };
+        self.center.1 = if scroll_y as i32 == wanted_y {
+            y
+        } else {
+            CameraCoordinate::from_num(scroll_y) + half_screen_height
+        };
+    }
+}
+
+/// Returns where the camera has to be on one axis so that the target is within the dead zone,
+/// moving it as little as possible.
+fn follow_axis(center: CameraCoordinate, target: u32, dead_zone: u32) -> CameraCoordinate {
+    let target = CameraCoordinate::from_num(target);
+    let half_dead_zone = CameraCoordinate::from_num(dead_zone / 2);
+    if target < center - half_dead_zone {
+        return target + half_dead_zone;
+    } else if target > center + half_dead_zone {
+        return target - half_dead_zone;
+    }
+    return center;
+}
@@ -0,0 +1,68 @@
+use super::camera::*;
+use crate::shared_constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::test::test;
+
+const CENTER_X: u32 = (SCREEN_WIDTH / 2) as u32;
+const CENTER_Y: u32 = (SCREEN_HEIGHT / 2) as u32;
+
+/// Returns the next center of the camera, rounded down to whole pixels.
+fn next_center(camera: &mut Camera) -> (i32, i32) {
+    let (x, y) = camera.next_center();
+    return (x.to_num(), y.to_num());
+}
+
+/// Ensure the camera only follows its target once it leaves the dead zone
+#[test_case]
+fn test_camera_dead_zone() {
+    test(
+        &|| {
+            let mut camera = Camera::new();
+            camera.set_dead_zone(40, 20);
+            camera.follow(CENTER_X + 20, CENTER_Y - 10);
+            assert_eq!(next_center(&mut camera), (CENTER_X as i32, CENTER_Y as i32));
+
+            // The camera moves just enough for the target to be at the edge of the dead zone
+            camera.follow(CENTER_X + 25, CENTER_Y - 13);
+            assert_eq!(
+                next_center(&mut camera),
+                (CENTER_X as i32 + 5, CENTER_Y as i32 - 3)
+            );
+
+            camera.set_dead_zone(0, 0);
+            assert_eq!(
+                next_center(&mut camera),
+                (CENTER_X as i32 + 25, CENTER_Y as i32 - 13)
+            );
+        },
+        "test_camera_dead_zone",
+        "ensure the camera only follows its target once it leaves the dead zone",
+    );
+}
+
+/// Ensure the camera eases towards its destination and can be panned elsewhere temporarily
+#[test_case]
+fn test_camera_smoothing_and_panning() {
+    test(
+        &|| {
+            let mut camera = Camera::new();
+            camera.set_dead_zone(0, 0);
+            camera.set_smoothing(Some(4));
+            camera.follow(CENTER_X + 40, CENTER_Y);
+            assert_eq!(
+                next_center(&mut camera),
+                (CENTER_X as i32 + 10, CENTER_Y as i32)
+            );
+
+            camera.set_smoothing(None);
+            camera.pan_to(1000, 2000, 1);
+            assert_eq!(next_center(&mut camera), (1000, 2000));
+            // Back to the target afterwards
+            assert_eq!(
+                next_center(&mut camera),
+                (CENTER_X as i32 + 40, CENTER_Y as i32)
+            );
+        },
+        "test_camera_smoothing_and_panning",
+        "ensure the camera eases towards its destination and can be panned elsewhere temporarily",
+    );
+}
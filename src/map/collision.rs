@@ -0,0 +1,87 @@
+//! Which parts of the map units can't move through.
+//!
+//! Terrain is solid per Mindustry tile, as generated by the `asset-converter` tool.
+//! Buildings are placed on the tile grid, so they make single tiles solid instead.
+
+use crate::shared_constants::TILE_SIZE_IN_PX;
+
+use core::hash::BuildHasherDefault;
+
+use alloc::borrow::Cow;
+
+use hashbrown::HashSet;
+use twox_hash::XxHash64;
+
+/// How many pixels wide and high a Mindustry tile is.
+const BLOCK_SIZE_IN_PX: u32 = 2 * TILE_SIZE_IN_PX as u32;
+
+/// Solidity of every part of the map.
+#[derive(Debug)]
+pub(crate) struct CollisionMap {
+    /// One byte per Mindustry tile, see `MapEntry::solid_layer`
+    solid_blocks: Cow<'static, [u8]>,
+    /// Width of the map, in Mindustry tiles
+    width_in_blocks: usize,
+    /// Tiles (x, y) which were made solid after loading the map, e.g. by buildings
+    solid_tiles: HashSet<(u32, u32), BuildHasherDefault<XxHash64>>,
+}
+
+impl CollisionMap {
+    /// `solid_blocks` contains one byte per Mindustry tile, in rows `width_in_blocks` entries wide.
+    /// Every non-zero entry is solid.
+    pub(crate) fn new(solid_blocks: Cow<'static, [u8]>, width_in_blocks: usize) -> CollisionMap {
+        return CollisionMap {
+            solid_blocks,
+            width_in_blocks,
+            solid_tiles: Default::default(),
+        };
+    }
+
+    /// Makes the tile at the given position (in tiles) solid, or not solid anymore.
+    /// Only affects tiles made solid this way, not the terrain.
+    pub(crate) fn set_tile_solid(&mut self, x: u32, y: u32, solid: bool) {
+        if solid {
+            self.solid_tiles.insert((x, y));
+        } else {
+            self.solid_tiles.remove(&(x, y));
+        }
+    }
+
+    /// Returns whether any part of the area with the given top-left corner and size (in pixels) is solid.
+    /// Anything outside of the map isn't solid, as entities are kept within the map anyway.
+    pub(crate) fn is_area_solid(&self, x: u32, y: u32, width: u32, height: u32) -> bool {
+        if width == 0 || height == 0 {
+            return false;
+        }
+        let (right, bottom) = (x + width - 1, y + height - 1);
+
+        for block_y in y / BLOCK_SIZE_IN_PX..=bottom / BLOCK_SIZE_IN_PX {
+            for block_x in x / BLOCK_SIZE_IN_PX..=right / BLOCK_SIZE_IN_PX {
+                if block_x as usize >= self.width_in_blocks {
+                    continue;
+                }
+                let idx = block_x as usize + block_y as usize * self.width_in_blocks;
+                if self
+                    .solid_blocks
+                    .get(idx)
+                    .map_or(false, |solid| *solid != 0)
+                {
+                    return true;
+                }
+            }
+        }
+
+        if self.solid_tiles.is_empty() {
+            return false;
+        }
+        let tile_size = TILE_SIZE_IN_PX as u32;
+        for tile_y in y / tile_size..=bottom / tile_size {
+            for tile_x in x / tile_size..=right / tile_size {
+                if self.solid_tiles.contains(&(tile_x, tile_y)) {
+                    return true;
+                }
+            }
+        }
+        return false;
+    }
+}
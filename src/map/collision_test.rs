@@ -0,0 +1,47 @@
+use super::collision::*;
+use crate::test::test;
+
+use alloc::borrow::Cow;
+
+/// A map which is 3x2 Mindustry tiles large, with the middle of the top row solid
+const SOLID_BLOCKS: [u8; 6] = [0, 1, 0, 0, 0, 0];
+
+/// Ensure solid terrain is detected for every pixel of its Mindustry tile
+#[test_case]
+fn test_solid_terrain() {
+    test(
+        &|| {
+            let collision = CollisionMap::new(Cow::Borrowed(&SOLID_BLOCKS), 3);
+            assert!(!collision.is_area_solid(0, 0, 16, 16));
+            assert!(collision.is_area_solid(0, 0, 17, 16));
+            assert!(collision.is_area_solid(31, 15, 1, 1));
+            assert!(!collision.is_area_solid(32, 0, 16, 32));
+            assert!(!collision.is_area_solid(16, 16, 16, 16));
+            // Outside of the map
+            assert!(!collision.is_area_solid(48, 0, 16, 16));
+            assert!(!collision.is_area_solid(0, 32, 16, 16));
+        },
+        "test_solid_terrain",
+        "ensure solid terrain is detected for every pixel of its Mindustry tile",
+    );
+}
+
+/// Ensure tiles can be made solid and not solid again
+#[test_case]
+fn test_solid_tiles() {
+    test(
+        &|| {
+            let mut collision = CollisionMap::new(Cow::Borrowed(&SOLID_BLOCKS), 3);
+            collision.set_tile_solid(1, 3, true);
+            assert!(collision.is_area_solid(8, 24, 8, 8));
+            assert!(collision.is_area_solid(0, 20, 9, 5));
+            assert!(!collision.is_area_solid(0, 24, 8, 8));
+            assert!(!collision.is_area_solid(8, 32, 8, 8));
+
+            collision.set_tile_solid(1, 3, false);
+            assert!(!collision.is_area_solid(8, 24, 8, 8));
+        },
+        "test_solid_tiles",
+        "ensure tiles can be made solid and not solid again",
+    );
+}
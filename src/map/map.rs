@@ -1,34 +1,78 @@
+use super::animation::{self, AnimatedTiles, AnimationToken, TileAnimation};
 use super::background::LargeBackground;
+use super::buildings::{Building, BuildingTileset};
+use super::collision::CollisionMap;
+use super::minimap::{Minimap, MinimapTerrain};
 
+use crate::assets::{compression, files};
 use crate::debug_log::Subsystems;
-use crate::shared_constants::{SCREENBLOCK_SIZE_BYTES, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::item::Item;
+use crate::shared_constants::{
+    BACKING_MAP_LENGTH_IN_TILES, SCREENBLOCK_SIZE_BYTES, TILE_SIZE_IN_PX,
+};
 use crate::FS;
 
 use core::str;
 
+use alloc::borrow::Cow;
 use alloc::boxed::Box;
 use alloc::string::String;
 use alloc::vec::Vec;
 
 use serde::Deserialize;
 
-#[derive(Debug, Clone)]
+/// How many tiles wide and high a single Mindustry tile (and therefore an ore layer entry) is.
+const TILES_PER_BLOCK: usize = 2;
+/// Screenblock entry of the transparent tile, which is always the map's first one.
+const TRANSPARENT_ENTRY: u16 = 0;
+
+#[derive(Debug)]
 pub struct Map {
     bg: LargeBackground,
+    // Tiles of buildings which can be placed on the map
+    buildings: BuildingTileset,
+    // One byte per Mindustry tile, see `MapEntry::ore_layer`
+    ore_layer: Cow<'static, [u8]>,
+    // Width of the ore layer, in Mindustry tiles
+    ore_layer_width: usize,
+    // Terrain of the minimap
+    minimap: MinimapTerrain,
+    // Which parts of the map units can't move through
+    collision: CollisionMap,
+    // Buildings placed on the map, with their top-left corner in pixels
+    placed_buildings: Vec<(Building, u32, u32)>,
+    // Identifies the map's animations, which run until the map is dropped
+    animation_token: AnimationToken,
 }
 
 impl Map {
     /// Create a new map.
     /// `x` and `y` are the size of the entire map, given in number of horizontal and vertical 32x32 sub-tilemaps, respectively.
-    /// . Their number must match x*y and they must be in the vector in a left-to-right, top-to-bottom order.
-    /// Each tilemap must be SCREENBLOCK_SIZE_IN_U8 large.
+    /// `width_in_tiles` and `height_in_tiles` are the actual size of the map, as the last sub-tilemaps are padded.
+    /// The map doesn't have to be square.
+    /// The number of tilemaps must match x*y and they must be in the vector in a left-to-right, top-to-bottom order.
+    /// `overlay_tilemaps` are the tilemaps of the overlay layer drawn on top, in the same order.
+    /// Each tilemap must be SCREENBLOCK_SIZE_IN_U8 large once decompressed.
     /// If it isn't, this function will panic.
-    pub fn new_map(
-        palette: &'static [u16],
+    /// `tiles` and the tilemaps are file contents as stored in GBFS and may be compressed (see `assets::compression`).
+    /// `animated_tiles` are the animations of the map's tiles, which run during vblank until the map is dropped.
+    /// `ore_layer` contains one byte per Mindustry tile (2x2 tiles), in rows as wide as the map.
+    /// `minimap` is the map's terrain as shown on the minimap.
+    /// `solid_layer` contains one byte per Mindustry tile like `ore_layer`, which is non-zero for solid terrain.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new_map(
+        palette: &[u16],
         x_size_in_tilemaps: usize,
         y_size_in_tilemaps: usize,
-        tiles: &'static [u32],
+        width_in_tiles: usize,
+        height_in_tiles: usize,
+        tiles: &'static [u8],
         tilemaps: Vec<&'static [u8]>,
+        overlay_tilemaps: Vec<&'static [u8]>,
+        animated_tiles: AnimatedTiles,
+        ore_layer: Cow<'static, [u8]>,
+        minimap: MinimapTerrain,
+        solid_layer: Cow<'static, [u8]>,
     ) -> Map {
         debug_log!(
             Subsystems::Map,
@@ -37,19 +81,142 @@ impl Map {
             y_size_in_tilemaps,
             tilemaps.len()
         );
-        for tilemap in tilemaps.clone() {
-            assert_eq!(tilemap.len(), SCREENBLOCK_SIZE_BYTES);
+        let floor = to_two_d_indexed(&tilemaps, x_size_in_tilemaps, y_size_in_tilemaps);
+        let overlay = to_two_d_indexed(&overlay_tilemaps, x_size_in_tilemaps, y_size_in_tilemaps);
+        let bg = LargeBackground::init(
+            tiles,
+            width_in_tiles,
+            height_in_tiles,
+            floor,
+            overlay,
+            palette,
+        );
+        let animation_token = animation::start_animating(animated_tiles, bg.tiles());
+        let buildings = BuildingTileset::load();
+        return Map {
+            bg,
+            buildings,
+            ore_layer,
+            ore_layer_width: width_in_tiles / TILES_PER_BLOCK,
+            minimap,
+            collision: CollisionMap::new(solid_layer, width_in_tiles / TILES_PER_BLOCK),
+            placed_buildings: Vec::new(),
+            animation_token,
+        };
+    }
+
+    /// Draws the building into the overlay layer, with its top-left corner at the given position (in pixels).
+    /// The position is rounded down to the tile grid.
+    /// Units can't move through solid buildings afterwards.
+    pub fn place_building(&mut self, building: Building, x: u32, y: u32) {
+        let tile_x = x as usize / TILE_SIZE_IN_PX;
+        let tile_y = y as usize / TILE_SIZE_IN_PX;
+        let (size, entries) = self.buildings.get(building);
+        for (i, entry) in entries.iter().enumerate() {
+            self.bg
+                .set_overlay_entry(tile_x + i % size, tile_y + i / size, *entry);
+            if building.is_solid() {
+                self.collision.set_tile_solid(
+                    (tile_x + i % size) as u32,
+                    (tile_y + i / size) as u32,
+                    true,
+                );
+            }
         }
-        let mut two_d_indexed_tilemaps: Vec<Vec<&'static [u8]>> =
-            Vec::with_capacity(x_size_in_tilemaps);
-        for i in 0..x_size_in_tilemaps {
-            two_d_indexed_tilemaps.push(Vec::with_capacity(y_size_in_tilemaps));
-            for j in 0..y_size_in_tilemaps {
-                two_d_indexed_tilemaps[i].push(tilemaps[i * x_size_in_tilemaps + j]);
+        self.placed_buildings.push((
+            building,
+            (tile_x * TILE_SIZE_IN_PX) as u32,
+            (tile_y * TILE_SIZE_IN_PX) as u32,
+        ));
+    }
+
+    /// Removes the building with its top-left corner at the given position (in pixels) from the overlay layer,
+    /// as placed by `place_building`, and lets units move through it again.
+    /// Does nothing if there is no such building.
+    pub fn remove_building(&mut self, x: u32, y: u32) {
+        let tile_x = x as usize / TILE_SIZE_IN_PX;
+        let tile_y = y as usize / TILE_SIZE_IN_PX;
+        let (grid_x, grid_y) = (
+            (tile_x * TILE_SIZE_IN_PX) as u32,
+            (tile_y * TILE_SIZE_IN_PX) as u32,
+        );
+        let index = match self
+            .placed_buildings
+            .iter()
+            .position(|(_, placed_x, placed_y)| *placed_x == grid_x && *placed_y == grid_y)
+        {
+            Some(index) => index,
+            None => return,
+        };
+        let (building, _, _) = self.placed_buildings.remove(index);
+        let (size, _) = self.buildings.get(building);
+        for i in 0..size * size {
+            self.bg
+                .reset_overlay_entry(tile_x + i % size, tile_y + i / size);
+            if building.is_solid() {
+                self.collision.set_tile_solid(
+                    (tile_x + i % size) as u32,
+                    (tile_y + i / size) as u32,
+                    false,
+                );
             }
         }
-        let bg = LargeBackground::init(tiles, two_d_indexed_tilemaps, palette);
-        return Map { bg };
+    }
+
+    /// Returns the width and height of the building in pixels.
+    pub fn get_building_size_in_px(&self, building: Building) -> u32 {
+        let (size, _) = self.buildings.get(building);
+        return (size * TILE_SIZE_IN_PX) as u32;
+    }
+
+    /// Renders a minimap of the map, with the player at the given position (in pixels).
+    pub fn render_minimap(&self, player_x: u32, player_y: u32) -> Minimap {
+        let centers: Vec<(u32, u32)> = self
+            .placed_buildings
+            .iter()
+            .map(|(building, x, y)| {
+                let center_offset = self.get_building_size_in_px(*building) / 2;
+                (x + center_offset, y + center_offset)
+            })
+            .collect();
+        return self.minimap.render((player_x, player_y), &centers);
+    }
+
+    /// Changes the overlay tile at the given position (in tiles) to the given screenblock entry,
+    /// which refers to one of the map's tiles.
+    #[allow(dead_code)]
+    pub fn set_overlay_tile(&mut self, x: usize, y: usize, entry: u16) {
+        let entry = self.bg.map_tile_entry(entry);
+        self.bg.set_overlay_entry(x, y, entry);
+    }
+
+    /// Makes the overlay tile at the given position (in tiles) transparent.
+    #[allow(dead_code)]
+    pub fn clear_overlay_tile(&mut self, x: usize, y: usize) {
+        let entry = self.bg.map_tile_entry(TRANSPARENT_ENTRY);
+        self.bg.set_overlay_entry(x, y, entry);
+    }
+
+    /// Returns the item that can be mined at the given position (in pixels), if any.
+    // TODO: Use this to restrict where drills can be placed
+    #[allow(dead_code)]
+    pub fn get_ore_at(&self, x: u32, y: u32) -> Option<Item> {
+        let block_size_in_px = (TILES_PER_BLOCK * TILE_SIZE_IN_PX) as u32;
+        let block_x = (x / block_size_in_px) as usize;
+        let block_y = (y / block_size_in_px) as usize;
+        if block_x >= self.ore_layer_width {
+            return None;
+        }
+        return self
+            .ore_layer
+            .get(block_x + block_y * self.ore_layer_width)
+            .and_then(|value| Item::from_ore_layer_value(*value));
+    }
+
+    /// Returns whether any part of the area with the given top-left corner and size (in pixels)
+    /// is solid terrain or a solid building.
+    pub fn is_area_solid(&self, x: u32, y: u32, width: u32, height: u32) -> bool {
+        return self.collision.is_area_solid(x, y, width, height);
     }
 
     /// Returns whether the given area (in pixels) is visible on screen right now.
@@ -71,38 +238,58 @@ impl Map {
     }
 
     /// Scroll the map to an absolute position.
+    /// The position is clamped so that nothing outside of the map becomes visible.
     pub fn scroll_abs(&mut self, x_pos: u32, y_pos: u32) {
         self.bg.scroll_abs(x_pos, y_pos);
     }
 
-    /// Scroll the map by xy pixels (relative to current position).
-    /// If scrolling would make an out-of-bounds area visible, the scroll is not performed
-    /// and false is returned.
-    pub fn try_scroll(&mut self, x: i32, y: i32) -> bool {
-        if self.can_scroll(x, y) {
-            self.bg.scroll(x, y);
-            return true;
-        }
-        return false;
-    }
-
-    /// Report whether the map can be scrolled by xy pixels without going OOB.
-    pub fn can_scroll(&self, x: i32, y: i32) -> bool {
-        // Optimization: No point in checking anything if no movement occurs
-        if x != 0 || y != 0 {
-            // Bounds check: Both corners must have positive coords after scroll
-            let (curr_x, curr_y) = self.bg.get_top_left_corner_coords();
-            let curr_x = curr_x as i32;
-            let curr_y = curr_y as i32;
-            let curr_bottom_right_x = curr_x + SCREEN_WIDTH as i32;
-            let curr_bottom_right_y = curr_y + SCREEN_HEIGHT as i32;
-            return !(curr_x + x < 0
-                || curr_y + y < 0
-                || curr_bottom_right_x as i32 + x < 0
-                || curr_bottom_right_y as i32 + y < 0);
+    /// Returns the scroll position closest to the given one which doesn't make any area outside of the map visible.
+    pub fn clamp_scroll(&self, x: i32, y: i32) -> (u32, u32) {
+        return self.bg.clamp_scroll(x, y);
+    }
+
+    /// Returns the positions of the enemy spawns, in pixels.
+    pub fn get_enemy_spawns(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        return self.minimap.enemy_spawns();
+    }
+
+    /// Returns the (width, height) of the map in pixels.
+    pub fn get_size_in_px(&self) -> (u32, u32) {
+        return self.bg.get_size_in_px();
+    }
+}
+
+impl Drop for Map {
+    fn drop(&mut self) {
+        // The animated tiles are freed together with the background.
+        // A map loaded before this one was dropped has replaced its animations already.
+        animation::stop_animating(&self.animation_token);
+    }
+}
+
+/// Turns a list of tilemaps in left-to-right, top-to-bottom order into a 2D vector indexed by [x][y].
+fn to_two_d_indexed(
+    tilemaps: &[&'static [u8]],
+    x_size_in_tilemaps: usize,
+    y_size_in_tilemaps: usize,
+) -> Vec<Vec<&'static [u8]>> {
+    assert_eq!(tilemaps.len(), x_size_in_tilemaps * y_size_in_tilemaps);
+    for tilemap in tilemaps {
+        assert_eq!(
+            compression::decompressed_len(tilemap),
+            SCREENBLOCK_SIZE_BYTES
+        );
+    }
+    let mut two_d_indexed_tilemaps: Vec<Vec<&'static [u8]>> =
+        Vec::with_capacity(x_size_in_tilemaps);
+    for x in 0..x_size_in_tilemaps {
+        two_d_indexed_tilemaps.push(Vec::with_capacity(y_size_in_tilemaps));
+        for y in 0..y_size_in_tilemaps {
+            // Tilemaps are stored row by row
+            two_d_indexed_tilemaps[x].push(tilemaps[y * x_size_in_tilemaps + x]);
         }
-        return true;
     }
+    return two_d_indexed_tilemaps;
 }
 
 /// Top-level struct describing all available maps.
@@ -124,6 +311,7 @@ impl Maps {
     }
 }
 /// Describes single map.
+/// Generated by the `asset-converter` tool from Mindustry's maps.
 #[derive(Deserialize, Clone)]
 pub struct MapEntry {
     // Map display name
@@ -132,8 +320,35 @@ pub struct MapEntry {
     height: usize,
     // width in tiles
     width: usize,
-    // list of chunks belonging to map
+    // GBFS file containing the 4bpp tile graphics
+    // All map files may be compressed, see `assets::compression`.
+    tiles: String,
+    // GBFS file containing the palette
+    palette: String,
+    // GBFS file containing the frames of all animated tiles
+    animated_tiles: String,
+    // tiles which are animated by swapping out their graphics
+    animations: Vec<TileAnimation>,
+    // GBFS file containing one byte per Mindustry tile (2x2 tiles) describing which ore is there.
+    // 0 means no ore, see `Item::from_ore_layer_value`.
+    ore_layer: String,
+    // GBFS file containing one byte per Mindustry tile, 1 if units can't move through it and 0 otherwise
+    solid_layer: String,
+    // GBFS file containing one byte per Mindustry tile, the index of its color in the minimap palette
+    minimap: String,
+    // GBFS file containing the minimap palette
+    minimap_palette: String,
+    // list of chunks belonging to map, in left-to-right, top-to-bottom order
     chunks: Vec<MapChunk>,
+    // chunks of the overlay layer containing ores and decorations, in the same order
+    overlay_chunks: Vec<MapChunk>,
+    // enemy spawn points
+    // TODO: Spawn enemies at these once we have enemies, for now they're only shown on the minimap
+    spawns: Vec<MapPoint>,
+    // blocks which are already built when the map is loaded
+    // TODO: Place these once we have buildings which can be owned by nobody
+    #[allow(dead_code)]
+    derelicts: Vec<DerelictBlock>,
 }
 
 /// Describes a 32x32 chunk.
@@ -142,13 +357,28 @@ pub struct MapChunk {
     filename: String,
 }
 
-impl Maps {
-    const MAPS_PATH: &'static str = "maps.json";
+/// Top-left corner of a Mindustry tile, in tiles.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct MapPoint {
+    pub x: usize,
+    pub y: usize,
+}
+
+/// A block which exists on the map from the start.
+#[allow(dead_code)]
+#[derive(Deserialize, Clone, Debug)]
+pub struct DerelictBlock {
+    // Mindustry's name for the block
+    pub block: String,
+    // position of the block's center tile
+    pub pos: MapPoint,
+}
 
+impl Maps {
     /// Reads from the default map description file.
     pub fn read_map_data() -> Maps {
-        let map = FS.get_file_data_by_name(Maps::MAPS_PATH).unwrap();
-        let map_data: Maps = serde_json::from_str(str::from_utf8(map).unwrap()).unwrap();
+        let map = files::MAPS_JSON.load::<u8>();
+        let map_data: Maps = serde_json::from_str(str::from_utf8(&map).unwrap()).unwrap();
         map_data
     }
 }
@@ -160,20 +390,54 @@ impl MapEntry {
         for chunk in &self.chunks {
             tilemaps.push(FS.get_file_data_by_name(&chunk.filename).unwrap())
         }
+        let overlay_tilemaps: Vec<&'static [u8]> = self
+            .overlay_chunks
+            .iter()
+            .map(|chunk| FS.get_file_data_by_name(&chunk.filename).unwrap())
+            .collect();
+
+        let pal: Cow<'static, [u16]> =
+            compression::load(FS.get_file_data_by_name(&self.palette).unwrap());
+
+        // Tiles are decompressed and copied into VRAM by the background's tile allocation
+        let tiles: &'static [u8] = FS.get_file_data_by_name(&self.tiles).unwrap();
 
-        // FIXME: Grit truncates filenames if they're too long for GBFS.
-        // Figure out how the truncation algo works and reverse it here.
-        let pal: &'static [u16] = FS
-            .get_file_data_by_name_as_u16_slice(format!("{}Pal", self.name).as_str())
-            .unwrap();
+        let ore_layer: Cow<'static, [u8]> =
+            compression::load(FS.get_file_data_by_name(&self.ore_layer).unwrap());
+        let solid_layer: Cow<'static, [u8]> =
+            compression::load(FS.get_file_data_by_name(&self.solid_layer).unwrap());
 
-        let tiles: &'static [u32] = FS
-            .get_file_data_by_name_as_u32_slice(format!("{}Tiles", self.name).as_str())
-            .unwrap();
+        // Maps without animations have an empty frames file, so don't bother loading it
+        let animation_frames: Cow<'static, [u32]> = if self.animations.is_empty() {
+            Cow::Borrowed(&[])
+        } else {
+            compression::load(FS.get_file_data_by_name(&self.animated_tiles).unwrap())
+        };
+        let animated_tiles = AnimatedTiles::new(self.animations.clone(), animation_frames);
+
+        let minimap = MinimapTerrain::new(
+            compression::load(FS.get_file_data_by_name(&self.minimap).unwrap()),
+            compression::load(FS.get_file_data_by_name(&self.minimap_palette).unwrap()),
+            self.width / TILES_PER_BLOCK,
+            self.spawns.clone(),
+        );
 
-        // Calculate size in chunks
-        let height = self.height / 32;
-        let width = self.width / 32;
-        return Box::new(Map::new_map(pal, width, height, tiles, tilemaps));
+        // Calculate size in chunks. The last row and column of chunks are padded.
+        let height = (self.height + BACKING_MAP_LENGTH_IN_TILES - 1) / BACKING_MAP_LENGTH_IN_TILES;
+        let width = (self.width + BACKING_MAP_LENGTH_IN_TILES - 1) / BACKING_MAP_LENGTH_IN_TILES;
+        return Box::new(Map::new_map(
+            &pal,
+            width,
+            height,
+            self.width,
+            self.height,
+            tiles,
+            tilemaps,
+            overlay_tilemaps,
+            animated_tiles,
+            ore_layer,
+            minimap,
+            solid_layer,
+        ));
     }
 }
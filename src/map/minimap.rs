@@ -0,0 +1,206 @@
+//! Rendering of the map into a small overview, which is displayed by `Window::make_minimap`.
+//!
+//! The minimap consists of 4bpp tiles using the text palbank, where the first two colors belong to the font.
+//! The terrain colors come from the asset converter, which reduces every Mindustry tile to a single color.
+
+use super::map::MapPoint;
+
+use crate::shared_constants::{TILE_SIZE_BYTES_4BPP, TILE_SIZE_IN_PX};
+
+use alloc::borrow::Cow;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Maximum size of the minimap in pixels, so that it fits into a window together with a title.
+const MAX_WIDTH_IN_PX: usize = 120;
+const MAX_HEIGHT_IN_PX: usize = 80;
+/// Number of terrain colors, which has to match the asset converter.
+const TERRAIN_COLORS: usize = 10;
+/// Colors of the markers, which come right after the terrain colors in the palbank.
+const MARKER_COLORS: [u16; 3] = [
+    0x03E0, // Player, green
+    0x03FF, // Buildings, yellow
+    0x001F, // Enemy spawns, red
+];
+/// Size of a marker's edge, in pixels
+const MARKER_SIZE_IN_PX: usize = 2;
+/// How many pixels of the map a Mindustry tile is.
+const BLOCK_SIZE_IN_PX: usize = 2 * TILE_SIZE_IN_PX;
+
+#[derive(Debug, Clone, Copy)]
+enum Marker {
+    Player = 0,
+    Building = 1,
+    EnemySpawn = 2,
+}
+
+/// Everything needed to draw the map's terrain into a minimap.
+#[derive(Debug)]
+pub(crate) struct MinimapTerrain {
+    /// One palette index per Mindustry tile, see `MapEntry::minimap`
+    colors: Cow<'static, [u8]>,
+    /// Colors the indices refer to
+    palette: Cow<'static, [u16]>,
+    /// Size of the map, in Mindustry tiles
+    width: usize,
+    height: usize,
+    /// Enemy spawn points, shown as markers
+    enemy_spawns: Vec<MapPoint>,
+}
+
+/// A rendered minimap.
+pub struct Minimap {
+    /// 4bpp tiles in left-to-right, top-to-bottom order
+    pub tiles: Vec<u32>,
+    /// Colors of the minimap, which belong at `Minimap::FIRST_COLOR` onwards in the text palbank
+    pub palette: Vec<u16>,
+    /// Size of the minimap in tiles
+    pub width_in_tiles: usize,
+    pub height_in_tiles: usize,
+    /// Size of the area actually covered by the map, in pixels
+    pub width_in_px: usize,
+    pub height_in_px: usize,
+    /// Size of the map, in pixels
+    map_width_in_px: usize,
+    map_height_in_px: usize,
+}
+
+impl MinimapTerrain {
+    /// `colors` contains one index into `palette` per Mindustry tile, in rows `width` entries wide.
+    pub(crate) fn new(
+        colors: Cow<'static, [u8]>,
+        palette: Cow<'static, [u16]>,
+        width: usize,
+        enemy_spawns: Vec<MapPoint>,
+    ) -> MinimapTerrain {
+        assert!(palette.len() <= TERRAIN_COLORS, "Too many minimap colors");
+        return MinimapTerrain {
+            height: colors.len() / width,
+            colors,
+            palette,
+            width,
+            enemy_spawns,
+        };
+    }
+
+    /// Returns the positions of the enemy spawns, in pixels.
+    pub(crate) fn enemy_spawns(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        return self.enemy_spawns.iter().map(|spawn| {
+            return (
+                (spawn.x * TILE_SIZE_IN_PX) as u32,
+                (spawn.y * TILE_SIZE_IN_PX) as u32,
+            );
+        });
+    }
+
+    /// Renders the terrain scaled down (or up) to fit into the minimap,
+    /// with markers at the player's and the buildings' positions (in pixels).
+    pub(crate) fn render(&self, player: (u32, u32), buildings: &[(u32, u32)]) -> Minimap {
+        // Keep the aspect ratio, using up as much space as possible
+        let (width_in_px, height_in_px) =
+            if self.width * MAX_HEIGHT_IN_PX >= self.height * MAX_WIDTH_IN_PX {
+                (MAX_WIDTH_IN_PX, self.height * MAX_WIDTH_IN_PX / self.width)
+            } else {
+                (
+                    self.width * MAX_HEIGHT_IN_PX / self.height,
+                    MAX_HEIGHT_IN_PX,
+                )
+            };
+        let mut minimap = Minimap {
+            tiles: Vec::new(),
+            palette: Vec::new(),
+            width_in_tiles: (width_in_px + TILE_SIZE_IN_PX - 1) / TILE_SIZE_IN_PX,
+            height_in_tiles: (height_in_px + TILE_SIZE_IN_PX - 1) / TILE_SIZE_IN_PX,
+            width_in_px: width_in_px.max(1),
+            height_in_px: height_in_px.max(1),
+            map_width_in_px: self.width * BLOCK_SIZE_IN_PX,
+            map_height_in_px: self.height * BLOCK_SIZE_IN_PX,
+        };
+
+        // Pixels outside the map stay transparent
+        let stride = minimap.width_in_tiles * TILE_SIZE_IN_PX;
+        let mut pixels = vec![0_u8; stride * minimap.height_in_tiles * TILE_SIZE_IN_PX];
+        for y in 0..height_in_px {
+            let block_y = y * self.height / height_in_px;
+            for x in 0..width_in_px {
+                let block_x = x * self.width / width_in_px;
+                let color = self.colors[block_x + block_y * self.width];
+                pixels[x + y * stride] = color + Minimap::FIRST_COLOR as u8;
+            }
+        }
+
+        let mut draw_marker = |map_x: u32, map_y: u32, marker: Marker| {
+            let (x, y) = minimap.to_minimap_pos(map_x, map_y);
+            let color = (Minimap::FIRST_COLOR + TERRAIN_COLORS + marker as usize) as u8;
+            for dy in 0..MARKER_SIZE_IN_PX {
+                for dx in 0..MARKER_SIZE_IN_PX {
+                    // Markers may stick out of the map, but not out of the minimap
+                    let (x, y) = (x as usize + dx, y as usize + dy);
+                    if x < stride && y * stride < pixels.len() {
+                        pixels[x + y * stride] = color;
+                    }
+                }
+            }
+        };
+        for (x, y) in self.enemy_spawns() {
+            draw_marker(x, y, Marker::EnemySpawn);
+        }
+        for (x, y) in buildings {
+            draw_marker(*x, *y, Marker::Building);
+        }
+        // The player goes last so that nothing hides them
+        draw_marker(player.0, player.1, Marker::Player);
+
+        minimap.tiles = to_4bpp_tiles(&pixels, minimap.width_in_tiles, minimap.height_in_tiles);
+        minimap.palette.extend_from_slice(&self.palette);
+        minimap.palette.resize(TERRAIN_COLORS, 0);
+        minimap.palette.extend_from_slice(&MARKER_COLORS);
+        return minimap;
+    }
+}
+
+impl Minimap {
+    /// Index of the minimap's first color in the text palbank, as the font uses the ones before.
+    pub const FIRST_COLOR: usize = 2;
+
+    /// Converts a position on the map (in pixels) to the corresponding pixel of the minimap.
+    pub fn to_minimap_pos(&self, x: u32, y: u32) -> (u32, u32) {
+        let x = (x as usize).min(self.map_width_in_px - 1);
+        let y = (y as usize).min(self.map_height_in_px - 1);
+        return (
+            (x * self.width_in_px / self.map_width_in_px) as u32,
+            (y * self.height_in_px / self.map_height_in_px) as u32,
+        );
+    }
+
+    /// Converts a pixel of the minimap to the position on the map (in pixels) it shows.
+    /// Positions outside of the map are moved to its edge.
+    pub fn to_map_pos(&self, x: u32, y: u32) -> (u32, u32) {
+        let x = (x as usize).min(self.width_in_px - 1);
+        let y = (y as usize).min(self.height_in_px - 1);
+        return (
+            (x * self.map_width_in_px / self.width_in_px) as u32,
+            (y * self.map_height_in_px / self.height_in_px) as u32,
+        );
+    }
+}
+
+/// Packs one palette index per pixel, in rows `width_in_tiles` tiles wide, into 4bpp tiles.
+fn to_4bpp_tiles(pixels: &[u8], width_in_tiles: usize, height_in_tiles: usize) -> Vec<u32> {
+    let stride = width_in_tiles * TILE_SIZE_IN_PX;
+    let mut tiles = Vec::with_capacity(width_in_tiles * height_in_tiles * TILE_SIZE_BYTES_4BPP / 4);
+    for tile_y in 0..height_in_tiles {
+        for tile_x in 0..width_in_tiles {
+            for y in 0..TILE_SIZE_IN_PX {
+                let row_start = tile_x * TILE_SIZE_IN_PX + (tile_y * TILE_SIZE_IN_PX + y) * stride;
+                // The leftmost pixel is in the lowest nibble
+                let row = pixels[row_start..row_start + TILE_SIZE_IN_PX]
+                    .iter()
+                    .rev()
+                    .fold(0_u32, |row, pixel| (row << 4) | *pixel as u32);
+                tiles.push(row);
+            }
+        }
+    }
+    return tiles;
+}
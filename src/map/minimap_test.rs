@@ -0,0 +1,79 @@
+use super::map::MapPoint;
+use super::minimap::*;
+use crate::shared_constants::TILE_SIZE_IN_PX;
+use crate::test::test;
+
+use alloc::borrow::Cow;
+use alloc::vec;
+
+/// Returns the palette index of the given minimap pixel.
+fn pixel_at(minimap: &Minimap, x: usize, y: usize) -> u32 {
+    let tile = x / TILE_SIZE_IN_PX + (y / TILE_SIZE_IN_PX) * minimap.width_in_tiles;
+    let row = minimap.tiles[tile * TILE_SIZE_IN_PX + y % TILE_SIZE_IN_PX];
+    return (row >> ((x % TILE_SIZE_IN_PX) * 4)) & 0xF;
+}
+
+/// Ensure the terrain is scaled to fit while keeping the aspect ratio
+#[test_case]
+fn test_minimap_scaling() {
+    test(
+        &|| {
+            // 240x40 Mindustry tiles, where the left half has color 0 and the right half color 1
+            let colors: Cow<'static, [u8]> =
+                (0..240 * 40).map(|i| (i % 240 >= 120) as u8).collect();
+            let terrain =
+                MinimapTerrain::new(colors, Cow::Owned(vec![0x1111, 0x2222]), 240, vec![]);
+            let minimap = terrain.render((0, 0), &[]);
+            assert_eq!(minimap.width_in_tiles, 15);
+            assert_eq!(minimap.height_in_tiles, 3);
+            assert_eq!(minimap.palette[..2], [0x1111, 0x2222]);
+
+            assert_eq!(pixel_at(&minimap, 59, 10), Minimap::FIRST_COLOR as u32);
+            assert_eq!(pixel_at(&minimap, 60, 10), Minimap::FIRST_COLOR as u32 + 1);
+            // The map is 20 pixels high, so the rest of the last tile row is transparent
+            assert_eq!(pixel_at(&minimap, 60, 19), Minimap::FIRST_COLOR as u32 + 1);
+            assert_eq!(pixel_at(&minimap, 60, 20), 0);
+
+            // Each minimap pixel shows 2x2 Mindustry tiles of 16x16 pixels each
+            assert_eq!(minimap.to_map_pos(60, 10), (60 * 32, 10 * 32));
+            assert_eq!(minimap.to_minimap_pos(60 * 32 + 31, 10 * 32), (60, 10));
+            // Positions outside the map end up on its edge
+            assert_eq!(minimap.to_map_pos(60, 23), (60 * 32, 19 * 32));
+        },
+        "test_minimap_scaling",
+        "ensure the terrain is scaled to fit while keeping the aspect ratio",
+    );
+}
+
+/// Ensure markers are drawn on top of the terrain
+#[test_case]
+fn test_minimap_markers() {
+    test(
+        &|| {
+            let colors: Cow<'static, [u8]> = vec![0; 60 * 40].into();
+            let spawn = MapPoint { x: 8, y: 0 };
+            let terrain = MinimapTerrain::new(colors, Cow::Owned(vec![0x1111]), 60, vec![spawn]);
+            // Each Mindustry tile is 2x2 minimap pixels
+            let minimap = terrain.render((32, 48), &[(0, 0)]);
+            assert_eq!(minimap.width_in_tiles, 15);
+            assert_eq!(minimap.height_in_tiles, 10);
+
+            let building = pixel_at(&minimap, 0, 0);
+            let spawn = pixel_at(&minimap, 8, 0);
+            let player = pixel_at(&minimap, 4, 6);
+            assert_eq!(pixel_at(&minimap, 1, 1), building);
+            assert_eq!(pixel_at(&minimap, 2, 2), Minimap::FIRST_COLOR as u32);
+            assert_ne!(building, spawn);
+            assert_ne!(building, player);
+            assert_ne!(spawn, player);
+            // All terrain colors come first, even if the map doesn't use all of them
+            assert_eq!(minimap.palette.len(), 10 + 3);
+            assert_eq!(
+                minimap.palette[player as usize - Minimap::FIRST_COLOR],
+                0x03E0
+            );
+        },
+        "test_minimap_markers",
+        "ensure markers are drawn on top of the terrain",
+    );
+}
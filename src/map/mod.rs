@@ -1,3 +1,21 @@
+mod animation;
 mod background;
+mod buildings;
+mod camera;
+mod collision;
 mod map;
+mod minimap;
+pub use buildings::Building;
+pub use camera::Camera;
 pub use map::{Map, Maps};
+pub use minimap::Minimap;
+#[cfg(test)]
+mod animation_test;
+#[cfg(test)]
+mod background_test;
+#[cfg(test)]
+mod camera_test;
+#[cfg(test)]
+mod collision_test;
+#[cfg(test)]
+mod minimap_test;
@@ -14,6 +14,8 @@ pub const SCREENBLOCK_SIZE_BYTES: usize = 32 * 32 * 2;
 pub const CHARBLOCK_SIZE_BYTES: usize = 16 * 1024;
 /// Size of a single tile edge in pixels
 pub const TILE_SIZE_IN_PX: usize = 8;
+/// Size of a single 4bpp tile in bytes
+pub const TILE_SIZE_BYTES_4BPP: usize = 32;
 /// Length of the edge of a single backing tilemap part in tiles
 pub const BACKING_MAP_LENGTH_IN_TILES: usize = 32;
 /// Charblock to use for map tiles
@@ -22,6 +24,9 @@ pub const BACKGROUND_CHARBLOCK: usize = 0;
 /// Screenblock to start at for loading backing tilemaps of map background
 /// NOTE: Do not use for anything else!
 pub const BACKGROUND_SCREEN_BASE_BLOCK: usize = 8;
+/// Screenblock to start at for loading backing tilemaps of the map's overlay layer
+/// NOTE: Do not use for anything else!
+pub const BACKGROUND_OVERLAY_SCREEN_BASE_BLOCK: usize = 12;
 /// Charblock used for font data
 /// NOTE: Do not use for anything else!
 pub const TEXT_CHARBLOCK: usize = 2;
@@ -34,7 +39,11 @@ pub const WINDOW_1_SCREENBLOCK: usize = 26;
 /// The first background palette slot for use by the map.
 pub const MAP_BG_PALETTE_START: usize = 0;
 /// The last background palette slot for use by the map.
-pub const MAP_BG_PALETTE_END: usize = 239;
+pub const MAP_BG_PALETTE_END: usize = 223;
+/// The first background palette slot for use by buildings drawn into the map.
+pub const BUILDING_BG_PALETTE_START: usize = 224;
+/// The last background palette slot for use by buildings drawn into the map.
+pub const BUILDING_BG_PALETTE_END: usize = 239;
 /// The first background palette slot for use by the text engine.
 pub const TEXT_BG_PALETTE_START: usize = 240;
 /// The last background palette slot for use by the text engine.
@@ -16,6 +16,7 @@ pub type Position = (Coordinate, Coordinate);
 /// This enum represents any background.
 /// It's quite convenient, and should be upstreamed.
 #[allow(dead_code)] // Not all variants used ATM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Background {
     Zero,
     One,
@@ -38,6 +39,29 @@ impl Background {
         }
     }
 
+    /// Set the background's scroll offsets
+    pub fn set_scroll(&self, x: u16, y: u16) {
+        use Background::*;
+        match self {
+            Zero => {
+                background::BG0HOFS.write(x);
+                background::BG0VOFS.write(y);
+            }
+            One => {
+                background::BG1HOFS.write(x);
+                background::BG1VOFS.write(y);
+            }
+            Two => {
+                background::BG2HOFS.write(x);
+                background::BG2VOFS.write(y);
+            }
+            Three => {
+                background::BG3HOFS.write(x);
+                background::BG3VOFS.write(y);
+            }
+        }
+    }
+
     /// Set background visibility in DISPCNT
     pub fn set_visible(&self, visible: bool) {
         use Background::*;
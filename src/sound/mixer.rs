@@ -9,7 +9,7 @@ use core::convert::TryInto;
 
 use super::wave;
 use crate::debug_log::Subsystems::Sound;
-use crate::interrupt;
+use crate::interrupt::{self, VBlankSlot};
 use crate::FS;
 
 use byte_slice_cast::{AsByteSlice, AsSliceOf};
@@ -129,7 +129,7 @@ pub fn init() {
     );
 
     // Configure the vblank interrupt to switch our buffers
-    interrupt::set_vblank_handler(Some(&sound_vblank_irq));
+    interrupt::set_vblank_handler(VBlankSlot::Sound, Some(&sound_vblank_irq));
 
     // Configure DMA 1 to continuously transfer samples from the currently active buffer
     unsafe {
@@ -273,7 +273,7 @@ fn mix_buffers() {
 
         // TODO: If no more streams are active, disable mixing (and re-enable once new stream is added).
         //if streams.iter().filter(|stream| stream.is_some()).count() == 0 {
-        //    interrupt::set_vblank_handler(None);
+        //    interrupt::set_vblank_handler(VBlankSlot::Sound, None);
         //}
     }
 
@@ -330,7 +330,7 @@ fn sound_vblank_irq() {
 /// Not to be used outside of tests.
 pub(super) fn spin_until_all_streams_inactive() {
     loop {
-        if !interrupt::vblank_isr_active() {
+        if !interrupt::vblank_isr_active(VBlankSlot::Sound) {
             return;
         }
     }
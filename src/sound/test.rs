@@ -1,4 +1,5 @@
 use super::*;
+use crate::assets::files;
 use crate::test::test;
 
 #[test_case]
@@ -17,7 +18,7 @@ fn test_raw_sound_playback() {
         &|| {
             crate::interrupt::init();
             mixer::init();
-            mixer::add_raw_file_stream("drill.wav").unwrap();
+            mixer::add_raw_file_stream(files::DRILL_WAV.name).unwrap();
             mixer::spin_until_all_streams_inactive();
         },
         "test_raw_sound_playback",
@@ -31,7 +32,7 @@ fn test_wav_sound_playback() {
         &|| {
             crate::interrupt::init();
             mixer::init();
-            mixer::add_wave_file_stream("drill.wav").unwrap();
+            mixer::add_wave_file_stream(files::DRILL_WAV.name).unwrap();
             mixer::spin_until_all_streams_inactive();
         },
         "test_wav_sound_playback",
@@ -0,0 +1,10 @@
+//! This module provides lookups of entities by their position on the map,
+//! so that systems don't have to check every live entity to find the ones in some area.
+//!
+//! Entities are indexed by their bounding box, which has to be kept up to date
+//! by whatever moves or places them (the movement and building systems).
+
+mod spatial_hash;
+pub(crate) use spatial_hash::SpatialHash;
+#[cfg(test)]
+mod spatial_hash_test;
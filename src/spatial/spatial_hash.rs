@@ -0,0 +1,173 @@
+use crate::debug_log::*;
+
+use core::hash::BuildHasherDefault;
+
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+use twox_hash::XxHash64;
+
+/// Edge length of a cell, in pixels.
+/// Large enough that most entities only touch a few cells, small enough that cells don't hold many entities.
+const CELL_SIZE_IN_PX: u32 = 64;
+
+/// An axis-aligned box on the map, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bounds {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Bounds {
+    /// Returns the range of cells (first x, first y, last x, last y) the box touches.
+    fn cells(&self) -> (u32, u32, u32, u32) {
+        return (
+            self.x / CELL_SIZE_IN_PX,
+            self.y / CELL_SIZE_IN_PX,
+            (self.x + self.width - 1) / CELL_SIZE_IN_PX,
+            (self.y + self.height - 1) / CELL_SIZE_IN_PX,
+        );
+    }
+
+    fn overlaps(&self, other: &Bounds) -> bool {
+        return self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height;
+    }
+
+    /// Returns the squared distance between the given point and the closest pixel of the box.
+    fn distance_squared(&self, x: u32, y: u32) -> u64 {
+        let dx = distance_to_range(x, self.x, self.x + self.width - 1) as u64;
+        let dy = distance_to_range(y, self.y, self.y + self.height - 1) as u64;
+        return dx * dx + dy * dy;
+    }
+}
+
+/// Spatial index of entities, which sorts them into square cells of the map based on their bounding box.
+///
+/// Queries only have to look at the entities in the cells they touch, so their cost doesn't depend on
+/// how many entities exist in total.
+#[derive(Debug, Default)]
+pub(crate) struct SpatialHash {
+    /// IDs of the entities touching each cell (x, y)
+    cells: HashMap<(u32, u32), Vec<usize>, BuildHasherDefault<XxHash64>>,
+    /// Bounding box of every entity in the index, so that it can be moved without knowing where it was
+    bounds: HashMap<usize, Bounds, BuildHasherDefault<XxHash64>>,
+}
+
+impl SpatialHash {
+    /// Create an empty index.
+    pub fn new() -> SpatialHash {
+        return Default::default();
+    }
+
+    /// Add the entity with the given top-left corner and size (in pixels) to the index,
+    /// or move it there if it's already in it.
+    /// Empty boxes are treated as a single pixel.
+    pub fn insert(&mut self, id: usize, x: u32, y: u32, width: u32, height: u32) {
+        let bounds = Bounds {
+            x,
+            y,
+            width: width.max(1),
+            height: height.max(1),
+        };
+        match self.bounds.get(&id) {
+            // Most entities don't move most of the time
+            Some(old_bounds) if *old_bounds == bounds => return,
+            Some(_) => self.remove(id),
+            None => debug_log!(Subsystems::Spatial, "Adding entity {} at {} {}", id, x, y),
+        }
+
+        let (first_x, first_y, last_x, last_y) = bounds.cells();
+        for cell_y in first_y..=last_y {
+            for cell_x in first_x..=last_x {
+                self.cells.entry((cell_x, cell_y)).or_default().push(id);
+            }
+        }
+        self.bounds.insert(id, bounds);
+    }
+
+    /// Remove the entity from the index, if it's in it.
+    pub fn remove(&mut self, id: usize) {
+        let bounds = match self.bounds.remove(&id) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let (first_x, first_y, last_x, last_y) = bounds.cells();
+        for cell_y in first_y..=last_y {
+            for cell_x in first_x..=last_x {
+                let cell = self.cells.get_mut(&(cell_x, cell_y)).unwrap();
+                cell.retain(|other| *other != id);
+                // Don't keep empty cells around, as entities move through a lot of them over time
+                if cell.is_empty() {
+                    self.cells.remove(&(cell_x, cell_y));
+                }
+            }
+        }
+    }
+
+    /// Returns the IDs of all entities whose bounding box contains the given pixel, in ascending order.
+    pub fn query_point(&self, x: u32, y: u32) -> Vec<usize> {
+        return self.query_rect(x, y, 1, 1);
+    }
+
+    /// Returns the IDs of all entities whose bounding box overlaps the area with the given top-left corner
+    /// and size (in pixels), in ascending order.
+    pub fn query_rect(&self, x: u32, y: u32, width: u32, height: u32) -> Vec<usize> {
+        let area = Bounds {
+            x,
+            y,
+            width: width.max(1),
+            height: height.max(1),
+        };
+        return self.query(area, |bounds| bounds.overlaps(&area));
+    }
+
+    /// Returns the IDs of all entities whose bounding box is at most `radius` pixels away from the given pixel,
+    /// in ascending order.
+    // TODO: Use this for targeting once there are turrets
+    #[allow(dead_code)]
+    pub fn query_radius(&self, x: u32, y: u32, radius: u32) -> Vec<usize> {
+        let area = Bounds {
+            x: x.saturating_sub(radius),
+            y: y.saturating_sub(radius),
+            width: x + radius + 1 - x.saturating_sub(radius),
+            height: y + radius + 1 - y.saturating_sub(radius),
+        };
+        let radius_squared = radius as u64 * radius as u64;
+        return self.query(area, |bounds| {
+            bounds.distance_squared(x, y) <= radius_squared
+        });
+    }
+
+    /// Returns the IDs of all entities in the cells the area touches for which `matches` returns true,
+    /// in ascending order and without duplicates.
+    fn query(&self, area: Bounds, matches: impl Fn(&Bounds) -> bool) -> Vec<usize> {
+        let mut result = Vec::new();
+        let (first_x, first_y, last_x, last_y) = area.cells();
+        for cell_y in first_y..=last_y {
+            for cell_x in first_x..=last_x {
+                if let Some(cell) = self.cells.get(&(cell_x, cell_y)) {
+                    result.extend(cell.iter().filter(|id| matches(&self.bounds[*id])));
+                }
+            }
+        }
+        // Entities spanning several cells are found once per cell
+        result.sort_unstable();
+        result.dedup();
+        return result;
+    }
+}
+
+/// Returns how far `pos` is from the range `start..=end` on a single axis.
+fn distance_to_range(pos: u32, start: u32, end: u32) -> u32 {
+    if pos < start {
+        return start - pos;
+    } else if pos > end {
+        return pos - end;
+    }
+    return 0;
+}
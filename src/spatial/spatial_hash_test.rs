@@ -0,0 +1,66 @@
+use super::spatial_hash::*;
+use crate::test::test;
+
+/// Ensure entities are found by point and rectangle queries, including ones spanning several cells
+#[test_case]
+fn test_spatial_hash_point_and_rect() {
+    test(
+        &|| {
+            let mut spatial = SpatialHash::new();
+            spatial.insert(1, 0, 0, 16, 16);
+            // Spans four cells
+            spatial.insert(2, 56, 56, 16, 16);
+            spatial.insert(3, 200, 10, 8, 8);
+
+            assert_eq!(spatial.query_point(15, 15), [1]);
+            assert!(spatial.query_point(16, 16).is_empty());
+            assert_eq!(spatial.query_point(70, 70), [2]);
+            assert_eq!(spatial.query_rect(0, 0, 57, 57), [1, 2]);
+            assert_eq!(spatial.query_rect(60, 0, 200, 64), [2, 3]);
+            assert!(spatial.query_rect(100, 100, 10, 10).is_empty());
+        },
+        "test_spatial_hash_point_and_rect",
+        "ensure entities are found by point and rectangle queries",
+    );
+}
+
+/// Ensure radius queries measure the distance to the closest pixel of an entity
+#[test_case]
+fn test_spatial_hash_radius() {
+    test(
+        &|| {
+            let mut spatial = SpatialHash::new();
+            spatial.insert(1, 100, 100, 8, 8);
+            // 3 pixels to the left and 4 above the entity's top-left corner is 5 pixels away
+            assert_eq!(spatial.query_radius(97, 96, 5), [1]);
+            assert!(spatial.query_radius(97, 96, 4).is_empty());
+            // Inside of the entity
+            assert_eq!(spatial.query_radius(104, 104, 0), [1]);
+            // Near the map's top-left corner
+            assert_eq!(spatial.query_radius(0, 0, 200), [1]);
+        },
+        "test_spatial_hash_radius",
+        "ensure radius queries measure the distance to the closest pixel of an entity",
+    );
+}
+
+/// Ensure moved and removed entities are only found where they are now
+#[test_case]
+fn test_spatial_hash_move_and_remove() {
+    test(
+        &|| {
+            let mut spatial = SpatialHash::new();
+            spatial.insert(1, 0, 0, 16, 16);
+            spatial.insert(1, 300, 300, 16, 16);
+            assert!(spatial.query_point(0, 0).is_empty());
+            assert_eq!(spatial.query_point(300, 300), [1]);
+
+            spatial.remove(1);
+            assert!(spatial.query_point(300, 300).is_empty());
+            // Removing twice does nothing
+            spatial.remove(1);
+        },
+        "test_spatial_hash_move_and_remove",
+        "ensure moved and removed entities are only found where they are now",
+    );
+}
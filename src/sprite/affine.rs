@@ -0,0 +1,86 @@
+//! Rotation and scaling of sprites, using the affine parameters stored in OAM.
+//!
+//! There are only 32 sets of affine parameters, so sprites with the same transformation share one
+//! (see `HWSpriteAllocator`).
+
+use fixed::types::I8F8;
+use gba::oam::AffineParameters;
+
+/// An angle, where 0x10000 would be a full turn.
+/// Only the upper 8 bits are used, so there are 256 distinct angles.
+pub type Angle = u16;
+
+/// A scaling factor, in the same 8.8 fixed-point format as the affine parameters.
+pub type Scale = I8F8;
+
+/// sin(x) for a quarter turn in 64 steps, in .12 fixed-point.
+/// The other quarters are mirrored from this one.
+const SIN_LUT: [i32; 65] = [
+    0, 101, 201, 301, 401, 501, 601, 700, 799, 897, 995, 1092, 1189, 1285, 1380, 1474, 1567, 1660,
+    1751, 1842, 1931, 2019, 2106, 2191, 2276, 2359, 2440, 2520, 2598, 2675, 2751, 2824, 2896, 2967,
+    3035, 3102, 3166, 3229, 3290, 3349, 3406, 3461, 3513, 3564, 3612, 3659, 3703, 3745, 3784, 3822,
+    3857, 3889, 3920, 3948, 3973, 3996, 4017, 4036, 4052, 4065, 4076, 4085, 4091, 4095, 4096,
+];
+/// Number of fractional bits of the values in `SIN_LUT`.
+const LUT_FRACTIONAL_BITS: u32 = 12;
+
+/// Returns sin(angle) in .12 fixed-point.
+pub fn sin(angle: Angle) -> i32 {
+    let step = (angle >> 8) as usize;
+    let (quarter, i) = (step / 64, step % 64);
+    return match quarter {
+        0 => SIN_LUT[i],
+        1 => SIN_LUT[64 - i],
+        2 => -SIN_LUT[i],
+        _ => -SIN_LUT[64 - i],
+    };
+}
+
+/// Returns cos(angle) in .12 fixed-point.
+pub fn cos(angle: Angle) -> i32 {
+    return sin(angle.wrapping_add(0x4000));
+}
+
+/// Rotation and scaling of a sprite around its center.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transform {
+    /// Clockwise rotation
+    pub angle: Angle,
+    pub scale_x: Scale,
+    pub scale_y: Scale,
+}
+
+impl Transform {
+    /// Neither rotated nor scaled.
+    pub const IDENTITY: Transform = Transform {
+        angle: 0,
+        scale_x: Scale::from_bits(1 << 8),
+        scale_y: Scale::from_bits(1 << 8),
+    };
+
+    /// Returns the affine parameters which apply the transformation.
+    ///
+    /// The parameters map from screen to sprite pixels, so they're the inverse of the transformation:
+    /// The rotation goes the other way, and the scale is inverted.
+    pub fn to_affine_parameters(&self) -> AffineParameters {
+        let (sin, cos) = (sin(self.angle), cos(self.angle));
+        let (inv_x, inv_y) = (inverse(self.scale_x), inverse(self.scale_y));
+        return AffineParameters {
+            pa: ((cos * inv_x) >> LUT_FRACTIONAL_BITS) as i16,
+            pb: ((sin * inv_x) >> LUT_FRACTIONAL_BITS) as i16,
+            pc: ((-sin * inv_y) >> LUT_FRACTIONAL_BITS) as i16,
+            pd: ((cos * inv_y) >> LUT_FRACTIONAL_BITS) as i16,
+        };
+    }
+}
+
+/// Returns 1 / scale in 8.8 fixed-point.
+/// A scale of 0 results in the largest possible value, making the sprite as small as it gets.
+fn inverse(scale: Scale) -> i32 {
+    if scale.to_bits() == 0 {
+        return i16::MAX as i32;
+    }
+    return ((1 << 16) / scale.to_bits() as i32)
+        .max(i16::MIN as i32)
+        .min(i16::MAX as i32);
+}
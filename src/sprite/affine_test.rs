@@ -0,0 +1,56 @@
+use super::affine::*;
+use crate::test::test;
+
+/// Ensure the lookup table results in the right values for every quarter turn
+#[test_case]
+fn test_sin_cos() {
+    test(
+        &|| {
+            assert_eq!(sin(0), 0);
+            assert_eq!(sin(0x4000), 4096);
+            assert_eq!(sin(0x8000), 0);
+            assert_eq!(sin(0xC000), -4096);
+            assert_eq!(cos(0), 4096);
+            assert_eq!(cos(0x8000), -4096);
+            // 30 degrees
+            assert_eq!(sin(0x1555), 2019);
+            assert_eq!(sin(0x9555), -2019);
+        },
+        "test_sin_cos",
+        "ensure the sin/cos lookup table is correct for every quarter turn",
+    );
+}
+
+/// Ensure transformations result in the inverse affine matrix
+#[test_case]
+fn test_affine_parameters() {
+    test(
+        &|| {
+            let params = Transform::IDENTITY.to_affine_parameters();
+            assert_eq!(
+                (params.pa, params.pb, params.pc, params.pd),
+                (256, 0, 0, 256)
+            );
+
+            let quarter_turn = Transform {
+                angle: 0x4000,
+                ..Transform::IDENTITY
+            };
+            let params = quarter_turn.to_affine_parameters();
+            assert_eq!(
+                (params.pa, params.pb, params.pc, params.pd),
+                (0, 256, -256, 0)
+            );
+
+            // Twice as wide means sampling the sprite at half the rate
+            let stretched = Transform {
+                scale_x: Scale::from_num(2),
+                ..Transform::IDENTITY
+            };
+            let params = stretched.to_affine_parameters();
+            assert_eq!((params.pa, params.pd), (128, 256));
+        },
+        "test_affine_parameters",
+        "ensure transformations result in the inverse affine matrix",
+    );
+}
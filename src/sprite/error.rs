@@ -2,24 +2,43 @@ use gbfs_rs::GBFSError;
 /// The error returned in sprite allocation-related failure cases.
 #[derive(Clone, Debug, PartialEq)]
 pub enum HWSpriteAllocError {
-    OAMFull,
     VRAMFull,
+    /// All palette banks which aren't part of the shared palette are in use by other 4bpp sprites.
+    PaletteFull,
+    /// All sets of affine parameters are in use by sprites with other transformations.
+    AffineFull,
     File(GBFSError),
     SpriteVisibilityStackEmpty,
+    /// The sprite's metadata file is malformed.
+    InvalidMetadata,
+    /// The sprite's dimensions (width, height) aren't supported by the hardware.
+    UnsupportedSize(u16, u16),
+    /// The sprite's bits per pixel aren't supported.
+    UnsupportedBpp(u8),
+    /// The 4bpp sprite's palette doesn't have 16 colors (actual).
+    InvalidPalette(usize),
+    /// The sprite's tile data doesn't have the length its metadata says it should have (expected, actual).
+    DataLengthMismatch(usize, usize),
+    /// The sprite's tile data isn't made up of whole frames (length of a frame, actual).
+    PartialFrame(usize, usize),
 }
 
 impl core::fmt::Display for HWSpriteAllocError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         use HWSpriteAllocError::*;
         match self {
-            OAMFull => write!(
-                f,
-                "HWSpriteAllocError: Can't create sprite because OAM is full"
-            ),
             VRAMFull => write!(
                 f,
                 "HWSpriteAllocError: No contiguous free block of VRAM available to allocate hardware sprite"
             ),
+            PaletteFull => write!(
+                f,
+                "HWSpriteAllocError: No free palette bank available to allocate 4bpp hardware sprite"
+            ),
+            AffineFull => write!(
+                f,
+                "HWSpriteAllocError: No free set of affine parameters available to transform hardware sprite"
+            ),
             File(gbfs_err) => write!(
                 f,
                 "HWSpriteAllocError: Couldn't read sprite file: {}",
@@ -29,6 +48,32 @@ impl core::fmt::Display for HWSpriteAllocError {
                 f,
                 "HWSpriteAllocError: No sprite visibility info was saved before the call"
             ),
+            InvalidMetadata => write!(f, "HWSpriteAllocError: Sprite metadata is malformed"),
+            UnsupportedSize(x, y) => write!(
+                f,
+                "HWSpriteAllocError: Sprite size {}x{} is not supported by the hardware",
+                x, y
+            ),
+            UnsupportedBpp(bpp) => write!(
+                f,
+                "HWSpriteAllocError: Sprites with {} bits per pixel are not supported",
+                bpp
+            ),
+            InvalidPalette(len) => write!(
+                f,
+                "HWSpriteAllocError: Sprite palette should have 16 colors, but has {}",
+                len
+            ),
+            DataLengthMismatch(expected, actual) => write!(
+                f,
+                "HWSpriteAllocError: Sprite data should be {} bytes according to metadata, but is {} bytes",
+                expected, actual
+            ),
+            PartialFrame(frame_len, actual) => write!(
+                f,
+                "HWSpriteAllocError: Sprite data should consist of frames of {} bytes, but is {} bytes",
+                frame_len, actual
+            ),
         }
     }
 }
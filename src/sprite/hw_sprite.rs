@@ -1,4 +1,3 @@
-use core::convert::TryInto;
 use gba::oam::{ObjectShape, ObjectSize};
 
 #[allow(dead_code)]
@@ -24,33 +23,18 @@ pub enum HWSpriteSize {
 }
 
 impl HWSpriteSize {
-    /// Returns the sprite's size in bytes.
-    /// Note that this assumes 8bpp sprites for now.
-    pub fn to_size_in_bytes(&self) -> u32 {
-        use HWSpriteSize::*;
-        match self {
-            EightByEight => 64,
-            SixteenBySixteen => 256,
-            ThirtyTwoByThirtyTwo => 1024,
-            SixtyFourBySixtyFour => 4096,
-
-            EightBySixteen => 128,
-            EightByThirtyTwo => 256,
-
-            SixteenByEight => 128,
-            SixteenByThirtyTwo => 512,
-
-            ThirtyTwoByEight => 256,
-            ThirtyTwoBySixteen => 512,
-            ThirtyTwoBySixtyFour => 2048,
-
-            SixtyFourByThirtyTwo => 2048,
-        }
+    /// Returns the sprite's size in bytes, with the given bits per pixel (4 or 8).
+    pub fn to_size_in_bytes(&self, bpp: u8) -> u32 {
+        let (x, y) = self.to_size_in_px();
+        return x as u32 * y as u32 * bpp as u32 / 8;
     }
 
-    /// Calculates the amount of 32 byte blocks of VRAM required to fit the sprite.
-    pub fn to_num_of_32_byte_blocks(&self) -> usize {
-        return (self.to_size_in_bytes() / 64).try_into().unwrap();
+    /// Calculates the amount of 64 byte slots of VRAM required to fit a single frame of the sprite,
+    /// with the given bits per pixel (4 or 8).
+    /// A slot holds two 4bpp tiles or one 8bpp tile, so a 4bpp 8x8 sprite only fills half of its slot.
+    #[cfg(test)]
+    pub fn to_num_of_64_byte_slots(&self, bpp: u8) -> usize {
+        return (self.to_size_in_bytes(bpp) as usize + 63) / 64;
     }
 
     /// Calculates the sprite's size and shape in the format required by OAM.
@@ -78,6 +62,30 @@ impl HWSpriteSize {
         }
     }
 
+    /// Returns the sprite size with the given dimensions in pixels, if the hardware supports it.
+    pub fn from_size_in_px(x: u16, y: u16) -> Option<HWSpriteSize> {
+        use HWSpriteSize::*;
+        match (x, y) {
+            (8, 8) => Some(EightByEight),
+            (16, 16) => Some(SixteenBySixteen),
+            (32, 32) => Some(ThirtyTwoByThirtyTwo),
+            (64, 64) => Some(SixtyFourBySixtyFour),
+
+            (8, 16) => Some(EightBySixteen),
+            (8, 32) => Some(EightByThirtyTwo),
+
+            (16, 8) => Some(SixteenByEight),
+            (16, 32) => Some(SixteenByThirtyTwo),
+
+            (32, 8) => Some(ThirtyTwoByEight),
+            (32, 16) => Some(ThirtyTwoBySixteen),
+            (32, 64) => Some(ThirtyTwoBySixtyFour),
+
+            (64, 32) => Some(SixtyFourByThirtyTwo),
+            _ => None,
+        }
+    }
+
     /// Returns the size in pixels in the form (x, y).
     pub fn to_size_in_px(&self) -> (u16, u16) {
         use HWSpriteSize::*;
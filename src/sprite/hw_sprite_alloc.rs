@@ -1,15 +1,24 @@
+use super::affine::Transform;
+use super::shadow_oam::{self, NUM_AFFINE_PARAMETERS, NUM_OAM_ENTRIES};
 use super::*;
+use crate::assets::{compression, Asset, SpriteAsset};
 use crate::debug_log::*;
 
+use core::cell::RefCell;
 use core::convert::TryInto;
 use core::hash::{BuildHasher, BuildHasherDefault, Hash, Hasher};
 
+use alloc::borrow::Cow;
 use alloc::boxed::Box;
+use alloc::rc::Rc;
 use alloc::vec::Vec;
 use gba::{oam, palram, Color};
 use hashbrown::HashMap;
 use twox_hash::XxHash64;
 
+/// Number of 16 color palette banks in the object palette.
+const NUM_PALBANKS: usize = 16;
+
 #[derive(Debug, PartialEq)]
 enum SpriteBlockState {
     Unused,
@@ -21,6 +30,19 @@ enum SpriteBlockState {
 ///
 /// Sprites which have identical tiles to a sprite already in VRAM won't be loaded in order to
 /// save VRAM, instead reference counting is used to keep the number of tiles down.
+///
+/// 8bpp sprites share a single palette, while 4bpp sprites each come with a palette bank of 16 colors.
+/// Palette banks are reference counted the same way as tiles, and the ones covered by the shared palette
+/// are never handed out.
+///
+/// Rotated, scaled and double-size sprites need one of the 32 sets of affine parameters,
+/// which are shared by sprites with the same transformation and reference counted as well.
+///
+/// Sprites are freed by dropping their handles, which share the allocator's bookkeeping.
+///
+/// Allocated sprites aren't tied to a slot in OAM. Instead, `update_oam()` hands out the slots
+/// to visible sprites each frame, so there may be more sprites than OAM can hold as long as
+/// not all of them are visible at once.
 /// # Safety
 ///
 /// This assumes that it's in complete control over the memory storing the sprite palette,
@@ -30,24 +52,94 @@ enum SpriteBlockState {
 ///
 /// Using more than one HWSpriteAllocator is also UB.
 pub struct HWSpriteAllocator {
-    /// List of 32 byte regions in object VRAM (1 tile per region),
-    /// as well as how many OAM sprites use that particular tile.
-    allocation_map: Box<[(u16, SpriteBlockState); 1024]>,
+    /// Which parts of VRAM and OAM are in use, shared with every handle so that they can free themselves.
+    state: Rc<RefCell<SpriteAllocatorState>>,
+    /// Creates the hashers used for hashing the sprite data. This hash is what's stored in the HashMap.
+    hasher_builder: BuildHasherDefault<XxHash64>,
+    /// Palette shared by 8bpp sprites.
+    palette: Box<[Color; 256]>,
+    /// IDs and generations of visible sprites, see `hide_sprites_push()` and `show_sprites_pop()` docs for details
+    sprite_visibility_stack: Vec<Vec<(usize, u32)>>,
+}
+
+/// The part of the allocator which handles need in order to free themselves.
+pub(super) struct SpriteAllocatorState {
+    /// List of 64 byte slots in object VRAM (two 4bpp tiles or one 8bpp tile per slot),
+    /// as well as how many OAM sprites use that particular slot.
+    allocation_map: Box<[(u16, SpriteBlockState); sprite_dma::NUM_SPRITE_SLOTS]>,
     /// Maps the hash of a sprite's tile data to a slot, if any.
     /// Note that we don't use the sprite data directly here, in order to avoid dealing with lifetimes.
     allocation_hashmap: HashMap<u64, usize, BuildHasherDefault<XxHash64>>,
-    /// The hasher used for initially hashing the sprite data. This hash is what's stored in the HashMap.
-    hasher: XxHash64,
-    /// Sprite palette.
-    palette: Box<[Color; 256]>,
-    /// List keeping track of which slots in OAM are free.
-    oam_occupied_list: Box<[bool; 128]>,
-    /// See `hide_all_push()` and `show_all_pop()` docs for details
-    sprite_visibility_stack: Vec<Box<[bool; 128]>>,
+    /// Reference count and colors of each palette bank used by 4bpp sprites.
+    /// A reference count of 0 means the bank is free.
+    palbanks: [(u16, [u16; 16]); NUM_PALBANKS],
+    /// Number of palette banks at the beginning of the palette occupied by the shared palette
+    num_shared_palbanks: usize,
+    /// Reference count and transformation of each set of affine parameters.
+    /// A reference count of 0 means the set is free.
+    affine_slots: [(u16, Transform); NUM_AFFINE_PARAMETERS],
+    /// All allocated sprites, indexed by their ID. Freed IDs are reused.
+    sprites: Vec<Option<LogicalSprite>>,
+    /// How often each sprite ID was reused, to tell sprites with the same ID apart
+    sprite_generations: Vec<u32>,
+    /// How many OAM slots were handed out by the last `update_oam()`
+    num_oam_slots_used: usize,
+}
+
+/// Usage of object VRAM, e.g. for displaying in a debug overlay.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VRAMStats {
+    /// Number of 64 byte blocks not used by any sprite
+    pub free_blocks: usize,
+    /// Number of 64 byte blocks used by at least one sprite
+    pub used_blocks: usize,
+    /// Largest number of free blocks in a row, i.e. the largest sprite that can still be allocated
+    pub largest_free_run: usize,
+}
+
+/// A sprite as seen by its handle, which only gets an OAM slot while it's visible and there's room.
+pub(super) struct LogicalSprite {
+    /// Attributes which are copied into the sprite's OAM slot
+    pub attrs: oam::ObjectAttributes,
+    /// First block of the sprite's tiles, which may change when VRAM is compacted
+    pub starting_block: usize,
+    /// Visible sprites with lower values are given OAM slots first
+    pub culling_priority: u8,
+    /// Slot the sprite got during the last `update_oam()`, if any
+    pub oam_slot: Option<usize>,
+    /// Palette bank of the sprite if it's 4bpp
+    pub palbank: Option<usize>,
+    /// Rotation and scaling of the sprite
+    pub transform: Transform,
+    /// Whether the sprite is drawn in an area twice its size, so that transforming it doesn't clip it
+    pub double_size: bool,
+    /// Set of affine parameters used by the sprite, if it's transformed or double-size
+    pub affine_slot: Option<usize>,
+    /// Horizontal and vertical flip of the sprite from before it got a set of affine parameters,
+    /// as the affine index overwrites those bits
+    pub flip: (bool, bool),
+    /// Whether the sprite is culled for being off-screen, independently of whether it's visible
+    pub offscreen: bool,
+}
+
+impl LogicalSprite {
+    /// Returns how the sprite is rendered while it's visible.
+    pub fn visible_rendering(&self) -> oam::ObjectRender {
+        if self.affine_slot.is_none() {
+            return oam::ObjectRender::Normal;
+        }
+        if self.double_size {
+            return oam::ObjectRender::DoubleAreaAffine;
+        }
+        return oam::ObjectRender::Affine;
+    }
 }
 
 impl HWSpriteAllocator {
     /// Create a new hardware sprite allocator for sprites with the given palette.
+    ///
+    /// The palette is shared by all 8bpp sprites. It may be shorter than 256 colors,
+    /// leaving the palette banks it doesn't cover to 4bpp sprites.
     pub fn new(palette: &[u16]) -> HWSpriteAllocator {
         // Cast to palette color type
         let mut palette_as_gba_colors: [Color; 256] = [Color::default(); 256];
@@ -56,27 +148,37 @@ impl HWSpriteAllocator {
             palette_as_gba_colors[i] = color_as_gba_color;
         }
 
-        let entries = Box::new([(0, SpriteBlockState::Unused); 1024]);
+        let entries = Box::new([(0, SpriteBlockState::Unused); sprite_dma::NUM_SPRITE_SLOTS]);
         let pal = Box::new(palette_as_gba_colors);
-        let oam_occupied_list = Box::new([false; 128]);
 
         let hashmap: HashMap<u64, usize, BuildHasherDefault<XxHash64>> = Default::default();
         let hasher_builder: BuildHasherDefault<XxHash64> = Default::default();
-        let hasher = hasher_builder.build_hasher();
-        let sprite_visibility_stack: Vec<Box<[bool; 128]>> = Vec::new();
-        return HWSpriteAllocator {
+        let sprite_visibility_stack: Vec<Vec<(usize, u32)>> = Vec::new();
+        // The allocator owns all of OAM, so nothing may be displayed until it's allocated
+        shadow_oam::clear();
+        let state = SpriteAllocatorState {
             allocation_map: entries,
             allocation_hashmap: hashmap,
-            hasher,
+            palbanks: [(0, [0; 16]); NUM_PALBANKS],
+            num_shared_palbanks: (palette.len() + 15) / 16,
+            affine_slots: [(0, Transform::IDENTITY); NUM_AFFINE_PARAMETERS],
+            sprites: Vec::new(),
+            sprite_generations: Vec::new(),
+            num_oam_slots_used: 0,
+        };
+        return HWSpriteAllocator {
+            state: Rc::new(RefCell::new(state)),
+            hasher_builder,
             palette: pal,
-            oam_occupied_list,
             sprite_visibility_stack,
         };
     }
 
-    /// Initialize the allocator by copying the palette into VRAM.
-    ///  
+    /// Initialize the allocator by copying the palette into VRAM,
+    /// and start copying sprite changes into OAM during each vblank.
+    ///
     /// Note that you're still required to manually enable object display in DISPCNT in order to see the sprites.
+    /// Interrupts have to be enabled beforehand by calling `interrupt::init()`.
     /// # Safety
     ///
     /// Any other code manipulating the sprite palette after
@@ -86,134 +188,485 @@ impl HWSpriteAllocator {
         for (i, color) in self.palette.iter().enumerate() {
             pal_block.index(i).write(*color);
         }
+        shadow_oam::start_copying_on_vblank();
     }
 
-    /// Allocate the given sprite in VRAM from data contained in the file with the given name.
-    /// A sprite size has to be supplied because several different shapes of sprites share the same size.
-    /// TODO: Store sprite size info in the filesystem, so there's no need to keep track of it in code
+    /// Allocate the given sprite in VRAM from the asset filesystem.
+    /// The sprite's size and palette are taken from the asset manifest.
     pub fn alloc_from_fs_file(
         &mut self,
-        filename: &str,
-        sprite_size: HWSpriteSize,
+        sprite: &SpriteAsset,
     ) -> Result<HWSpriteHandle, HWSpriteAllocError> {
-        match crate::FS.get_file_data_by_name_as_u32_slice(filename) {
-            Ok(sprite_data) => return self.alloc(sprite_data, sprite_size),
+        let sprite_data = match crate::FS.get_file_data_by_name(sprite.asset.name) {
+            Ok(sprite_data) => compression::load::<u32>(sprite_data),
             Err(gbfs_err) => return Err(HWSpriteAllocError::File(gbfs_err)),
+        };
+        match sprite.palette {
+            Some(palette) => {
+                return self.alloc_4bpp(&sprite_data, sprite.size, &load_palette(&palette)?)
+            }
+            None => return self.alloc(&sprite_data, sprite.size),
         }
     }
 
-    /// Allocate the given sprite in VRAM.
+    /// Allocate all frames of the given sprite in VRAM from the asset filesystem,
+    /// taking its size and format from the metadata stored alongside it.
+    ///
+    /// Unlike `alloc_from_fs_file`, this verifies that the length of the sprite's data matches the metadata.
+    pub fn alloc_from_fs(
+        &mut self,
+        sprite: &SpriteAsset,
+    ) -> Result<HWSpriteHandle, HWSpriteAllocError> {
+        let meta = SpriteMeta::from_bytes(
+            crate::FS
+                .get_file_data_by_name(sprite.meta.name)
+                .map_err(HWSpriteAllocError::File)?,
+        )?;
+        let palette = match (meta.bpp, sprite.palette) {
+            (8, _) => None,
+            (4, Some(palette)) => Some(load_palette(&palette)?),
+            // The manifest is generated from the metadata, so they can only disagree if it's broken
+            (4, None) => return Err(HWSpriteAllocError::InvalidMetadata),
+            (bpp, _) => return Err(HWSpriteAllocError::UnsupportedBpp(bpp)),
+        };
+        let sprite_size = meta
+            .size()
+            .ok_or(HWSpriteAllocError::UnsupportedSize(meta.width, meta.height))?;
+        let sprite_data = crate::FS
+            .get_file_data_by_name(sprite.asset.name)
+            .map_err(HWSpriteAllocError::File)?;
+        // Check before decompressing, so that we don't decompress garbage
+        let data_len = compression::decompressed_len(sprite_data);
+        if data_len != meta.data_len_in_bytes() {
+            return Err(HWSpriteAllocError::DataLengthMismatch(
+                meta.data_len_in_bytes(),
+                data_len,
+            ));
+        }
+        let sprite_data = compression::load::<u32>(sprite_data);
+        match palette {
+            Some(palette) => return self.alloc_4bpp(&sprite_data, sprite_size, &palette),
+            None => return self.alloc(&sprite_data, sprite_size),
+        }
+    }
+
+    /// Allocate the given 8bpp sprite in VRAM, using the shared palette.
+    /// The data may contain several frames of the sprite one after another, see `HWSpriteHandle::set_frame()`.
+    /// It's freed again once the returned handle is dropped.
+    ///
+    /// The sprite is hidden at first and only shows up on screen once `update_oam()` is called.
     pub fn alloc(
         &mut self,
         sprite_data: &[u32],
         sprite_size: HWSpriteSize,
     ) -> Result<HWSpriteHandle, HWSpriteAllocError> {
-        // Check whether the sprite is already in VRAM by comparing it's hash
-        // FIXME: I think we don't correctly understand the Hasher interface, as identical
-        // sprites return different hashes. Maybe using by_address is part of the solution.
-        sprite_data.hash(&mut self.hasher);
-        let sprite_hash = self.hasher.finish();
+        return self.alloc_with_palette(sprite_data, sprite_size, None);
+    }
+
+    /// Allocate the given 4bpp sprite in VRAM, using the given palette bank of 16 colors.
+    /// Sprites with identical palettes share a palette bank, so the same tiles can be allocated
+    /// with a different palette in order to recolor them (e.g. per team) without using more VRAM.
+    /// It's freed again once the returned handle is dropped.
+    ///
+    /// The sprite is hidden at first and only shows up on screen once `update_oam()` is called.
+    pub fn alloc_4bpp(
+        &mut self,
+        sprite_data: &[u32],
+        sprite_size: HWSpriteSize,
+        palette: &[u16],
+    ) -> Result<HWSpriteHandle, HWSpriteAllocError> {
+        let palette: &[u16; 16] = palette
+            .try_into()
+            .map_err(|_| HWSpriteAllocError::InvalidPalette(palette.len()))?;
+        return self.alloc_with_palette(sprite_data, sprite_size, Some(palette));
+    }
+
+    /// Allocate the given sprite, which is 4bpp if it comes with a palette bank and 8bpp otherwise.
+    fn alloc_with_palette(
+        &mut self,
+        sprite_data: &[u32],
+        sprite_size: HWSpriteSize,
+        palette: Option<&[u16; 16]>,
+    ) -> Result<HWSpriteHandle, HWSpriteAllocError> {
+        let bpp = if palette.is_some() { 4 } else { 8 };
+        let frame_len = sprite_size.to_size_in_bytes(bpp) as usize / 4;
+        if sprite_data.is_empty() || sprite_data.len() % frame_len != 0 {
+            return Err(HWSpriteAllocError::PartialFrame(
+                frame_len * 4,
+                sprite_data.len() * 4,
+            ));
+        }
+        let num_frames = sprite_data.len() / frame_len;
+        // Check whether the sprite is already in VRAM by comparing it's hash.
+        // Every sprite needs a fresh hasher, as hashers accumulate everything written into them.
+        let mut hasher = self.hasher_builder.build_hasher();
+        sprite_data.hash(&mut hasher);
+        let sprite_hash = hasher.finish();
+        let mut state = self.state.borrow_mut();
         let starting_vram_tile_id: usize;
         debug_log!(
             Subsystems::HWSprite,
             "Allocating sprite with hash {:?}",
             sprite_hash
         );
-        if self.allocation_hashmap.contains_key(&sprite_hash) {
+        // A different sprite with the same hash must not be mistaken for this one
+        let present_block = state
+            .allocation_hashmap
+            .get(&sprite_hash)
+            .copied()
+            .filter(|block| sprite_dma::is_sprite_in_slot(sprite_data, *block));
+        if let Some(block) = present_block {
             debug_log!(
                 Subsystems::HWSprite,
                 "Sprite already present, not actually allocating"
             );
-            starting_vram_tile_id = *self.allocation_hashmap.get(&sprite_hash).unwrap();
-            self.allocation_map[starting_vram_tile_id].0 += 1;
+            starting_vram_tile_id = block;
+            state.add_reference(starting_vram_tile_id);
         } else {
             debug_log!(
                 Subsystems::HWSprite,
                 "Sprite not present, actually allocating"
             );
 
-            // Find first spot with enough contiguous free blocks to hold the sprite
-            let num_32b_blocks = sprite_size.to_num_of_32_byte_blocks();
-            starting_vram_tile_id = self.find_contiguous_free_blocks(num_32b_blocks)?;
+            // Find first spot with enough contiguous free blocks to hold all frames of the sprite
+            let num_slots = sprite_dma::num_occupied_slots(sprite_data);
+            starting_vram_tile_id = match state.find_contiguous_free_blocks(num_slots) {
+                Ok(block) => block,
+                // Enough blocks may be free, just not in a row
+                Err(HWSpriteAllocError::VRAMFull)
+                    if state.vram_stats().free_blocks >= num_slots =>
+                {
+                    state.compact_vram();
+                    state.find_contiguous_free_blocks(num_slots)?
+                }
+                Err(err) => return Err(err),
+            };
 
             debug_log!(
                 Subsystems::HWSprite,
                 "Beginning allocation at block #{} for {} block sprite",
                 starting_vram_tile_id,
-                num_32b_blocks
+                num_slots
             );
 
             // Copy the sprite into VRAM using DMA
-            sprite_dma::dma_copy_sprite(sprite_data, starting_vram_tile_id, sprite_size);
+            sprite_dma::dma_copy_sprite(sprite_data, starting_vram_tile_id);
 
             // Mark blocks as occupied, with a reference count of 1
-            self.allocation_map[starting_vram_tile_id] = (1, SpriteBlockState::Used);
-            for i in 1..num_32b_blocks {
-                self.allocation_map[starting_vram_tile_id + i] = (1, SpriteBlockState::Continue);
+            state.allocation_map[starting_vram_tile_id] = (1, SpriteBlockState::Used);
+            for i in 1..num_slots {
+                state.allocation_map[starting_vram_tile_id + i] = (1, SpriteBlockState::Continue);
             }
 
-            // Insert new sprite's hash into hashmap
-            self.allocation_hashmap
-                .insert(sprite_hash, starting_vram_tile_id);
+            // Insert new sprite's hash into hashmap, unless a colliding sprite is already in there
+            if !state.allocation_hashmap.contains_key(&sprite_hash) {
+                state
+                    .allocation_hashmap
+                    .insert(sprite_hash, starting_vram_tile_id);
+            }
+            debug_log!(
+                Subsystems::HWSprite,
+                "VRAM usage after allocation: {:?}",
+                state.vram_stats()
+            );
         }
 
-        // Assign a slot in OAM
-        let oam_slot = self.find_free_oam_slot()?;
-        self.oam_occupied_list[oam_slot] = true;
-        let (size, shape) = sprite_size.to_obj_size_and_shape();
+        let palbank = match palette {
+            Some(palette) => match state.add_palbank_reference(palette) {
+                Ok(palbank) => Some(palbank),
+                Err(err) => {
+                    state.free_tiles(starting_vram_tile_id, sprite_hash);
+                    return Err(err);
+                }
+            },
+            None => None,
+        };
 
-        self.prepare_oam_slot(
-            (starting_vram_tile_id * 2).try_into().unwrap(),
-            oam_slot,
-            size,
-            shape,
-        );
+        let (size, shape) = sprite_size.to_obj_size_and_shape();
+        let sprite = LogicalSprite {
+            attrs: initial_attributes(
+                (starting_vram_tile_id * 2).try_into().unwrap(),
+                size,
+                shape,
+                palbank,
+            ),
+            starting_block: starting_vram_tile_id,
+            culling_priority: HWSpriteHandle::DEFAULT_CULLING_PRIORITY,
+            oam_slot: None,
+            palbank,
+            transform: Transform::IDENTITY,
+            double_size: false,
+            affine_slot: None,
+            flip: (false, false),
+            offscreen: false,
+        };
+        // Reuse the ID of a freed sprite if possible
+        let sprite_id = match state.sprites.iter().position(|sprite| sprite.is_none()) {
+            Some(id) => {
+                state.sprites[id] = Some(sprite);
+                state.sprite_generations[id] = state.sprite_generations[id].wrapping_add(1);
+                id
+            }
+            None => {
+                state.sprites.push(Some(sprite));
+                state.sprite_generations.push(0);
+                state.sprites.len() - 1
+            }
+        };
         return Ok(HWSpriteHandle {
             sprite_size,
-            starting_block: starting_vram_tile_id,
-            oam_slot,
+            num_frames,
+            sprite_id,
             data_hash: sprite_hash,
+            allocator: Rc::clone(&self.state),
         });
     }
 
-    /// Prepares a slot in OAM for the sprite.
-    fn prepare_oam_slot(
-        &self,
-        starting_vram_tile_id: u16,
-        oam_slot: usize,
-        obj_size: oam::ObjectSize,
-        obj_shape: oam::ObjectShape,
-    ) {
-        oam::write_affine_parameters(
-            oam_slot,
-            oam::AffineParameters {
-                pa: 1,
-                pb: 0,
-                pc: 0,
-                pd: 1,
-            },
-        ); // Identity matrix, ensures that the last use's affine transform is wiped
-        oam::write_obj_attributes(
-            oam_slot,
-            oam::ObjectAttributes {
-                attr0: oam::OBJAttr0::new()
-                    .with_obj_rendering(oam::ObjectRender::Disabled)
-                    .with_obj_shape(obj_shape)
-                    .with_is_8bpp(true),
-                attr1: oam::OBJAttr1::new().with_obj_size(obj_size),
-                attr2: oam::OBJAttr2::new()
-                    .with_tile_id(starting_vram_tile_id)
-                    .with_priority(1),
-            },
-        );
+    /// Hand out the OAM slots to the visible sprites and write their attributes into the shadow OAM,
+    /// which is copied into OAM during the next vblank.
+    /// Has to be called once per frame, after sprites have been changed.
+    ///
+    /// If there are more visible sprites than slots, the ones with the lowest culling priority win,
+    /// with ties going to the lowest sprite ID (IDs of freed sprites are reused).
+    /// The rest isn't displayed this frame.
+    pub fn update_oam(&mut self) {
+        let mut state = self.state.borrow_mut();
+        let mut visible: Vec<(u8, usize)> = Vec::new();
+        for (id, sprite) in state.sprites.iter_mut().enumerate() {
+            if let Some(sprite) = sprite {
+                sprite.oam_slot = None;
+                if !sprite.offscreen
+                    && sprite.attrs.attr0.obj_rendering() != oam::ObjectRender::Disabled
+                {
+                    visible.push((sprite.culling_priority, id));
+                }
+            }
+        }
+        if visible.len() > NUM_OAM_ENTRIES {
+            debug_log!(
+                Subsystems::HWSprite,
+                "{} sprites visible, not displaying {} of them",
+                visible.len(),
+                visible.len() - NUM_OAM_ENTRIES
+            );
+        }
+        // Sprites in lower slots are drawn on top of others, so the important ones are in front as well
+        visible.sort_unstable();
+        visible.truncate(NUM_OAM_ENTRIES);
+
+        // The vblank handler mustn't copy a half-written table
+        shadow_oam::with_shadow_oam(|shadow| {
+            for (slot, (_, id)) in visible.iter().enumerate() {
+                let sprite = state.sprites[*id].as_mut().unwrap();
+                sprite.oam_slot = Some(slot);
+                shadow.write_obj_attributes(slot, sprite.attrs);
+            }
+            // Only slots which were in use last frame can contain anything that has to be hidden
+            for slot in visible.len()..state.num_oam_slots_used {
+                shadow.write_obj_attributes(slot, hidden_attributes());
+            }
+        });
+        state.num_oam_slots_used = visible.len();
+    }
+
+    /// Record which sprites are visible on an internal stack and hide all sprites.
+    ///
+    /// This is very useful to, for example, display a sprite-based menu
+    /// without interference from game sprites and then return
+    /// to the main game, restoring game sprites on screen without having to reinitialize each sprite.
+    pub fn hide_sprites_push(&mut self) {
+        let mut state = self.state.borrow_mut();
+        let state = &mut *state;
+        let mut visible_list = Vec::new();
+        for (id, sprite) in state.sprites.iter_mut().enumerate() {
+            if let Some(sprite) = sprite {
+                if sprite.attrs.attr0.obj_rendering() != oam::ObjectRender::Disabled {
+                    visible_list.push((id, state.sprite_generations[id]));
+                    sprite.attrs.attr0 = sprite
+                        .attrs
+                        .attr0
+                        .with_obj_rendering(oam::ObjectRender::Disabled);
+                }
+            }
+        }
+        self.sprite_visibility_stack.push(visible_list);
+    }
+
+    /// Restore which sprites are visible from an internal stack.
+    /// If `hide_sprites_push()` was not called beforehand this will error.
+    /// Sprites which were freed in the meantime stay hidden, even if their ID was reused by another sprite.
+    ///
+    /// This is very useful to, for example, display a sprite-based menu
+    /// without interference from game sprites and then return
+    /// to the main game, restoring game sprites on screen without having to reinitialize each sprite.
+    pub fn show_sprites_pop(&mut self) -> Result<(), HWSpriteAllocError> {
+        let visible_list = match self.sprite_visibility_stack.pop() {
+            Some(list) => list,
+            None => return Err(HWSpriteAllocError::SpriteVisibilityStackEmpty),
+        };
+        let mut state = self.state.borrow_mut();
+        let state = &mut *state;
+        for (id, generation) in visible_list {
+            if state.sprite_generations[id] != generation {
+                continue;
+            }
+            if let Some(sprite) = state.sprites[id].as_mut() {
+                sprite.attrs.attr0 = sprite
+                    .attrs
+                    .attr0
+                    .with_obj_rendering(sprite.visible_rendering());
+            }
+        }
+        return Ok(());
     }
-    /// Find a free slot in OAM.
-    /// If none are available, panic.
-    fn find_free_oam_slot(&self) -> Result<usize, HWSpriteAllocError> {
-        match self.oam_occupied_list.iter().position(|&x| !x) {
-            Some(pos) => Ok(pos),
-            None => return Err(HWSpriteAllocError::OAMFull),
+
+    /// Returns how many sprites are currently allocated, whether they're visible or not.
+    #[cfg(test)]
+    pub fn num_sprites(&self) -> usize {
+        let state = self.state.borrow();
+        return state
+            .sprites
+            .iter()
+            .filter(|sprite| sprite.is_some())
+            .count();
+    }
+
+    /// Returns how many palette banks are left for 4bpp sprites with new palettes.
+    #[cfg(test)]
+    pub fn num_free_palbanks(&self) -> usize {
+        let state = self.state.borrow();
+        return state.palbanks[state.num_shared_palbanks..]
+            .iter()
+            .filter(|(refcount, _)| *refcount == 0)
+            .count();
+    }
+
+    /// Returns how many sets of affine parameters are left for sprites with new transformations.
+    #[cfg(test)]
+    pub fn num_free_affine_slots(&self) -> usize {
+        let state = self.state.borrow();
+        return state
+            .affine_slots
+            .iter()
+            .filter(|(refcount, _)| *refcount == 0)
+            .count();
+    }
+
+    /// Returns how many sprites got an OAM slot during the last `update_oam()`.
+    #[cfg(test)]
+    pub fn num_sprites_in_oam(&self) -> usize {
+        return self.state.borrow().num_oam_slots_used;
+    }
+
+    /// Returns how many 64 byte blocks of object VRAM are not used by any sprite.
+    #[cfg(test)]
+    pub fn num_free_vram_blocks(&self) -> usize {
+        let state = self.state.borrow();
+        return state
+            .allocation_map
+            .iter()
+            .filter(|(_, block)| *block == SpriteBlockState::Unused)
+            .count();
+    }
+
+    /// Returns how much of object VRAM is used, and how fragmented the rest of it is.
+    /// The allocator logs these after each allocation.
+    #[cfg(test)]
+    pub fn vram_stats(&self) -> VRAMStats {
+        return self.state.borrow().vram_stats();
+    }
+
+    /// Moves the tiles of all sprites to the beginning of object VRAM, one after another,
+    /// so that the free blocks form a single run and large sprites fit again.
+    /// The sprites' tile IDs are patched accordingly, keeping the frame they show.
+    /// Returns how many blocks were moved.
+    ///
+    /// Allocating and freeing sprites leaves gaps, and the allocator only ever uses the first gap
+    /// large enough for a sprite, so this is worth doing when `vram_stats()` shows lots of free blocks
+    /// but no large run of them. Allocations which don't fit into any gap do so automatically.
+    ///
+    /// The new tile IDs only end up in OAM with the next `update_oam()` and vblank, while the tiles
+    /// are moved right away, so visible sprites show the wrong tiles for a frame.
+    /// Better hide them while compacting, e.g. between `hide_sprites_push()` and `show_sprites_pop()`.
+    // TODO: Call it on loading screens once there are any
+    #[allow(dead_code)]
+    pub fn compact_vram(&mut self) -> usize {
+        return self.state.borrow_mut().compact_vram();
+    }
+}
+
+impl SpriteAllocatorState {
+    /// Returns the sprite with the given ID.
+    pub(super) fn sprite(&mut self, sprite_id: usize) -> &mut LogicalSprite {
+        return self.sprites[sprite_id]
+            .as_mut()
+            .expect("Attempt to access a freed sprite");
+    }
+
+    /// Returns how much of object VRAM is used, see `HWSpriteAllocator::vram_stats()`.
+    fn vram_stats(&self) -> VRAMStats {
+        let mut stats = VRAMStats {
+            free_blocks: 0,
+            used_blocks: 0,
+            largest_free_run: 0,
+        };
+        let mut free_run = 0;
+        for (_, block) in self.allocation_map.iter() {
+            if *block == SpriteBlockState::Unused {
+                stats.free_blocks += 1;
+                free_run += 1;
+                stats.largest_free_run = stats.largest_free_run.max(free_run);
+            } else {
+                stats.used_blocks += 1;
+                free_run = 0;
+            }
         }
+        return stats;
+    }
+
+    /// Moves all sprites' tiles to the beginning of object VRAM, see `HWSpriteAllocator::compact_vram()`.
+    fn compact_vram(&mut self) -> usize {
+        let mut new_map = Box::new([(0, SpriteBlockState::Unused); sprite_dma::NUM_SPRITE_SLOTS]);
+        let mut num_moved = 0;
+        let mut next_free = 0;
+        let mut block = 0;
+        while block < self.allocation_map.len() {
+            if self.allocation_map[block].1 != SpriteBlockState::Used {
+                block += 1;
+                continue;
+            }
+            // The sprite consists of its first block and all the ones continuing it
+            let mut len = 1;
+            while block + len < self.allocation_map.len()
+                && self.allocation_map[block + len].1 == SpriteBlockState::Continue
+            {
+                len += 1;
+            }
+            // Blocks only ever move towards the beginning, so the sprite can't overwrite one which hasn't been moved yet
+            if block != next_free {
+                sprite_dma::dma_move_slots(block, next_free, len);
+                num_moved += len;
+                self.move_sprite_tiles(block, next_free);
+            }
+            for i in 0..len {
+                let refcount = self.allocation_map[block + i].0;
+                let block_state = if i == 0 {
+                    SpriteBlockState::Used
+                } else {
+                    SpriteBlockState::Continue
+                };
+                new_map[next_free + i] = (refcount, block_state);
+            }
+            next_free += len;
+            block += len;
+        }
+        self.allocation_map = new_map;
+        debug_log!(
+            Subsystems::HWSprite,
+            "Compacted VRAM, moving {} blocks",
+            num_moved
+        );
+        return num_moved;
     }
 
     /// Return the index of the beginning of the first area in the allocation map
@@ -221,14 +674,14 @@ impl HWSpriteAllocator {
     fn find_contiguous_free_blocks(&self, num_blocks: usize) -> Result<usize, HWSpriteAllocError> {
         for (i, (_refcount, block)) in self.allocation_map.iter().enumerate() {
             if *block == SpriteBlockState::Unused {
-                let mut free_blocks: usize = 1;
-                for j in 1..num_blocks {
-                    if self.allocation_map[i + j].1 == SpriteBlockState::Unused {
-                        free_blocks += 1;
-                    } else {
-                        break;
-                    }
-                }
+                // The run may not continue past the end of VRAM
+                let free_blocks = 1
+                    + (1..num_blocks)
+                        .take_while(|j| {
+                            i + j < self.allocation_map.len()
+                                && self.allocation_map[i + j].1 == SpriteBlockState::Unused
+                        })
+                        .count();
                 if free_blocks >= num_blocks {
                     return Ok(i);
                 }
@@ -237,86 +690,240 @@ impl HWSpriteAllocator {
         return Err(HWSpriteAllocError::VRAMFull);
     }
 
-    /// Drop the allocation of the given sprite.
-    /// Note that the sprite still exists in VRAM until overwritten (or reused if the refcount is not 0),
-    /// but is marked as inactive in OAM and therefore not displayed.
-    pub fn free(&mut self, handle: HWSpriteHandle) {
-        handle.set_visibility(false);
-        self.oam_occupied_list[handle.oam_slot] = false;
+    /// Increase the refcount of the sprite tiles starting at the given block.
+    fn add_reference(&mut self, starting_block: usize) {
+        self.allocation_map[starting_block].0 += 1;
+        let mut i = starting_block + 1;
+        while i < self.allocation_map.len()
+            && self.allocation_map[i].1 == SpriteBlockState::Continue
+        {
+            self.allocation_map[i].0 += 1;
+            i += 1;
+        }
+    }
+
+    /// Drop the sprite with the given ID, whose tiles have the given hash.
+    /// It disappears from the screen with the next `update_oam()`.
+    pub(super) fn free(&mut self, sprite_id: usize, data_hash: u64) {
+        let sprite = self.sprites[sprite_id]
+            .take()
+            .expect("Attempt to free a freed sprite");
+        if let Some(palbank) = sprite.palbank {
+            self.palbanks[palbank].0 -= 1;
+        }
+        if let Some(affine_slot) = sprite.affine_slot {
+            self.affine_slots[affine_slot].0 -= 1;
+        }
+        self.free_tiles(sprite.starting_block, data_hash);
+    }
 
+    /// Points all sprites and hashes whose tiles start at the given block to the new block instead.
+    fn move_sprite_tiles(&mut self, from_block: usize, to_block: usize) {
+        for sprite in self.sprites.iter_mut().flatten() {
+            if sprite.starting_block == from_block {
+                sprite.starting_block = to_block;
+                // Sprites showing a later frame keep showing it
+                let tile_id = sprite.attrs.attr2.tile_id() - ((from_block - to_block) * 2) as u16;
+                sprite.attrs.attr2 = sprite.attrs.attr2.with_tile_id(tile_id);
+            }
+        }
+        for block in self.allocation_hashmap.values_mut() {
+            if *block == from_block {
+                *block = to_block;
+            }
+        }
+    }
+
+    /// Changes the transformation and double-size mode of the sprite with the given ID,
+    /// moving it into a set of affine parameters which applies them.
+    /// Sprites which are neither transformed nor double-size don't need one.
+    ///
+    /// If no set is left for the transformation, the sprite stays as it is.
+    pub(super) fn set_transform(
+        &mut self,
+        sprite_id: usize,
+        transform: Transform,
+        double_size: bool,
+    ) -> Result<(), HWSpriteAllocError> {
+        let sprite = self.sprite(sprite_id);
+        let (old_transform, old_slot) = (sprite.transform, sprite.affine_slot);
+        // Release the old set first, as the sprite may be the only one using it
+        if let Some(old_slot) = old_slot {
+            self.affine_slots[old_slot].0 -= 1;
+        }
+        let new_slot = if transform != Transform::IDENTITY || double_size {
+            match self.add_affine_reference(transform) {
+                Ok(slot) => Some(slot),
+                Err(err) => {
+                    if old_slot.is_some() {
+                        // Can't fail, as there's at least the set which was just released
+                        self.add_affine_reference(old_transform).unwrap();
+                    }
+                    return Err(err);
+                }
+            }
+        } else {
+            None
+        };
+
+        let sprite = self.sprite(sprite_id);
+        sprite.transform = transform;
+        sprite.double_size = double_size;
+        sprite.affine_slot = new_slot;
+        let attr1 = sprite.attrs.attr1;
+        match (old_slot, new_slot) {
+            (None, Some(new_slot)) => {
+                sprite.flip = (attr1.hflip(), attr1.vflip());
+                sprite.attrs.attr1 = attr1.with_affine_index(new_slot as u16);
+            }
+            (Some(_), Some(new_slot)) => {
+                sprite.attrs.attr1 = attr1.with_affine_index(new_slot as u16);
+            }
+            // Outside of affine mode, the upper bits of the affine index are the flip bits
+            (Some(_), None) => {
+                sprite.attrs.attr1 = attr1
+                    .with_affine_index(0)
+                    .with_hflip(sprite.flip.0)
+                    .with_vflip(sprite.flip.1);
+            }
+            (None, None) => {}
+        }
+        if sprite.attrs.attr0.obj_rendering() != oam::ObjectRender::Disabled {
+            sprite.attrs.attr0 = sprite
+                .attrs
+                .attr0
+                .with_obj_rendering(sprite.visible_rendering());
+        }
+        return Ok(());
+    }
+
+    /// Returns a set of affine parameters applying the given transformation,
+    /// writing them into a free one if there's none yet, and increases its refcount.
+    fn add_affine_reference(&mut self, transform: Transform) -> Result<usize, HWSpriteAllocError> {
+        let slot = match self
+            .affine_slots
+            .iter()
+            .position(|(refcount, t)| *refcount > 0 && *t == transform)
+        {
+            Some(slot) => slot,
+            None => {
+                let slot = self
+                    .affine_slots
+                    .iter()
+                    .position(|(refcount, _)| *refcount == 0)
+                    .ok_or(HWSpriteAllocError::AffineFull)?;
+                shadow_oam::write_affine_parameters(slot, transform.to_affine_parameters())
+                    .unwrap();
+                self.affine_slots[slot].1 = transform;
+                slot
+            }
+        };
+        self.affine_slots[slot].0 += 1;
+        return Ok(slot);
+    }
+
+    /// Returns a palette bank containing the given colors, loading them into a free one if there's none yet,
+    /// and increases its refcount.
+    fn add_palbank_reference(&mut self, palette: &[u16; 16]) -> Result<usize, HWSpriteAllocError> {
+        let first = self.num_shared_palbanks;
+        let palbank = match self.palbanks[first..]
+            .iter()
+            .position(|(refcount, colors)| *refcount > 0 && colors == palette)
+        {
+            Some(i) => first + i,
+            None => {
+                let i = self.palbanks[first..]
+                    .iter()
+                    .position(|(refcount, _)| *refcount == 0)
+                    .ok_or(HWSpriteAllocError::PaletteFull)?;
+                debug_log!(
+                    Subsystems::HWSprite,
+                    "Loading palette into palbank #{}",
+                    first + i
+                );
+                for (j, color) in palette.iter().enumerate() {
+                    palram::index_palram_obj_4bpp((first + i) as u8, j as u8).write(Color(*color));
+                }
+                self.palbanks[first + i].1 = *palette;
+                first + i
+            }
+        };
+        self.palbanks[palbank].0 += 1;
+        return Ok(palbank);
+    }
+
+    /// Drop one reference to the sprite tiles starting at the given block.
+    /// Note that the sprite still exists in VRAM until overwritten (or reused if the refcount is not 0).
+    fn free_tiles(&mut self, starting_block: usize, data_hash: u64) {
         // Decrease refcount of first block
-        self.allocation_map[handle.starting_block].0 -= 1;
+        self.allocation_map[starting_block].0 -= 1;
         // Decrease refcount of  all blocks that are marked CONTINUE after the first block
         // (therefore part of this sprite)
         let mut i = 1;
-        loop {
-            if self.allocation_map[handle.starting_block + i].1 == SpriteBlockState::Continue {
-                self.allocation_map[handle.starting_block + i].0 -= 1;
-            } else {
-                break;
-            }
+        while starting_block + i < self.allocation_map.len()
+            && self.allocation_map[starting_block + i].1 == SpriteBlockState::Continue
+        {
+            self.allocation_map[starting_block + i].0 -= 1;
             i += 1;
         }
 
         // Mark starting block with refcount of 0 as free
-        if self.allocation_map[handle.starting_block].0 == 0 {
+        if self.allocation_map[starting_block].0 == 0 {
             debug_log!(Subsystems::HWSprite, "Refcount reached 0, freeing sprite");
-            self.allocation_map[handle.starting_block] = (0, SpriteBlockState::Unused);
-            self.allocation_hashmap.remove(&handle.data_hash);
+            self.allocation_map[starting_block] = (0, SpriteBlockState::Unused);
+            // The hash may belong to another sprite if they collided
+            if self.allocation_hashmap.get(&data_hash) == Some(&starting_block) {
+                self.allocation_hashmap.remove(&data_hash);
+            }
         }
         // Mark all CONTINUE blocks with refcount of 0 as free as well
         let mut i = 1;
-        loop {
-            if self.allocation_map[handle.starting_block + i].1 == SpriteBlockState::Continue {
-                if self.allocation_map[handle.starting_block + i].0 == 0 {
-                    self.allocation_map[handle.starting_block + i] = (0, SpriteBlockState::Unused);
-                }
-            } else {
-                break;
+        while starting_block + i < self.allocation_map.len()
+            && self.allocation_map[starting_block + i].1 == SpriteBlockState::Continue
+        {
+            if self.allocation_map[starting_block + i].0 == 0 {
+                self.allocation_map[starting_block + i] = (0, SpriteBlockState::Unused);
             }
             i += 1;
         }
     }
+}
 
-    /// Record which sprites are live on an internal stack and hide all sprites.
-    ///
-    /// This is very useful to, for example, display a sprite-based menu
-    /// without interference from game sprites and then return
-    /// to the main game, restoring game sprites on screen without having to reinitialize each sprite.
-    pub fn hide_sprites_push(&mut self) {
-        self.sprite_visibility_stack
-            .push(self.oam_occupied_list.clone());
-        for (slot, is_occupied) in self.oam_occupied_list.iter().enumerate() {
-            if *is_occupied {
-                let mut attrs = oam::read_obj_attributes(slot).unwrap();
-                attrs.attr0 = attrs.attr0.with_obj_rendering(oam::ObjectRender::Disabled);
-                oam::write_obj_attributes(slot, attrs);
-            }
-        }
-    }
+/// Returns the OAM attributes of a newly allocated, hidden sprite.
+/// 4bpp sprites use the given palette bank, 8bpp sprites don't have one.
+fn initial_attributes(
+    starting_vram_tile_id: u16,
+    obj_size: oam::ObjectSize,
+    obj_shape: oam::ObjectShape,
+    palbank: Option<usize>,
+) -> oam::ObjectAttributes {
+    return oam::ObjectAttributes {
+        attr0: oam::OBJAttr0::new()
+            .with_obj_rendering(oam::ObjectRender::Disabled)
+            .with_obj_shape(obj_shape)
+            .with_is_8bpp(palbank.is_none()),
+        attr1: oam::OBJAttr1::new().with_obj_size(obj_size),
+        attr2: oam::OBJAttr2::new()
+            .with_tile_id(starting_vram_tile_id)
+            .with_priority(1)
+            .with_palbank(palbank.unwrap_or(0) as u16),
+    };
+}
 
-    /// Restore which sprites are live on an internal stack.
-    /// If `hide_sprites_push()` was not called beforehand this will error.
-    ///
-    /// This is very useful to, for example, display a sprite-based menu
-    /// without interference from game sprites and then return
-    /// to the main game, restoring game sprites on screen without having to reinitialize each sprite.
-    pub fn show_sprites_pop(&mut self) -> Result<(), HWSpriteAllocError> {
-        match self.sprite_visibility_stack.pop() {
-            Some(list) => self.oam_occupied_list = list,
-            None => return Err(HWSpriteAllocError::SpriteVisibilityStackEmpty),
-        }
-        for (slot, is_occupied) in self.oam_occupied_list.iter().enumerate() {
-            if *is_occupied {
-                let mut attrs = oam::read_obj_attributes(slot).unwrap();
-                attrs.attr0 = attrs.attr0.with_obj_rendering(oam::ObjectRender::Normal);
-                oam::write_obj_attributes(slot, attrs);
-            } else {
-                let mut attrs = oam::read_obj_attributes(slot).unwrap();
-                attrs.attr0 = attrs.attr0.with_obj_rendering(oam::ObjectRender::Disabled);
-                oam::write_obj_attributes(slot, attrs);
-            }
-        }
-        return Ok(());
-    }
+/// Loads a palette bank from the asset filesystem.
+fn load_palette(palette: &Asset) -> Result<Cow<'static, [u16]>, HWSpriteAllocError> {
+    return Ok(compression::load::<u16>(
+        crate::FS
+            .get_file_data_by_name(palette.name)
+            .map_err(HWSpriteAllocError::File)?,
+    ));
+}
+
+/// Returns the OAM attributes of a slot without a sprite.
+fn hidden_attributes() -> oam::ObjectAttributes {
+    return oam::ObjectAttributes {
+        attr0: oam::OBJAttr0::new().with_obj_rendering(oam::ObjectRender::Disabled),
+        attr1: oam::OBJAttr1::new(),
+        attr2: oam::OBJAttr2::new(),
+    };
 }
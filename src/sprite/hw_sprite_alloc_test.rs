@@ -1,15 +1,16 @@
 use super::*;
+use crate::assets::{palettes, sprites};
 use crate::test::test;
-use crate::FS;
+
+use alloc::vec::Vec;
 
 // TODO: Write tests
 
 // Generic test setup code
 #[cfg(test)]
 fn test_setup() -> HWSpriteAllocator {
-    let pal = FS
-        .get_file_data_by_name_as_u16_slice("sprite_sharedPal")
-        .unwrap();
+    crate::interrupt::init();
+    let pal = palettes::SPRITE_SHARED.load::<u16>();
     let mut sprite_allocator = super::HWSpriteAllocator::new(&pal);
     sprite_allocator.init();
     return sprite_allocator;
@@ -26,54 +27,657 @@ fn test_sprite_alloc_init() {
     );
 }
 
-/// Ensure that OAM fits exactly 128 sprites
+/// Ensure that all OAM slots can be filled with visible sprites
 #[test_case]
 fn test_sprite_alloc_fill_oam() {
     test(
         &|| {
             let mut alloc = test_setup();
+            let mut handles = Vec::new();
             for _ in 0..128 {
-                alloc
-                    .alloc_from_fs_file("copper_wallTiles", HWSpriteSize::SixteenBySixteen)
-                    .unwrap();
+                let handle = alloc.alloc_from_fs_file(&sprites::COPPER_WALL).unwrap();
+                handle.set_visibility(true);
+                handles.push(handle);
             }
+            alloc.update_oam();
+            assert_eq!(alloc.num_sprites_in_oam(), 128);
+            assert!(handles.iter().all(|handle| handle.is_in_oam()));
         },
         "test_sprite_alloc_fill_oam",
         "ensure all OAM slots can be filled",
     );
 }
 
-/// Ensure that when OAM is full adding a new sprite fails
+/// Ensure that more sprites than fit into OAM can be allocated,
+/// and that the most important visible ones are displayed
 #[test_case]
 fn test_sprite_alloc_overfill_oam() {
     test(
         &|| {
             let mut alloc = test_setup();
-            for _ in 0..128 {
-                alloc
-                    .alloc_from_fs_file("copper_wallTiles", HWSpriteSize::SixteenBySixteen)
-                    .unwrap();
+            let mut handles = Vec::new();
+            for _ in 0..200 {
+                let handle = alloc.alloc_from_fs_file(&sprites::COPPER_WALL).unwrap();
+                handle.set_visibility(true);
+                handles.push(handle);
             }
-            match alloc.alloc_from_fs_file("copper_wallTiles", HWSpriteSize::SixteenBySixteen) {
-                Ok(_) => panic!("Expected allocation to fail with full OAM, but it didn't"),
-                Err(_) => return,
-            };
+            assert_eq!(alloc.num_sprites(), 200);
+            handles[150].set_culling_priority(0);
+            alloc.update_oam();
+            assert_eq!(alloc.num_sprites_in_oam(), 128);
+            assert!(handles[150].is_in_oam());
+            assert!(handles[0].is_in_oam());
+            assert!(!handles[199].is_in_oam());
+
+            // Hidden sprites make room for others
+            for handle in &handles[..100] {
+                handle.set_visibility(false);
+            }
+            alloc.update_oam();
+            assert_eq!(alloc.num_sprites_in_oam(), 100);
+            assert!(!handles[0].is_in_oam());
+            assert!(handles[199].is_in_oam());
         },
         "test_sprite_alloc_overfill_oam",
-        "ensure that when OAM is full adding a new sprite fails",
+        "ensure more sprites than OAM slots can be allocated, with the most important ones displayed",
+    );
+}
+
+/// Ensure that sprites can be allocated based on their metadata
+#[test_case]
+fn test_sprite_alloc_from_metadata() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let meta = SpriteMeta::from_bytes(
+                crate::FS
+                    .get_file_data_by_name(sprites::COPPER_WALL.meta.name)
+                    .unwrap(),
+            )
+            .unwrap();
+            assert_eq!(
+                meta.size().unwrap().to_size_in_px(),
+                sprites::COPPER_WALL.size.to_size_in_px()
+            );
+            alloc.alloc_from_fs(&sprites::COPPER_WALL).unwrap();
+        },
+        "test_sprite_alloc_from_metadata",
+        "ensure sprites can be allocated using the metadata stored in the FS",
+    );
+}
+
+/// Ensure that malformed metadata is rejected
+#[test_case]
+fn test_sprite_meta_rejects_bad_length() {
+    test(
+        &|| {
+            assert_eq!(
+                SpriteMeta::from_bytes(&[16, 0, 16]),
+                Err(HWSpriteAllocError::InvalidMetadata)
+            );
+        },
+        "test_sprite_meta_rejects_bad_length",
+        "ensure sprite metadata of the wrong length is rejected",
     );
 }
 
 /// Ensure that reclaiming OAM works
 #[test_case]
 fn test_reclaim_oam() {
-    // Alloc the maximum number of sprites
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let mut handles = Vec::new();
+            for _ in 0..128 {
+                let handle = alloc.alloc_from_fs_file(&sprites::COPPER_WALL).unwrap();
+                handle.set_visibility(true);
+                handles.push(handle);
+            }
+            alloc.update_oam();
+            assert_eq!(alloc.num_sprites_in_oam(), 128);
+            // Dropping a handle gives its slot back
+            handles.pop();
+            alloc.update_oam();
+            assert_eq!(alloc.num_sprites(), 127);
+            assert_eq!(alloc.num_sprites_in_oam(), 127);
+            drop(handles);
+            alloc.update_oam();
+            assert_eq!(alloc.num_sprites(), 0);
+            assert_eq!(alloc.num_sprites_in_oam(), 0);
+        },
+        "test_reclaim_oam",
+        "ensure dropping sprite handles frees their OAM slots",
+    );
+}
+
+/// Ensure that identical sprites share their tiles in VRAM
+#[test_case]
+fn test_dedup_identical_sprites() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let first = alloc.alloc_from_fs_file(&sprites::DART_SHIP).unwrap();
+            let free_blocks = alloc.num_free_vram_blocks();
+            let second = alloc.alloc_from_fs_file(&sprites::DART_SHIP).unwrap();
+            assert_eq!(alloc.num_free_vram_blocks(), free_blocks);
+            assert_eq!(first.starting_block(), second.starting_block());
+
+            // The tiles stay around as long as any sprite uses them
+            drop(first);
+            assert_eq!(alloc.num_free_vram_blocks(), free_blocks);
+            let third = alloc.alloc_from_fs_file(&sprites::DART_SHIP).unwrap();
+            assert_eq!(alloc.num_free_vram_blocks(), free_blocks);
+            assert_eq!(second.starting_block(), third.starting_block());
+        },
+        "test_dedup_identical_sprites",
+        "ensure allocating the same sprite twice doesn't use more VRAM",
+    );
+}
+
+/// Ensure that different sprites don't share their tiles
+#[test_case]
+fn test_no_dedup_different_sprites() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let free_blocks = alloc.num_free_vram_blocks();
+            let ship = alloc.alloc_from_fs_file(&sprites::DART_SHIP).unwrap();
+            let wall = alloc.alloc_from_fs_file(&sprites::COPPER_WALL).unwrap();
+            assert_ne!(ship.starting_block(), wall.starting_block());
+            assert_eq!(
+                alloc.num_free_vram_blocks(),
+                free_blocks
+                    - ship.sprite_size.to_num_of_64_byte_slots(ship.bpp()) * ship.num_frames()
+                    - wall.sprite_size.to_num_of_64_byte_slots(wall.bpp()) * wall.num_frames()
+            );
+        },
+        "test_no_dedup_different_sprites",
+        "ensure different sprites get their own VRAM",
+    );
+}
+
+/// Ensure that 4bpp sprites need half the VRAM of 8bpp ones
+#[test_case]
+fn test_4bpp_vram_usage() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let free_blocks = alloc.num_free_vram_blocks();
+            let sprite = alloc
+                .alloc_4bpp(&[1; 32], HWSpriteSize::SixteenBySixteen, &[0; 16])
+                .unwrap();
+            assert_eq!(sprite.bpp(), 4);
+            assert_eq!(alloc.num_free_vram_blocks(), free_blocks - 2);
+            // Even the smallest sprite needs a whole block, though
+            let small = alloc
+                .alloc_4bpp(&[2; 8], HWSpriteSize::EightByEight, &[0; 16])
+                .unwrap();
+            assert_eq!(alloc.num_free_vram_blocks(), free_blocks - 3);
+            drop(small);
+            assert_eq!(
+                alloc
+                    .alloc_4bpp(&[2; 8], HWSpriteSize::EightByEight, &[0; 8])
+                    .err(),
+                Some(HWSpriteAllocError::InvalidPalette(8))
+            );
+        },
+        "test_4bpp_vram_usage",
+        "ensure 4bpp sprites need half the VRAM of 8bpp ones",
+    );
+}
+
+/// Ensure that all frames of an animated sprite are allocated one after another
+#[test_case]
+fn test_animation_frames() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let free_blocks = alloc.num_free_vram_blocks();
+            // Four 4bpp 8x8 frames fit into two blocks
+            let sprite = alloc
+                .alloc_4bpp(&[3; 32], HWSpriteSize::EightByEight, &[0; 16])
+                .unwrap();
+            assert_eq!(sprite.num_frames(), 4);
+            assert_eq!(alloc.num_free_vram_blocks(), free_blocks - 2);
+            let first_tile = sprite.read_obj_attributes().attr2.tile_id();
+            sprite.set_frame(3);
+            assert_eq!(sprite.read_obj_attributes().attr2.tile_id(), first_tile + 3);
+            sprite.set_frame(4);
+            assert_eq!(sprite.read_obj_attributes().attr2.tile_id(), first_tile);
+
+            // Data has to consist of whole frames
+            assert_eq!(
+                alloc
+                    .alloc_4bpp(&[3; 12], HWSpriteSize::EightByEight, &[0; 16])
+                    .err(),
+                Some(HWSpriteAllocError::PartialFrame(32, 48))
+            );
+        },
+        "test_animation_frames",
+        "ensure all frames of an animated sprite are allocated one after another",
+    );
+}
+
+/// Ensure that 4bpp sprites with the same palette share a palette bank
+#[test_case]
+fn test_palbank_sharing() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let free_palbanks = alloc.num_free_palbanks();
+            assert!(free_palbanks > 0);
+            let first = alloc
+                .alloc_4bpp(&[1; 32], HWSpriteSize::SixteenBySixteen, &[1; 16])
+                .unwrap();
+            let second = alloc
+                .alloc_4bpp(&[2; 32], HWSpriteSize::SixteenBySixteen, &[1; 16])
+                .unwrap();
+            assert_eq!(alloc.num_free_palbanks(), free_palbanks - 1);
+            // Recoloring the same tiles only needs another palette bank
+            let free_blocks = alloc.num_free_vram_blocks();
+            let recolored = alloc
+                .alloc_4bpp(&[1; 32], HWSpriteSize::SixteenBySixteen, &[2; 16])
+                .unwrap();
+            assert_eq!(alloc.num_free_palbanks(), free_palbanks - 2);
+            assert_eq!(alloc.num_free_vram_blocks(), free_blocks);
+            assert_eq!(first.starting_block(), recolored.starting_block());
+
+            drop(first);
+            assert_eq!(alloc.num_free_palbanks(), free_palbanks - 2);
+            drop(second);
+            drop(recolored);
+            assert_eq!(alloc.num_free_palbanks(), free_palbanks);
+        },
+        "test_palbank_sharing",
+        "ensure 4bpp sprites with the same palette share a palette bank",
+    );
+}
+
+/// Ensure that running out of palette banks fails without leaking VRAM
+#[test_case]
+fn test_exhaust_palbanks() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let mut handles = Vec::new();
+            for i in 0..alloc.num_free_palbanks() {
+                handles.push(
+                    alloc
+                        .alloc_4bpp(&[1; 8], HWSpriteSize::EightByEight, &[i as u16; 16])
+                        .unwrap(),
+                );
+            }
+            let free_blocks = alloc.num_free_vram_blocks();
+            assert_eq!(
+                alloc
+                    .alloc_4bpp(&[2; 8], HWSpriteSize::EightByEight, &[0xFFFF; 16])
+                    .err(),
+                Some(HWSpriteAllocError::PaletteFull)
+            );
+            assert_eq!(alloc.num_free_vram_blocks(), free_blocks);
+        },
+        "test_exhaust_palbanks",
+        "ensure running out of palette banks fails without leaking VRAM",
+    );
 }
 
-/// Ensure panic on VRAM exhaustion
+/// Ensure that restoring visibility doesn't show sprites which reused the ID of a freed visible sprite
 #[test_case]
-fn test_exhaust_vram() {}
+fn test_visibility_stack_reused_id() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let kept = alloc.alloc(&[1; 16], HWSpriteSize::EightByEight).unwrap();
+            kept.set_visibility(true);
+            let freed = alloc.alloc(&[2; 16], HWSpriteSize::EightByEight).unwrap();
+            freed.set_visibility(true);
+            alloc.hide_sprites_push();
+            assert!(!kept.get_visibility());
+
+            drop(freed);
+            // Reuses the freed sprite's ID
+            let reused = alloc.alloc(&[3; 16], HWSpriteSize::EightByEight).unwrap();
+            alloc.show_sprites_pop().unwrap();
+            assert!(kept.get_visibility());
+            assert!(!reused.get_visibility());
+        },
+        "test_visibility_stack_reused_id",
+        "ensure restoring visibility doesn't show sprites which reused the ID of a freed visible sprite",
+    );
+}
+
+/// Ensure that sprites with the same transformation share affine parameters
+#[test_case]
+fn test_affine_sharing() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let free_slots = alloc.num_free_affine_slots();
+            let first = alloc.alloc_from_fs_file(&sprites::COPPER_WALL).unwrap();
+            let second = alloc.alloc_from_fs_file(&sprites::COPPER_WALL).unwrap();
+            first.set_rotation(0x2000).unwrap();
+            second.set_rotation(0x2000).unwrap();
+            assert_eq!(alloc.num_free_affine_slots(), free_slots - 1);
+            second
+                .set_scale(Scale::from_num(2), Scale::from_num(2))
+                .unwrap();
+            assert_eq!(alloc.num_free_affine_slots(), free_slots - 2);
+
+            // Sprites which aren't transformed anymore don't need affine parameters
+            first.set_rotation(0).unwrap();
+            assert_eq!(alloc.num_free_affine_slots(), free_slots - 1);
+            // Unless they're double-size
+            first.set_double_size(true).unwrap();
+            assert_eq!(alloc.num_free_affine_slots(), free_slots - 2);
+            drop(first);
+            drop(second);
+            assert_eq!(alloc.num_free_affine_slots(), free_slots);
+        },
+        "test_affine_sharing",
+        "ensure sprites with the same transformation share affine parameters",
+    );
+}
+
+/// Ensure that sprites which aren't transformed anymore get their flip bits back
+#[test_case]
+fn test_affine_restores_flip() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let handle = alloc.alloc_from_fs_file(&sprites::COPPER_WALL).unwrap();
+            handle.set_visibility(true);
+            let mut attrs = handle.read_obj_attributes();
+            attrs.attr1 = attrs.attr1.with_hflip(true);
+            handle.write_obj_attributes(attrs);
+
+            handle.set_rotation(0x2000).unwrap();
+            handle.set_rotation(0).unwrap();
+            let attrs = handle.read_obj_attributes();
+            assert_eq!(attrs.attr0.obj_rendering(), gba::oam::ObjectRender::Normal);
+            assert!(attrs.attr1.hflip());
+            assert!(!attrs.attr1.vflip());
+        },
+        "test_affine_restores_flip",
+        "ensure sprites which aren't transformed anymore get their flip bits back",
+    );
+}
+
+/// Ensure that running out of affine parameters leaves the sprite as it was
+#[test_case]
+fn test_exhaust_affine_slots() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let mut handles = Vec::new();
+            for i in 0..alloc.num_free_affine_slots() {
+                let handle = alloc.alloc_from_fs_file(&sprites::COPPER_WALL).unwrap();
+                handle.set_rotation((i as u16 + 1) << 8).unwrap();
+                handles.push(handle);
+            }
+            let handle = alloc.alloc_from_fs_file(&sprites::COPPER_WALL).unwrap();
+            assert_eq!(
+                handle.set_rotation(0x8000),
+                Err(HWSpriteAllocError::AffineFull)
+            );
+            // The sprite with the only reference to its parameters can still change them
+            handles[0].set_rotation(0x8000).unwrap();
+            assert_eq!(alloc.num_free_affine_slots(), 0);
+            handle.set_visibility(true);
+            assert_eq!(
+                handle.read_obj_attributes().attr0.obj_rendering(),
+                gba::oam::ObjectRender::Normal
+            );
+        },
+        "test_exhaust_affine_slots",
+        "ensure running out of affine parameters leaves the sprite as it was",
+    );
+}
+
+/// Ensure that allocating fails once VRAM is full, and works again once a sprite is freed
+#[test_case]
+fn test_exhaust_vram() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            // 8 8bpp 64x64 sprites of 64 blocks each fill VRAM
+            let mut handles = Vec::new();
+            for i in 0..8 {
+                let data: Vec<u32> = vec![i + 1; 1024];
+                handles.push(
+                    alloc
+                        .alloc(&data, HWSpriteSize::SixtyFourBySixtyFour)
+                        .unwrap(),
+                );
+            }
+            assert_eq!(alloc.num_free_vram_blocks(), 0);
+            assert_eq!(
+                alloc.alloc(&[99; 16], HWSpriteSize::EightByEight).err(),
+                Some(HWSpriteAllocError::VRAMFull)
+            );
+
+            drop(handles.pop());
+            let sprite = alloc.alloc(&[99; 16], HWSpriteSize::EightByEight).unwrap();
+            assert_eq!(sprite.starting_block(), 7 * 64);
+        },
+        "test_exhaust_vram",
+        "ensure allocating fails once VRAM is full, and works again once a sprite is freed",
+    );
+}
+
+/// Ensure that sprites which don't fit into the free blocks at the end of VRAM fail to allocate
+#[test_case]
+fn test_alloc_at_vram_end() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            // 7 8bpp 64x64 sprites and a sprite of 63 8bpp 8x8 frames leave only the last block free
+            let mut handles = Vec::new();
+            for i in 0..7 {
+                let data: Vec<u32> = vec![i + 1; 1024];
+                handles.push(
+                    alloc
+                        .alloc(&data, HWSpriteSize::SixtyFourBySixtyFour)
+                        .unwrap(),
+                );
+            }
+            handles.push(
+                alloc
+                    .alloc(&[50; 63 * 16], HWSpriteSize::EightByEight)
+                    .unwrap(),
+            );
+            assert_eq!(alloc.num_free_vram_blocks(), 1);
+
+            // Two frames would run past the end of VRAM
+            assert_eq!(
+                alloc.alloc(&[99; 32], HWSpriteSize::EightByEight).err(),
+                Some(HWSpriteAllocError::VRAMFull)
+            );
+            let sprite = alloc.alloc(&[99; 16], HWSpriteSize::EightByEight).unwrap();
+            assert_eq!(sprite.starting_block(), 511);
+        },
+        "test_alloc_at_vram_end",
+        "ensure sprites which don't fit into the free blocks at the end of VRAM fail to allocate",
+    );
+}
 
 /// Ensure reclaiming VRAM works
 #[test_case]
-fn test_reclaim_vram() {}
+fn test_reclaim_vram() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let free_blocks = alloc.num_free_vram_blocks();
+            // Allocating more sprites than fit into VRAM at once only works if they're freed again
+            for _ in 0..256 {
+                let handle = alloc.alloc_from_fs_file(&sprites::DART_SHIP).unwrap();
+                assert!(alloc.num_free_vram_blocks() < free_blocks);
+                drop(handle);
+            }
+            assert_eq!(alloc.num_free_vram_blocks(), free_blocks);
+        },
+        "test_reclaim_vram",
+        "ensure dropping sprite handles frees their VRAM",
+    );
+}
+
+/// Ensure that compacting VRAM closes the gaps left by freed sprites
+#[test_case]
+fn test_compact_vram() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let first = alloc.alloc(&[1; 16], HWSpriteSize::EightByEight).unwrap();
+            let second = alloc.alloc(&[2; 16], HWSpriteSize::EightByEight).unwrap();
+            // Two 8bpp 8x8 frames
+            let third = alloc.alloc(&[3; 32], HWSpriteSize::EightByEight).unwrap();
+            third.set_frame(1);
+            let stats = alloc.vram_stats();
+            assert_eq!(stats.used_blocks, 4);
+            assert_eq!(stats.free_blocks, 508);
+            assert_eq!(stats.largest_free_run, 508);
+
+            drop(first);
+            drop(second);
+            let stats = alloc.vram_stats();
+            assert_eq!(stats.free_blocks, 510);
+            assert_eq!(stats.largest_free_run, 508);
+
+            assert_eq!(alloc.compact_vram(), 2);
+            assert_eq!(alloc.vram_stats().largest_free_run, 510);
+            assert_eq!(third.starting_block(), 0);
+            // The sprite keeps showing the same frame, at its new place
+            assert_eq!(third.read_obj_attributes().attr2.tile_id(), 2);
+            assert!(sprite_dma::is_sprite_in_slot(&[3; 32], 0));
+            // Identical sprites are still found at the new place
+            let duplicate = alloc.alloc(&[3; 32], HWSpriteSize::EightByEight).unwrap();
+            assert_eq!(duplicate.starting_block(), 0);
+            assert_eq!(alloc.compact_vram(), 0);
+        },
+        "test_compact_vram",
+        "ensure compacting VRAM closes the gaps left by freed sprites",
+    );
+}
+
+/// Ensure that allocations which only fit once the gaps between sprites are closed compact VRAM
+#[test_case]
+fn test_alloc_compacts_vram() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            // 8 8bpp 64x64 sprites of 64 blocks each fill VRAM
+            let mut handles = Vec::new();
+            for i in 0..8 {
+                let data: Vec<u32> = vec![i + 1; 1024];
+                handles.push(
+                    alloc
+                        .alloc(&data, HWSpriteSize::SixtyFourBySixtyFour)
+                        .unwrap(),
+                );
+            }
+            // Free every other one, leaving gaps of 64 blocks
+            let handles: Vec<HWSpriteHandle> = handles.into_iter().skip(1).step_by(2).collect();
+            assert_eq!(alloc.vram_stats().largest_free_run, 64);
+
+            // Two frames need 128 blocks in a row
+            let data: Vec<u32> = vec![99; 2048];
+            let sprite = alloc
+                .alloc(&data, HWSpriteSize::SixtyFourBySixtyFour)
+                .unwrap();
+            assert_eq!(sprite.starting_block(), 4 * 64);
+            assert_eq!(alloc.vram_stats().largest_free_run, 512 - 6 * 64);
+            // The remaining sprites were moved and still find their tiles
+            for (i, handle) in handles.iter().enumerate() {
+                assert_eq!(handle.starting_block(), i * 64);
+                let data: Vec<u32> = vec![2 * i as u32 + 2; 1024];
+                assert!(sprite_dma::is_sprite_in_slot(&data, i * 64));
+            }
+        },
+        "test_alloc_compacts_vram",
+        "ensure allocations which only fit once gaps are closed compact VRAM",
+    );
+}
+
+/// Ensure that sprites at the edges of the screen wrap around correctly, and are culled once off-screen
+#[test_case]
+fn test_screen_pos() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let sprite = alloc.alloc(&[1; 16], HWSpriteSize::EightByEight).unwrap();
+            sprite.set_visibility(true);
+            let coords = |sprite: &HWSpriteHandle| {
+                let attrs = sprite.read_obj_attributes();
+                return (attrs.attr1.col_coordinate(), attrs.attr0.row_coordinate());
+            };
+            sprite.set_screen_pos(0, 0);
+            assert!(!sprite.is_offscreen());
+            assert_eq!(coords(&sprite), (0, 0));
+            sprite.set_screen_pos(-4, -7);
+            assert!(!sprite.is_offscreen());
+            assert_eq!(coords(&sprite), (508, 249));
+            sprite.set_screen_pos(239, 159);
+            assert!(!sprite.is_offscreen());
+
+            // Entirely off-screen, in every direction
+            for (x, y) in [(-8, 0), (0, -8), (240, 0), (0, 160)].iter() {
+                sprite.set_screen_pos(*x, *y);
+                assert!(sprite.is_offscreen());
+            }
+            // Culled sprites stay visible, but don't get a slot in OAM
+            assert!(sprite.get_visibility());
+            alloc.update_oam();
+            assert!(!sprite.is_in_oam());
+
+            // Moving hidden sprites back on screen doesn't show them
+            sprite.set_visibility(false);
+            sprite.set_screen_pos(0, 0);
+            assert!(!sprite.get_visibility());
+            sprite.set_visibility(true);
+
+            // The drawing area of double-size sprites reaches further
+            sprite.set_double_size(true).unwrap();
+            sprite.set_screen_pos(-10, 162);
+            assert!(!sprite.is_offscreen());
+            assert_eq!(coords(&sprite), (498, 158));
+        },
+        "test_screen_pos",
+        "ensure sprites at the edges of the screen wrap around and are culled once off-screen",
+    );
+}
+
+/// Ensure that the 128 pixel tall area of double-size 64x64 sprites never wraps around onto the screen
+#[test_case]
+fn test_screen_pos_tall_double_size() {
+    test(
+        &|| {
+            let mut alloc = test_setup();
+            let sprite = alloc
+                .alloc(&[1; 1024], HWSpriteSize::SixtyFourBySixtyFour)
+                .unwrap();
+            sprite.set_visibility(true);
+            sprite.set_double_size(true).unwrap();
+            let row = |sprite: &HWSpriteHandle| sprite.read_obj_attributes().attr0.row_coordinate();
+
+            // The area ends at row 216, well before it would wrap around
+            sprite.set_screen_pos(0, 120);
+            assert!(!sprite.is_offscreen());
+            assert_eq!(row(&sprite), 88);
+            // Reaches up to 92 rows past the top, so it starts below the screen's bottom edge
+            sprite.set_screen_pos(0, -60);
+            assert!(!sprite.is_offscreen());
+            assert_eq!(row(&sprite), 164);
+
+            // The area's top rows would show up at the bottom of the screen
+            sprite.set_screen_pos(0, -70);
+            assert!(sprite.is_offscreen());
+            // The area's bottom rows would show up at the top of the screen
+            sprite.set_screen_pos(0, 165);
+            assert!(sprite.is_offscreen());
+
+            sprite.set_screen_pos(0, 120);
+            assert!(!sprite.is_offscreen());
+        },
+        "test_screen_pos_tall_double_size",
+        "ensure the area of double-size 64x64 sprites never wraps around onto the screen",
+    );
+}
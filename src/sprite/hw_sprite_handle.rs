@@ -1,23 +1,43 @@
+use super::affine::Transform;
+use super::hw_sprite_alloc::SpriteAllocatorState;
 use super::*;
+use crate::shared_constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use core::cell::RefCell;
+
+use alloc::rc::Rc;
 use gba::oam;
 
-/// A handle to a hardware sprite allocated in VRAM/OAM.
+/// A handle to a hardware sprite allocated in VRAM.
 /// Also provides some wrappers to avoid the tedium of having to get an object, modify it, and write it back
 /// for commonly used object attributes.
+///
+/// Visible sprites are only put into OAM by `HWSpriteAllocator::update_oam()`, see `set_culling_priority()`.
+///
+/// The sprite is hidden and its VRAM is given back to the allocator once the handle is dropped,
+/// e.g. together with the `SpriteComponent` of a despawned entity.
 pub struct HWSpriteHandle {
     pub sprite_size: HWSpriteSize,
-    pub(super) starting_block: usize,
+    /// Number of frames stored one after another in VRAM
+    pub(super) num_frames: usize,
     pub(super) data_hash: u64,
-    pub(super) oam_slot: usize,
+    /// Index into the allocator's sprite table
+    pub(super) sprite_id: usize,
+    /// Bookkeeping of the allocator the sprite came from
+    pub(super) allocator: Rc<RefCell<SpriteAllocatorState>>,
 }
 
 impl HWSpriteHandle {
+    /// Culling priority of newly allocated sprites.
+    pub const DEFAULT_CULLING_PRIORITY: u8 = 128;
+
     /// Returns the OAM object attributes for the sprite.
     pub fn read_obj_attributes(&self) -> oam::ObjectAttributes {
-        return oam::read_obj_attributes(self.oam_slot).unwrap();
+        return self.allocator.borrow_mut().sprite(self.sprite_id).attrs;
     }
 
     /// Writes the OAM object attributes for the sprite.
+    /// They only take effect after the next `HWSpriteAllocator::update_oam()` and vblank.
     ///
     /// # Safety
     ///
@@ -27,26 +47,81 @@ impl HWSpriteHandle {
     /// The only reason why those fields are exposed is because it'd be too much work to create
     /// a wrapper for the OAM functionality of the gba crate that disallows this.
     pub fn write_obj_attributes(&self, attrs: oam::ObjectAttributes) {
-        oam::write_obj_attributes(self.oam_slot, attrs).unwrap();
+        self.allocator.borrow_mut().sprite(self.sprite_id).attrs = attrs;
+    }
+
+    /// Returns the sprite's bits per pixel, either 4 or 8.
+    pub fn bpp(&self) -> u8 {
+        if self.read_obj_attributes().attr0.is_8bpp() {
+            return 8;
+        }
+        return 4;
+    }
+
+    /// Returns the first 64 byte block of VRAM used by the sprite's tiles.
+    pub(super) fn starting_block(&self) -> usize {
+        return self
+            .allocator
+            .borrow_mut()
+            .sprite(self.sprite_id)
+            .starting_block;
+    }
+
+    /// Returns how many frames the sprite has.
+    pub fn num_frames(&self) -> usize {
+        return self.num_frames;
+    }
+
+    /// Shows the given frame of the sprite by pointing it at that frame's tiles,
+    /// so switching frames doesn't copy anything into VRAM.
+    /// Frames past the last one wrap around to the first.
+    pub fn set_frame(&self, frame: usize) {
+        let frame_len_in_tiles = self.sprite_size.to_size_in_bytes(self.bpp()) as usize / 32;
+        let tile_id = self.starting_block() * 2 + (frame % self.num_frames) * frame_len_in_tiles;
+        let mut attrs = self.read_obj_attributes();
+        attrs.attr2 = attrs.attr2.with_tile_id(tile_id as u16);
+        self.write_obj_attributes(attrs);
+    }
+
+    /// Sets how important it is to display the sprite when more sprites are visible than fit into OAM.
+    /// Sprites with lower values are displayed first.
+    pub fn set_culling_priority(&self, priority: u8) {
+        self.allocator
+            .borrow_mut()
+            .sprite(self.sprite_id)
+            .culling_priority = priority;
+    }
+
+    /// Returns whether the sprite got a slot in OAM during the last `HWSpriteAllocator::update_oam()`,
+    /// i.e. whether it's actually displayed.
+    #[cfg(test)]
+    pub fn is_in_oam(&self) -> bool {
+        return self
+            .allocator
+            .borrow_mut()
+            .sprite(self.sprite_id)
+            .oam_slot
+            .is_some();
     }
 
     // These are some wrappers to avoid the tedium of having to get an object, modify it, and write it back
     // for commonly used object attributes.
 
     /// Set the visibility of the sprite.
-    ///
-    /// Do not use to enable affine sprites.
+    /// Rotated, scaled and double-size sprites stay that way.
     pub fn set_visibility(&self, visible: bool) {
-        let mut attrs = self.read_obj_attributes();
-        if visible {
-            attrs.attr0 = attrs.attr0.with_obj_rendering(oam::ObjectRender::Normal);
+        let mut state = self.allocator.borrow_mut();
+        let sprite = state.sprite(self.sprite_id);
+        let rendering = if visible {
+            sprite.visible_rendering()
         } else {
-            attrs.attr0 = attrs.attr0.with_obj_rendering(oam::ObjectRender::Disabled);
-        }
-        self.write_obj_attributes(attrs);
+            oam::ObjectRender::Disabled
+        };
+        sprite.attrs.attr0 = sprite.attrs.attr0.with_obj_rendering(rendering);
     }
 
     /// Gets the visibility of the sprite.
+    /// Sprites stay visible while they're culled for being off-screen, see `set_offscreen()`.
     pub fn get_visibility(&self) -> bool {
         let attrs = self.read_obj_attributes();
         if attrs.attr0.obj_rendering() != oam::ObjectRender::Disabled {
@@ -55,27 +130,182 @@ impl HWSpriteHandle {
         return false;
     }
 
+    /// Culls the sprite for being off-screen, or stops doing so.
+    /// Unlike hiding it, this doesn't change whether the sprite is visible once it's back on screen,
+    /// so it doesn't undo `set_visibility()` or `HWSpriteAllocator::hide_sprites_push()`.
+    /// Culled sprites don't take up a slot in OAM either.
+    pub fn set_offscreen(&self, offscreen: bool) {
+        self.allocator.borrow_mut().sprite(self.sprite_id).offscreen = offscreen;
+    }
+
+    /// Returns whether the sprite is culled for being off-screen, see `set_offscreen()`.
+    #[cfg(test)]
+    pub fn is_offscreen(&self) -> bool {
+        return self.allocator.borrow_mut().sprite(self.sprite_id).offscreen;
+    }
+
     /// Sets the X position of the sprite.
+    /// This is where the left edge of the sprite would be if it wasn't transformed,
+    /// even in double-size mode.
     ///
     /// # Safety
     ///
     /// The position is not checked to be in bounds.
     pub fn set_x_pos(&self, pos: u16) {
+        let (offset_x, _) = self.double_size_offset();
         let mut attrs = self.read_obj_attributes();
-        attrs.attr1 = attrs.attr1.with_col_coordinate(pos);
+        attrs.attr1 = attrs
+            .attr1
+            .with_col_coordinate(pos.wrapping_sub(offset_x) & 0x1FF);
         self.write_obj_attributes(attrs);
     }
 
     /// Sets the Y position of the sprite.
+    /// This is where the top edge of the sprite would be if it wasn't transformed,
+    /// even in double-size mode.
     ///
     /// # Safety
     ///
     /// The position is not checked to be in bounds.
     pub fn set_y_pos(&self, pos: u16) {
+        let (_, offset_y) = self.double_size_offset();
         let mut attrs = self.read_obj_attributes();
-        attrs.attr0 = attrs.attr0.with_row_coordinate(pos);
+        attrs.attr0 = attrs
+            .attr0
+            .with_row_coordinate(pos.wrapping_sub(offset_y) & 0xFF);
         self.write_obj_attributes(attrs);
     }
+
+    /// Moves the sprite to the given position relative to the top-left corner of the screen,
+    /// which may be negative or past the screen's edges, e.g. for sprites partially scrolled out of view.
+    /// Like with `set_x_pos()` and `set_y_pos()`, this is where the top-left corner of the sprite would be
+    /// if it wasn't transformed.
+    ///
+    /// OAM only holds 9 bits of the X and 8 bits of the Y coordinate, so sprites reaching past 511 or 255
+    /// wrap around to the left or top edge of the screen, which is what makes negative coordinates work.
+    /// Sprites which are entirely off-screen are culled (see `set_offscreen()`), so that they don't wrap
+    /// around onto it (or take up a slot in OAM), and shown again once they're back unless they're hidden.
+    ///
+    /// Double-size sprites which are 64 pixels tall are drawn in an area 128 pixels tall,
+    /// more than the 96 rows of Y coordinates between the bottom of the screen and the wraparound.
+    /// Once the area reaches past either end of those, its far rows show up on the opposite edge
+    /// of the screen. That only happens while the sprite itself is entirely off-screen
+    /// (with at most the parts it's transformed into on it), so it's culled then as well.
+    pub fn set_screen_pos(&self, x: i32, y: i32) {
+        let (offset_x, offset_y) = self.double_size_offset();
+        let (width, height) = self.sprite_size.to_size_in_px();
+        // The area the hardware draws into, which is larger than the sprite in double-size mode
+        let (area_x, area_y) = (x - offset_x as i32, y - offset_y as i32);
+        let (area_width, area_height) = (
+            (width + 2 * offset_x) as i32,
+            (height + 2 * offset_y) as i32,
+        );
+        let is_onscreen = area_x < SCREEN_WIDTH as i32
+            && area_x + area_width > 0
+            && area_y < SCREEN_HEIGHT as i32
+            && area_y + area_height > 0;
+        // X coordinates have enough bits that no area wraps around onto the screen
+        let wraps_onto_screen = area_y + area_height > 256 || area_y + 256 < SCREEN_HEIGHT as i32;
+        if !is_onscreen || wraps_onto_screen {
+            self.set_offscreen(true);
+            return;
+        }
+
+        let mut attrs = self.read_obj_attributes();
+        attrs.attr1 = attrs.attr1.with_col_coordinate((area_x & 0x1FF) as u16);
+        attrs.attr0 = attrs.attr0.with_row_coordinate((area_y & 0xFF) as u16);
+        self.write_obj_attributes(attrs);
+        self.set_offscreen(false);
+    }
+
+    /// Rotates the sprite clockwise around its center.
+    ///
+    /// Sets of affine parameters are shared by sprites with the same rotation and scale,
+    /// and this fails if none is left for a new combination.
+    /// Parts of the sprite rotated outside of its size are cut off, unless double-size mode is enabled.
+    // TODO: Use for turrets, units and conveyors
+    #[allow(dead_code)]
+    pub fn set_rotation(&self, angle: Angle) -> Result<(), HWSpriteAllocError> {
+        let mut state = self.allocator.borrow_mut();
+        let sprite = state.sprite(self.sprite_id);
+        let transform = Transform {
+            angle,
+            ..sprite.transform
+        };
+        let double_size = sprite.double_size;
+        return state.set_transform(self.sprite_id, transform, double_size);
+    }
+
+    /// Scales the sprite around its center, horizontally and vertically.
+    /// Negative values flip the sprite.
+    ///
+    /// See `set_rotation()` for how affine parameters are shared.
+    // TODO: Use for units and effects
+    #[allow(dead_code)]
+    pub fn set_scale(&self, x: Scale, y: Scale) -> Result<(), HWSpriteAllocError> {
+        let mut state = self.allocator.borrow_mut();
+        let sprite = state.sprite(self.sprite_id);
+        let transform = Transform {
+            scale_x: x,
+            scale_y: y,
+            ..sprite.transform
+        };
+        let double_size = sprite.double_size;
+        return state.set_transform(self.sprite_id, transform, double_size);
+    }
+
+    /// Draws the sprite in an area twice its size, so that rotating or scaling it up doesn't cut it off.
+    /// The sprite stays centered on the same position.
+    ///
+    /// Double-size sprites need a set of affine parameters even if they aren't transformed,
+    /// see `set_rotation()`.
+    // TODO: Use for turrets, units and conveyors
+    #[allow(dead_code)]
+    pub fn set_double_size(&self, enabled: bool) -> Result<(), HWSpriteAllocError> {
+        let mut state = self.allocator.borrow_mut();
+        let sprite = state.sprite(self.sprite_id);
+        let (transform, was_enabled) = (sprite.transform, sprite.double_size);
+        state.set_transform(self.sprite_id, transform, enabled)?;
+        if was_enabled == enabled {
+            return Ok(());
+        }
+
+        // The area starts half a sprite further up and to the left, so that the sprite doesn't move
+        let (width, height) = self.sprite_size.to_size_in_px();
+        let (offset_x, offset_y) = if enabled {
+            (width / 2, height / 2)
+        } else {
+            ((width / 2).wrapping_neg(), (height / 2).wrapping_neg())
+        };
+        let attrs = &mut state.sprite(self.sprite_id).attrs;
+        attrs.attr1 = attrs
+            .attr1
+            .with_col_coordinate(attrs.attr1.col_coordinate().wrapping_sub(offset_x) & 0x1FF);
+        attrs.attr0 = attrs
+            .attr0
+            .with_row_coordinate(attrs.attr0.row_coordinate().wrapping_sub(offset_y) & 0xFF);
+        return Ok(());
+    }
+
+    /// Returns how far the sprite's drawing area is moved up and to the left by double-size mode.
+    fn double_size_offset(&self) -> (u16, u16) {
+        if !self
+            .allocator
+            .borrow_mut()
+            .sprite(self.sprite_id)
+            .double_size
+        {
+            return (0, 0);
+        }
+        let (width, height) = self.sprite_size.to_size_in_px();
+        return (width / 2, height / 2);
+    }
 }
 
-// TODO: impl Drop for HWSpriteHandle {} (currently causes a VRAM leak)
+impl Drop for HWSpriteHandle {
+    fn drop(&mut self) {
+        self.allocator
+            .borrow_mut()
+            .free(self.sprite_id, self.data_hash);
+    }
+}
@@ -1,24 +1,34 @@
 //! This module provides the ability to manage objects (hardware sprites) in video memory.
-//! The interface is allocator-like, with the ability to allocate and free sprites.
+//! The interface is allocator-like, with the ability to allocate sprites,
+//! which are freed again once their handles are dropped.
 //!
-//! Note that all sprites must share a palette.
+//! 8bpp sprites share a palette, while each 4bpp sprite brings a palette bank of its own.
 //!
 //! `DISPCNT` also has to be set for 1D mapping.
 //!
+//! Visible sprites are given OAM slots each frame, so more sprites than fit into OAM may be allocated.
+//! They're written to a shadow copy of OAM, which is copied into OAM during vblank (see `shadow_oam`).
+//!
 //! Heavily inspired by [this article](https://www.gamasutra.com/view/feature/131491/gameboy_advance_resource_management.php?print=1).
 //!
 //! # TODO:
 //! * Consider upstreaming to GBA crate.
-//! * Writes to OAM should only happen on VBlank, we should implement some sort of shadow OAM and copy on interrupt.
 
+mod affine;
 mod error;
 mod hw_sprite;
 mod hw_sprite_alloc;
 mod hw_sprite_handle;
+mod shadow_oam;
 mod sprite_dma;
+mod sprite_meta;
+pub use affine::{Angle, Scale};
 pub use error::HWSpriteAllocError;
 pub use hw_sprite::HWSpriteSize;
-pub use hw_sprite_alloc::HWSpriteAllocator;
+pub use hw_sprite_alloc::{HWSpriteAllocator, VRAMStats};
 pub use hw_sprite_handle::HWSpriteHandle;
+pub use sprite_meta::SpriteMeta;
+#[cfg(test)]
+mod affine_test;
 #[cfg(test)]
 mod hw_sprite_alloc_test;
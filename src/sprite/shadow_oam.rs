@@ -0,0 +1,120 @@
+//! A copy of OAM in regular memory, which is what the allocator and sprite handles actually modify.
+//!
+//! Writing to OAM while the screen is drawn causes tearing, and each write to it is slow.
+//! Therefore, the shadow copy is copied into OAM all at once using DMA3 during vblank.
+
+use crate::interrupt::{self, VBlankSlot};
+
+use core::convert::TryInto;
+
+use gba::io::dma;
+use gba::oam::{AffineParameters, OBJAttr0, OBJAttr1, OBJAttr2, ObjectAttributes, ObjectRender};
+use spinning_top::{const_spinlock, Spinlock};
+
+/// Number of objects in OAM.
+pub(super) const NUM_OAM_ENTRIES: usize = 128;
+/// Number of sets of affine parameters, which are stored in the unused parts of the object entries.
+pub(super) const NUM_AFFINE_PARAMETERS: usize = NUM_OAM_ENTRIES / 4;
+const OAM_BASE_ADDR: usize = 0x0700_0000;
+
+/// A single object entry, laid out the same way as in OAM.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct OAMEntry {
+    attr0: OBJAttr0,
+    attr1: OBJAttr1,
+    attr2: OBJAttr2,
+    /// Each group of 4 entries stores one set of affine parameters in these
+    affine_parameter: i16,
+}
+
+impl OAMEntry {
+    const EMPTY: OAMEntry = OAMEntry {
+        attr0: OBJAttr0::new(),
+        attr1: OBJAttr1::new(),
+        attr2: OBJAttr2::new(),
+        affine_parameter: 0,
+    };
+}
+
+/// All entries of OAM, aligned so that they can be copied using 32 bit DMA.
+#[repr(C, align(4))]
+pub(super) struct ShadowOAM([OAMEntry; NUM_OAM_ENTRIES]);
+
+impl ShadowOAM {
+    /// Writes the object attributes of the given OAM slot.
+    pub fn write_obj_attributes(&mut self, slot: usize, attrs: ObjectAttributes) {
+        let entry = &mut self.0[slot];
+        entry.attr0 = attrs.attr0;
+        entry.attr1 = attrs.attr1;
+        entry.attr2 = attrs.attr2;
+    }
+}
+
+/// Only locked for short batches of writes, so that the vblank handler rarely finds it locked.
+static SHADOW_OAM: Spinlock<ShadowOAM> =
+    const_spinlock(ShadowOAM([OAMEntry::EMPTY; NUM_OAM_ENTRIES]));
+
+/// Hides all objects and resets their attributes.
+pub(super) fn clear() {
+    let hidden = OAMEntry {
+        attr0: OBJAttr0::new().with_obj_rendering(ObjectRender::Disabled),
+        ..OAMEntry::EMPTY
+    };
+    let mut shadow = SHADOW_OAM.lock();
+    for entry in shadow.0.iter_mut() {
+        *entry = hidden;
+    }
+}
+
+/// Runs the given function with the shadow OAM locked, so that all of its writes
+/// are shown at once, with the first vblank after it's done.
+pub(super) fn with_shadow_oam<R>(f: impl FnOnce(&mut ShadowOAM) -> R) -> R {
+    let mut shadow = SHADOW_OAM.lock();
+    return f(&mut shadow);
+}
+
+/// Writes the given set of affine parameters.
+/// Returns None if there's no such set.
+pub(super) fn write_affine_parameters(index: usize, params: AffineParameters) -> Option<()> {
+    if index >= NUM_AFFINE_PARAMETERS {
+        return None;
+    }
+    let mut shadow = SHADOW_OAM.lock();
+    let entries = &mut shadow.0[index * 4..index * 4 + 4];
+    entries[0].affine_parameter = params.pa;
+    entries[1].affine_parameter = params.pb;
+    entries[2].affine_parameter = params.pc;
+    entries[3].affine_parameter = params.pd;
+    return Some(());
+}
+
+/// Start copying the shadow OAM into OAM during each vblank.
+///
+/// Interrupts have to be enabled first, see `interrupt::init()`.
+pub(super) fn start_copying_on_vblank() {
+    if !interrupt::vblank_isr_active(VBlankSlot::Sprites) {
+        interrupt::set_vblank_handler(VBlankSlot::Sprites, Some(&copy_to_oam));
+    }
+}
+
+/// Copies the shadow OAM into OAM using DMA3.
+/// Has to run during vblank, as the hardware doesn't allow OAM access while drawing anyway.
+fn copy_to_oam() {
+    // The game is in the middle of changing the sprites, so rather show the old state for another frame
+    let shadow = match SHADOW_OAM.try_lock() {
+        Some(shadow) => shadow,
+        None => return,
+    };
+    let num_words = core::mem::size_of::<ShadowOAM>() / 4;
+    unsafe {
+        dma::DMA3::set_source(shadow.0.as_ptr() as *const u32);
+        dma::DMA3::set_dest(OAM_BASE_ADDR as *mut u32);
+        dma::DMA3::set_count(num_words.try_into().unwrap());
+        dma::DMA3::set_control(
+            dma::DMAControlSetting::new()
+                .with_enabled(true)
+                .with_use_32bit(true),
+        );
+    }
+}
@@ -1,38 +1,90 @@
-use super::HWSpriteSize;
+use crate::interrupt;
 use core::convert::TryInto;
+use core::ptr;
 use gba::io::dma;
 
 const SPRITE_CHARBLOCK_BASE_ADDR: usize = 0x0601_0000;
 const NUM_BYTES_PER_SPRITE_TILE_SLOT: usize = 64;
+/// Number of sprite slots in the 32 KB of object VRAM (charblocks 4 and 5).
+/// Sprites' tile IDs count 32 byte tiles in 10 bits, so they can't address any more than that.
+pub(super) const NUM_SPRITE_SLOTS: usize = 512;
 
-/// Copies a sprite into the given sprite slot using DMA.
-/// The start slot can range from 0 to 1024, and represents an offset into charblocks 4 and 5 in VRAM,
+/// Returns the address of the given sprite slot in VRAM.
+fn slot_addr(slot: usize) -> usize {
+    return SPRITE_CHARBLOCK_BASE_ADDR + (slot * NUM_BYTES_PER_SPRITE_TILE_SLOT);
+}
+
+/// Returns whether the given sprite's tiles are in VRAM, beginning at the given sprite slot.
+pub fn is_sprite_in_slot(sprite_tile_data: &[u32], start_slot: usize) -> bool {
+    // Anything past the end of sprite VRAM can't match
+    if start_slot * NUM_BYTES_PER_SPRITE_TILE_SLOT + sprite_tile_data.len() * 4
+        > NUM_SPRITE_SLOTS * NUM_BYTES_PER_SPRITE_TILE_SLOT
+    {
+        return false;
+    }
+    let base_addr = slot_addr(start_slot);
+    return sprite_tile_data.iter().enumerate().all(|(i, word)| {
+        // Safety: Within sprite VRAM, as checked above
+        let vram_word = unsafe { ptr::read_volatile((base_addr + i * 4) as *const u32) };
+        return vram_word == *word;
+    });
+}
+
+/// Returns how many sprite slots the given tile data occupies, e.g. all frames of a sprite.
+pub fn num_occupied_slots(sprite_tile_data: &[u32]) -> usize {
+    return (sprite_tile_data.len() * 4 + NUM_BYTES_PER_SPRITE_TILE_SLOT - 1)
+        / NUM_BYTES_PER_SPRITE_TILE_SLOT;
+}
+
+/// Copies a sprite's tiles into the given sprite slot using DMA.
+/// The start slot can range from 0 to `NUM_SPRITE_SLOTS`, and represents an offset into charblocks 4 and 5 in VRAM,
 /// which are usable for sprite tiles.
-pub fn dma_copy_sprite(sprite_tile_data: &[u32], start_slot: usize, sprite_size: HWSpriteSize) {
-    let expected_num_sprite_u32s: usize = (sprite_size.to_size_in_bytes() / 4).try_into().unwrap();
-    // A single sprite tile consists of 16 u32's worth of data
-    let num_occupied_slots: usize = expected_num_sprite_u32s / 16;
+pub fn dma_copy_sprite(sprite_tile_data: &[u32], start_slot: usize) {
+    let num_occupied_slots = num_occupied_slots(sprite_tile_data);
     // Make sure we don't DMA into random memory
-    if (start_slot + num_occupied_slots) >= 1024 {
+    if (start_slot + num_occupied_slots) > NUM_SPRITE_SLOTS {
         panic!(
             "Attempt to write to invalid sprite start slot (Start slot: {}), sprite size: {}",
             start_slot, num_occupied_slots
         );
     }
-    // Ensure we were given enough data
-    if sprite_tile_data.len() != expected_num_sprite_u32s {
-        panic!("Attempt to create hardware sprite with incorrect amount of data for given size");
-    }
     // Perform transfer
-    let dest_addr = SPRITE_CHARBLOCK_BASE_ADDR + (start_slot * NUM_BYTES_PER_SPRITE_TILE_SLOT);
-    unsafe {
-        dma::DMA3::set_source(sprite_tile_data.as_ptr());
-        dma::DMA3::set_dest((dest_addr) as *mut u32);
-        dma::DMA3::set_count(sprite_tile_data.len().try_into().unwrap());
+    dma_copy_words(
+        sprite_tile_data.as_ptr(),
+        slot_addr(start_slot),
+        sprite_tile_data.len(),
+    );
+}
+
+/// Moves the contents of the given number of sprite slots to another slot using DMA.
+/// The areas may overlap as long as the destination comes first.
+pub fn dma_move_slots(from_slot: usize, to_slot: usize, num_slots: usize) {
+    if to_slot > from_slot || from_slot + num_slots > NUM_SPRITE_SLOTS {
+        panic!(
+            "Attempt to move sprite slots {}..{} to invalid slot {}",
+            from_slot,
+            from_slot + num_slots,
+            to_slot
+        );
+    }
+    dma_copy_words(
+        slot_addr(from_slot) as *const u32,
+        slot_addr(to_slot),
+        num_slots * NUM_BYTES_PER_SPRITE_TILE_SLOT / 4,
+    );
+}
+
+/// Copies the given number of words to the destination address using DMA, one after another.
+fn dma_copy_words(source: *const u32, dest_addr: usize, num_words: usize) {
+    // The vblank handler uses DMA3 as well, so it mustn't interrupt setting it up
+    interrupt::without_interrupts(|| unsafe {
+        dma::DMA3::set_source(source);
+        dma::DMA3::set_dest(dest_addr as *mut u32);
+        dma::DMA3::set_count(num_words.try_into().unwrap());
         dma::DMA3::set_control(
             dma::DMAControlSetting::new()
                 .with_enabled(true)
                 .with_use_32bit(true),
         );
-    }
+    });
 }
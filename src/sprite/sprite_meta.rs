@@ -0,0 +1,53 @@
+use super::{HWSpriteAllocError, HWSpriteSize};
+
+use core::convert::TryInto;
+
+/// Length of a sprite metadata file in bytes.
+const META_LEN: usize = 6;
+
+/// Metadata describing a sprite's tile data, stored next to the tiles in GBFS.
+///
+/// For a sprite whose tiles are stored in `{name}Tiles`, the metadata is stored in `{name}Meta`.
+/// It consists of the little-endian `u16` width and height of a single frame in pixels,
+/// followed by the bits per pixel and the number of frames as `u8`s.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteMeta {
+    /// Width of a frame in pixels
+    pub width: u16,
+    /// Height of a frame in pixels
+    pub height: u16,
+    /// Bits per pixel
+    pub bpp: u8,
+    /// Number of frames, stored one after another in the tile data
+    pub frames: u8,
+}
+
+impl SpriteMeta {
+    /// Parses the contents of a metadata file.
+    pub fn from_bytes(bytes: &[u8]) -> Result<SpriteMeta, HWSpriteAllocError> {
+        if bytes.len() != META_LEN {
+            return Err(HWSpriteAllocError::InvalidMetadata);
+        }
+        return Ok(SpriteMeta {
+            width: u16::from_le_bytes(bytes[0..2].try_into().unwrap()),
+            height: u16::from_le_bytes(bytes[2..4].try_into().unwrap()),
+            bpp: bytes[4],
+            frames: bytes[5],
+        });
+    }
+
+    /// Returns the hardware sprite size matching the dimensions of a frame, if any.
+    pub fn size(&self) -> Option<HWSpriteSize> {
+        return HWSpriteSize::from_size_in_px(self.width, self.height);
+    }
+
+    /// Length of a single frame's tile data in bytes.
+    pub fn frame_len_in_bytes(&self) -> usize {
+        return self.width as usize * self.height as usize * self.bpp as usize / 8;
+    }
+
+    /// Length of the tile data of all frames in bytes.
+    pub fn data_len_in_bytes(&self) -> usize {
+        return self.frame_len_in_bytes() * self.frames as usize;
+    }
+}
@@ -0,0 +1,33 @@
+//! This module implements a system which steps sprite animations.
+
+use crate::components::{SpriteAnimationComponent, SpriteComponent};
+
+use tiny_ecs::Entities;
+
+fn have_animations(ecs: &mut Entities) -> bool {
+    match ecs.borrow_mut::<SpriteAnimationComponent>() {
+        Ok(_) => return true,
+        Err(_) => return false,
+    }
+}
+
+/// Advances the animation of each live entity by one tick,
+/// switching its sprite to the next frame when it's time.
+/// Entities without a sprite have nothing to animate and are skipped.
+pub fn tick(ecs: &mut Entities, live_entities: &[usize]) {
+    if have_animations(ecs) && ecs.borrow::<SpriteComponent>().is_ok() {
+        let mut animations = ecs.borrow_mut::<SpriteAnimationComponent>().unwrap();
+        let mut sprites = ecs.borrow_mut::<SpriteComponent>().unwrap();
+        for id in live_entities {
+            if ecs.entity_contains::<SpriteAnimationComponent>(*id)
+                && ecs.entity_contains::<SpriteComponent>(*id)
+            {
+                let e_animation = animations.get_mut(*id).unwrap();
+                let handle = sprites.get_mut(*id).unwrap().get_handle();
+                if let Some(frame) = e_animation.tick(handle.num_frames()) {
+                    handle.set_frame(frame);
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,13 @@
 //! This system is responsible for executing the orders of `BuilderComponent`.
 
-use crate::components::BuilderComponent;
+use crate::components::{
+    BuilderComponent, ColliderComponent, CompositeSpriteComponent, ItemSourceComponent,
+    MinerComponent, PositionComponent, SpriteComponent,
+};
+use crate::map::Map;
+use crate::shared_constants::TILE_SIZE_IN_PX;
+use crate::shared_types::Coordinate;
+use crate::spatial::SpatialHash;
 use crate::sprite::HWSpriteAllocator;
 use crate::{debug_log, debug_log::Subsystems};
 
@@ -8,10 +15,16 @@ use alloc::vec::Vec;
 
 use tiny_ecs::Entities;
 
-/// Tick the system by placing the object to be built into the world, if any.
+/// Tick the system by placing the object to be built into the world, if any,
+/// or deconstructing the building under the builder.
+/// Nothing is built where it would overlap another building in the spatial index,
+/// and built objects are added to it.
+/// Units (anything with a collider, including the builder itself) don't block building.
 pub fn tick(
     ecs: &mut Entities,
     live_entities: &mut Vec<usize>,
+    map: &mut Map,
+    spatial: &mut SpatialHash,
     sprite_alloc: &mut HWSpriteAllocator,
 ) {
     for id in live_entities.clone() {
@@ -21,20 +34,103 @@ pub fn tick(
             let builder = e_builder.clone();
             // Gotta make borrow checker happy here
             drop(builders);
-            if builder.buildable.is_some() {
-                debug_log!(Subsystems::BuilderSystem, "Building");
-                // Create a new miner
-                let built_entity_id = builder
-                    .buildable
-                    .unwrap()
-                    .build(builder.pos.unwrap(), ecs, sprite_alloc)
-                    .unwrap();
-                live_entities.push(built_entity_id);
+            if builder.deconstruct {
+                deconstruct_at_builder(id, ecs, live_entities, map, spatial);
             }
-            // Ensure nothing gets built next tick
+            if let Some(buildable) = builder.buildable {
+                let pos = builder.pos.unwrap();
+                // Buildings are placed on the tile grid
+                let tile_size = TILE_SIZE_IN_PX as u32;
+                let x = pos.0.to_num::<u32>() / tile_size * tile_size;
+                let y = pos.1.to_num::<u32>() / tile_size * tile_size;
+                let size = map.get_building_size_in_px(buildable.building());
+                let blocked = spatial
+                    .query_rect(x, y, size, size)
+                    .into_iter()
+                    .any(|other| is_building(ecs, id, other));
+                if !blocked {
+                    debug_log!(Subsystems::BuilderSystem, "Building");
+                    // Create a new miner
+                    let grid_pos = (Coordinate::from_num(x), Coordinate::from_num(y));
+                    let built_entity_id =
+                        buildable.build(grid_pos, ecs, map, sprite_alloc).unwrap();
+                    spatial.insert(built_entity_id, x, y, size, size);
+                    live_entities.push(built_entity_id);
+                } else {
+                    debug_log!(
+                        Subsystems::BuilderSystem,
+                        "Another building is in the way, not building"
+                    );
+                }
+            }
+            // Ensure nothing gets built or deconstructed next tick
             let mut builders = ecs.borrow_mut::<BuilderComponent>().unwrap();
             let e_builder = builders.get_mut(id).unwrap();
             e_builder.buildable = None;
+            e_builder.deconstruct = false;
+        }
+    }
+}
+
+/// Returns whether the entity in the spatial index is a building, rather than the builder or a unit.
+fn is_building(ecs: &Entities, builder_id: usize, id: usize) -> bool {
+    return id != builder_id && !ecs.entity_contains::<ColliderComponent>(id);
+}
+
+/// Removes the building under the center of the builder's sprite from the map, the spatial index and the ECS, if any.
+fn deconstruct_at_builder(
+    builder_id: usize,
+    ecs: &mut Entities,
+    live_entities: &mut Vec<usize>,
+    map: &mut Map,
+    spatial: &mut SpatialHash,
+) {
+    let (x, y) = {
+        let positions = ecs.borrow::<PositionComponent>().unwrap();
+        let (x, y) = positions.get(builder_id).unwrap().floor();
+        let mut sprites = ecs.borrow_mut::<SpriteComponent>().unwrap();
+        let (width, height) = sprites
+            .get_mut(builder_id)
+            .unwrap()
+            .get_handle()
+            .sprite_size
+            .to_size_in_px();
+        (x + width as u32 / 2, y + height as u32 / 2)
+    };
+    let target = spatial
+        .query_point(x, y)
+        .into_iter()
+        .find(|other| is_building(ecs, builder_id, *other));
+    let target = match target {
+        Some(target) => target,
+        None => {
+            debug_log!(
+                Subsystems::BuilderSystem,
+                "Nothing to deconstruct at {}, {}",
+                x,
+                y
+            );
+            return;
         }
+    };
+    debug_log!(Subsystems::BuilderSystem, "Deconstructing {}", target);
+    let positions = ecs.borrow::<PositionComponent>().unwrap();
+    let (building_x, building_y) = positions.get(target).unwrap().floor();
+    drop(positions);
+    map.remove_building(building_x, building_y);
+    spatial.remove(target);
+    live_entities.retain(|other| *other != target);
+    // Without its components, none of the systems see the entity anymore.
+    // Dropping the sprites frees their VRAM and OAM slots.
+    ecs.rm_component::<PositionComponent>(target).unwrap();
+    if ecs.entity_contains::<CompositeSpriteComponent>(target) {
+        ecs.rm_component::<CompositeSpriteComponent>(target)
+            .unwrap();
+    }
+    if ecs.entity_contains::<MinerComponent>(target) {
+        ecs.rm_component::<MinerComponent>(target).unwrap();
+    }
+    if ecs.entity_contains::<ItemSourceComponent>(target) {
+        ecs.rm_component::<ItemSourceComponent>(target).unwrap();
     }
 }
@@ -6,11 +6,21 @@ use alloc::boxed::Box;
 use gba::io::keypad;
 use tiny_ecs::{ECSError, Entities};
 
+/// Buttons which don't concern any entity directly, but the game loop.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct GameButtons {
+    /// Start switches between game modes
+    pub start_pressed: bool,
+    /// Select opens the minimap
+    pub select_pressed: bool,
+}
+
 /// This system reads and processes player input.
 pub(crate) struct InputSystem {
     // Tracks the key state of the last update to allow checking for differences w/ current state
     last_keys: keypad::KeyInput,
     start_held: bool,
+    select_held: bool,
 }
 impl InputSystem {
     /// Initializes the system.
@@ -18,11 +28,16 @@ impl InputSystem {
         return InputSystem {
             last_keys: keypad::KeyInput::new(),
             start_held: false,
+            select_held: false,
         };
     }
 
     /// Updates the input-related components of entities.
-    pub fn tick(&mut self, ecs: &mut Entities, live_entities: &[usize]) -> Result<bool, ECSError> {
+    pub fn tick(
+        &mut self,
+        ecs: &mut Entities,
+        live_entities: &[usize],
+    ) -> Result<GameButtons, ECSError> {
         // Read the current state of the keypad
         let keys = keypad::read_key_input();
         // If the new state is different than the old one, do the updating
@@ -44,6 +59,11 @@ impl InputSystem {
                             crate::shared_types::Coordinate::from_num(33),
                         ));
                     }
+                    // B removes the building under the cursor
+                    if keys.b() {
+                        debug_log!(Subsystems::InputSystem, "B pressed, deconstructing");
+                        e_builder_component.deconstruct = true;
+                    }
                 }
                 if ecs.entity_contains::<InputComponent>(*id) {
                     let mut movables = ecs.borrow_mut::<InputComponent>().unwrap();
@@ -77,24 +97,30 @@ impl InputSystem {
                         e_input_component.down_pressed = false;
                         debug_log!(Subsystems::InputSystem, "D-Pad down released");
                     }
-                    /* The state of the start key doesn't concern any entity directly,
-                    but it does concern the game loop. Therefore, we return the value here. */
-                    let start_pressed: bool;
+                    /* The state of the start and select keys doesn't concern any entity directly,
+                    but it does concern the game loop. Therefore, we return the values here. */
+                    let mut buttons = GameButtons::default();
                     if keys.start() && !self.start_held {
                         debug_log!(Subsystems::InputSystem, "Start pressed");
-                        start_pressed = true;
+                        buttons.start_pressed = true;
                         self.start_held = true;
                     } else {
-                        start_pressed = false;
                         self.start_held = false;
                     }
+                    if keys.select() && !self.select_held {
+                        debug_log!(Subsystems::InputSystem, "Select pressed");
+                        buttons.select_pressed = true;
+                        self.select_held = true;
+                    } else {
+                        self.select_held = false;
+                    }
                     self.last_keys = keys;
-                    return Ok(start_pressed);
+                    return Ok(buttons);
                 }
             }
         }
         // Store the keypad state for next call
         self.last_keys = keys;
-        return Ok(false);
+        return Ok(GameButtons::default());
     }
 }
@@ -1,11 +1,14 @@
-use crate::components::{InputComponent, MovementComponent, PositionComponent, SpriteComponent};
+use crate::components::{
+    ColliderComponent, CompositeSpriteComponent, InputComponent, MovementComponent,
+    PositionComponent, SpriteComponent,
+};
 use crate::debug_log::*;
-use crate::map::Map;
+use crate::map::{Camera, Map};
 use crate::shared_types::{Coordinate, Velocity, ZERO_VELOCITY};
+use crate::spatial::SpatialHash;
+use crate::sprite::HWSpriteHandle;
 
-use fixed::traits::FromFixed;
-
-use core::convert::TryInto;
+use alloc::vec::Vec;
 
 /// Maximum player speed, in pixels per frame
 /// A value of 1 means the player can move at most 60 pixels or 7.5 tiles a second.
@@ -26,16 +29,26 @@ impl MovementSystem {
     /// For each entity that is live,
     /// check whether it has a sprite and move it if it does.
     ///
-    /// If the camera should stay focused on the entity, move the map instead of the entity.
+    /// If the camera should stay focused on the entity, the camera follows it,
+    /// and the sprites of all entities are moved to wherever they are on screen afterwards.
+    ///
+    /// Entities with a collider stop at solid terrain and buildings, one axis after the other,
+    /// so that they slide along walls instead of getting stuck on them.
+    /// Their colliders are kept up to date in the spatial index as well.
     pub fn tick(
         ecs: &mut Entities,
         live_entities: &[usize],
         map: &mut Map,
+        camera: &mut Camera,
+        spatial: &mut SpatialHash,
     ) -> Result<(), ECSError> {
         let mut movables = ecs.borrow_mut::<MovementComponent>().unwrap();
         let inputables = ecs.borrow_mut::<InputComponent>().unwrap();
         let mut positionables = ecs.borrow_mut::<PositionComponent>().unwrap();
         let mut sprites = ecs.borrow_mut::<SpriteComponent>().unwrap();
+        let colliders = ecs.borrow::<ColliderComponent>().unwrap();
+        // Unlike the other components, there may not be any composite sprites at all
+        let composites = ecs.borrow::<CompositeSpriteComponent>().ok();
         for id in live_entities {
             let id = *id;
             if ecs.entity_contains::<MovementComponent>(id) {
@@ -49,44 +62,72 @@ impl MovementSystem {
                 // Process updates to entity positions
                 if ecs.entity_contains::<PositionComponent>(id) {
                     let e_position: &mut PositionComponent = positionables.get_mut(id).unwrap();
-
-                    // Entities which the camera is centered on are only allowed to move if it
-                    // wouldn't cause the rendered background area to go OOB
-                    let mut entity_moved: bool = true;
-                    if e_movement.keep_camera_centered_on
-                        && (e_movement.pending_movement_delta_x != ZERO_VELOCITY
-                            || e_movement.pending_movement_delta_y != ZERO_VELOCITY)
-                    {
-                        let (map_delta_x, map_delta_y) = e_movement.get_pending_movement_delta();
-                        if map.can_scroll(map_delta_x, map_delta_y) {
-                            map.try_scroll(map_delta_x, map_delta_y);
+                    let (width, height) = if ecs.entity_contains::<SpriteComponent>(id) {
+                        let e_sprite: &mut SpriteComponent = sprites.get_mut(id).unwrap();
+                        let (width, height) = e_sprite.get_handle().sprite_size.to_size_in_px();
+                        (width as u32, height as u32)
+                    } else if ecs.entity_contains::<CompositeSpriteComponent>(id) {
+                        let e_composite = composites.as_ref().unwrap().get(id).unwrap();
+                        let (width, height) = e_composite.size_in_px();
+                        (width as u32, height as u32)
+                    } else {
+                        (0, 0)
+                    };
+                    let e_collider: Option<&ColliderComponent> =
+                        if ecs.entity_contains::<ColliderComponent>(id) {
+                            colliders.get(id)
                         } else {
-                            entity_moved = false;
-                        }
-                    }
-                    if entity_moved {
-                        update_position_based_on_movement(e_movement, e_position);
+                            None
+                        };
+                    update_position_based_on_movement(
+                        map, e_movement, e_position, e_collider, width, height,
+                    );
+
+                    if let Some(collider) = e_collider {
+                        let (x, y) = e_position.floor();
+                        spatial.insert(
+                            id,
+                            x + collider.offset_x,
+                            y + collider.offset_y,
+                            collider.width,
+                            collider.height,
+                        );
                     }
 
                     // Get rid of processed movement delta
                     e_movement.reset_pending_movement_delta();
-                }
-            }
-            // Process updates to entity sprites caused by position change
-            if ecs.entity_contains::<SpriteComponent>(id)
-                && ecs.entity_contains::<PositionComponent>(id)
-            {
-                // Do not move sprites that the camera is centered on
-                if ecs.entity_contains::<MovementComponent>(id) {
-                    let e_movement: &mut MovementComponent = movables.get_mut(id).unwrap();
+
+                    // The camera keeps the entity's center in view
                     if e_movement.keep_camera_centered_on {
-                        // Skip this entity
-                        continue;
+                        let (x, y) = e_position.floor();
+                        camera.follow(x + width / 2, y + height / 2);
                     }
                 }
+            }
+        }
+
+        // Only now that the camera has moved, the onscreen positions of the sprites are known
+        camera.update(map);
+        for id in live_entities {
+            let id = *id;
+            // Process updates to entity sprites caused by position change
+            if !ecs.entity_contains::<PositionComponent>(id) {
+                continue;
+            }
+            let e_position: &mut PositionComponent = positionables.get_mut(id).unwrap();
+            if ecs.entity_contains::<SpriteComponent>(id) {
                 let e_sprite: &mut SpriteComponent = sprites.get_mut(id).unwrap();
-                let e_position: &mut PositionComponent = positionables.get_mut(id).unwrap();
-                update_sprite_based_on_position(map, e_position, e_sprite);
+                let sh = e_sprite.get_handle();
+                let size = sh.sprite_size.to_size_in_px();
+                update_sprite_based_on_position(map, e_position, size, &[(&*sh, 0, 0)]);
+            } else if ecs.entity_contains::<CompositeSpriteComponent>(id) {
+                let e_composite = composites.as_ref().unwrap().get(id).unwrap();
+                let parts: Vec<(&HWSpriteHandle, u16, u16)> = e_composite
+                    .parts()
+                    .iter()
+                    .map(|part| (&part.handle, part.offset_x, part.offset_y))
+                    .collect();
+                update_sprite_based_on_position(map, e_position, e_composite.size_in_px(), &parts);
             }
         }
         return Ok(());
@@ -157,21 +198,84 @@ fn update_movement_based_on_input(ic: &InputComponent, mc: &mut MovementComponen
     }
 }
 
-/// This function updates the position of entities based on their pending_movement fields.
-fn update_position_based_on_movement(mc: &MovementComponent, pc: &mut PositionComponent) {
-    if mc.pending_movement_delta_x > ZERO_VELOCITY || mc.pending_movement_delta_y > ZERO_VELOCITY {
-        // Add the pending movement to entity's position
-        (pc.0).0 += Coordinate::from_fixed(mc.pending_movement_delta_x);
-        (pc.0).1 += Coordinate::from_fixed(mc.pending_movement_delta_y);
+/// This function updates the position of entities based on the whole part of their pending movement.
+/// Entities of the given size (in pixels) are kept within the map.
+/// Entities with a collider stop right before anything solid, losing their velocity on that axis.
+fn update_position_based_on_movement(
+    map: &Map,
+    mc: &mut MovementComponent,
+    pc: &mut PositionComponent,
+    collider: Option<&ColliderComponent>,
+    width: u32,
+    height: u32,
+) {
+    let (delta_x, delta_y) = mc.get_pending_movement_delta();
+    let (x, y) = pc.floor();
+    let (map_width, map_height) = map.get_size_in_px();
+    // Only the whole part moves the entity, the fractional part stays pending
+    let wanted_x = ((x as i32 + delta_x).max(0) as u32).min(map_width.saturating_sub(width));
+    let wanted_y = ((y as i32 + delta_y).max(0) as u32).min(map_height.saturating_sub(height));
+
+    let (new_x, new_y) = match collider {
+        // Entities which are already stuck in something solid (e.g. because they spawned there)
+        // may move freely until they're out again
+        Some(collider) if !is_colliding(map, collider, x, y) => {
+            let new_x = move_until_blocked(x, wanted_x, |x| is_colliding(map, collider, x, y));
+            let new_y = move_until_blocked(y, wanted_y, |y| is_colliding(map, collider, new_x, y));
+            (new_x, new_y)
+        }
+        _ => (wanted_x, wanted_y),
+    };
+    if new_x != wanted_x {
+        debug_log!(Subsystems::MovementSystem, "Blocked on the X axis");
+        mc.x_velocity = ZERO_VELOCITY;
+        mc.pending_movement_delta_x = ZERO_VELOCITY;
+    }
+    if new_y != wanted_y {
+        debug_log!(Subsystems::MovementSystem, "Blocked on the Y axis");
+        mc.y_velocity = ZERO_VELOCITY;
+        mc.pending_movement_delta_y = ZERO_VELOCITY;
+    }
+    (pc.0).0 = Coordinate::from_num(new_x);
+    (pc.0).1 = Coordinate::from_num(new_y);
+}
+
+/// Returns whether the collider overlaps anything solid if its entity is at the given position (in pixels).
+fn is_colliding(map: &Map, collider: &ColliderComponent, x: u32, y: u32) -> bool {
+    return map.is_area_solid(
+        x + collider.offset_x,
+        y + collider.offset_y,
+        collider.width,
+        collider.height,
+    );
+}
+
+/// Moves from `from` towards `to` on a single axis one pixel at a time,
+/// and returns the last position before `is_blocked` returns true for the next one.
+/// Entities move at most a few pixels per frame, so this doesn't take long.
+fn move_until_blocked(from: u32, to: u32, is_blocked: impl Fn(u32) -> bool) -> u32 {
+    let mut pos = from;
+    while pos != to {
+        let next = if to > pos { pos + 1 } else { pos - 1 };
+        if is_blocked(next) {
+            break;
+        }
+        pos = next;
     }
+    return pos;
 }
 
-// Updates the sprite's relative onscreen position based on changes in it's absolute map coordinates
-fn update_sprite_based_on_position(map: &Map, pc: &PositionComponent, sp: &mut SpriteComponent) {
+// Updates the sprites' relative onscreen positions based on changes in the entity's absolute map coordinates.
+// The sprites are given with their offsets from the entity's position, and are all culled
+// once the area of the given size isn't visible anymore.
+fn update_sprite_based_on_position(
+    map: &Map,
+    pc: &PositionComponent,
+    (x_size, y_size): (u16, u16),
+    sprites: &[(&HWSpriteHandle, u16, u16)],
+) {
     // Check whether sprite would be visible on screen (if not, disable drawing)
     let (sprite_top_left_x, sprite_top_left_y) = pc.floor();
-    let sh = sp.get_handle();
-    let (x_size, y_size) = sh.sprite_size.to_size_in_px();
     let sprite_bottom_right_x = sprite_top_left_x + (x_size as u32);
     let sprite_bottom_right_y = sprite_top_left_y + (y_size as u32);
     if !map.is_area_visible(
@@ -180,48 +284,31 @@ fn update_sprite_based_on_position(map: &Map, pc: &PositionComponent, sp: &mut S
         sprite_bottom_right_x,
         sprite_bottom_right_y,
     ) {
-        // TODO: Temporarily eject sprite from OAM to make room for visible ones
+        // Culled sprites don't take up a slot in OAM, making room for visible ones
         debug_log!(
             Subsystems::MovementSystem,
-            "Sprite now offscreen, making invisible"
+            "Sprite now offscreen, culling it"
         );
-        sh.set_visibility(false);
+        for (sh, _, _) in sprites {
+            sh.set_offscreen(true);
+        }
     } else {
-        if !sh.get_visibility() {
+        let (map_top_left_x, map_top_left_y) = map.get_top_left_corner_coords();
+        for (sh, offset_x, offset_y) in sprites {
+            // Convert the map coordinates to coordinates relative to the top-left corner of the screen
+            // (which are the ones the hardware cares about)
+            let onscreen_x =
+                (sprite_top_left_x as i32) + (*offset_x as i32) - (map_top_left_x as i32);
+            let onscreen_y =
+                (sprite_top_left_y as i32) + (*offset_y as i32) - (map_top_left_y as i32);
+            // Actually move the sprite, which also hides parts of it that are entirely off-screen
             debug_log!(
                 Subsystems::MovementSystem,
-                "Sprite now onscreen, making visible again"
+                "Moving sprite to onscreen coords {} {}",
+                onscreen_x,
+                onscreen_y
             );
-            sh.set_visibility(true);
+            sh.set_screen_pos(onscreen_x, onscreen_y);
         }
-        let (map_top_left_x, map_top_left_y) = map.get_top_left_corner_coords();
-        // Convert the map coordinates to coordinates relative to the top-left corner of the screen
-        // (which are the ones the hardware cares about)
-        let onscreen_x: i16 = ((sprite_top_left_x as i32) - (map_top_left_x as i32))
-            .try_into()
-            .unwrap();
-        let onscreen_y: i16 = ((sprite_top_left_y as i32) - (map_top_left_y as i32))
-            .try_into()
-            .unwrap();
-        // Because of the way the GBA stores sprite coordinates, we have to do some funkiness for negative values to work.
-        let onscreen_x: u16 = if onscreen_x > 0 {
-            onscreen_x.try_into().unwrap()
-        } else {
-            (onscreen_x & 0x01FF).try_into().unwrap()
-        };
-        let onscreen_y: u16 = if onscreen_y > 0 {
-            onscreen_y.try_into().unwrap()
-        } else {
-            (onscreen_y & 0x00FF).try_into().unwrap()
-        };
-        // Actually move the sprite
-        debug_log!(
-            Subsystems::MovementSystem,
-            "Moving sprite to onscreen coords {} {}",
-            onscreen_x,
-            onscreen_y
-        );
-        sh.set_x_pos(onscreen_x);
-        sh.set_y_pos(onscreen_y);
     }
 }
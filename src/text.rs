@@ -7,24 +7,20 @@
 //! # NOTE
 //! Keep the reserved resources documented by the README in mind.
 
+use crate::assets::{files, palettes, Asset};
+use crate::bg_tiles::{BGTileAllocation, TEXT_TILES};
 use crate::shared_constants::*;
 use crate::shared_types::Background;
-use crate::FS;
 use crate::{debug_log, Subsystems::Text};
 
 use gba::io::background::{BGSize, BackgroundControlSetting};
-use gba::io::dma::{DMAControlSetting, DMA3};
 use gba::palram;
 use gba::vram::text::TextScreenblockEntry;
-use gba::{
-    vram::{SCREEN_BASE_BLOCKS, VRAM_BASE_USIZE},
-    Color,
-};
+use gba::{vram::SCREEN_BASE_BLOCKS, Color};
 
 use hashbrown::hash_map::HashMap;
 use twox_hash::XxHash64;
 
-use core::convert::TryInto;
 use core::fmt;
 use core::hash::BuildHasherDefault;
 use core::str;
@@ -37,6 +33,9 @@ pub const CHARA_SIZE_IN_PX: u8 = 8;
 /// # SAFETY
 /// Only a single instance may exist. Otherwise, you'll get funky text rendering.
 pub struct TextEngine {
+    /// The font's tiles in the text charblock, which are shared by all text engines using the same font.
+    /// Only None while dropping.
+    font_tiles: Option<BGTileAllocation>,
     char_to_tile_id: HashMap<char, u16, BuildHasherDefault<XxHash64>>,
     /// X position of cursor, in tiles
     cursor_x: u8,
@@ -48,50 +47,32 @@ pub struct TextEngine {
 
 impl TextEngine {
     fn init(
-        font_tile_filename: &str,
-        font_chars_filename: &str,
+        font_tiles: Asset,
+        font_chars: Asset,
+        font_palette: Asset,
         screenblock: u16,
         background: Background,
         make_visible: bool,
     ) -> TextEngine {
-        let font_tiles = FS
-            .get_file_data_by_name_as_u32_slice(font_tile_filename)
-            .unwrap();
+        // Load characters into VRAM charblock, unless another text engine already did
+        // There are 512 4bpp tiles per charblock, each one is 32 bytes in length
+        let font_tiles = TEXT_TILES
+            .lock()
+            .alloc_from_fs(&font_tiles)
+            .unwrap_or_else(|err| panic!("Couldn't load font: {}", err));
 
         // Create character -> tile number lookup table
         // TODO: Make this more efficient, both in terms of memory for the mapping and CPU time (maybe use some const map)
         let mut hashmap: HashMap<char, u16, BuildHasherDefault<XxHash64>> = Default::default();
-        let font_chars = FS.get_file_data_by_name(font_chars_filename).unwrap();
-        let font_chars: &str = str::from_utf8(font_chars).unwrap();
+        let font_chars = font_chars.load::<u8>();
+        let font_chars: &str = str::from_utf8(&font_chars).unwrap();
         for (i, chara) in font_chars.chars().enumerate() {
-            debug_log!(Text, "Inserting char {} with tile ID {}", chara, i);
-            hashmap.insert(chara, i as u16);
+            let tile_id = font_tiles.first_tile + i as u16;
+            debug_log!(Text, "Inserting char {} with tile ID {}", chara, tile_id);
+            hashmap.insert(chara, tile_id);
         }
 
-        // Load characters into VRAM charblock
-        // There are 512 4bpp tiles per charblock, each one is 32 bytes (=8 u32's) in length
-        if font_tiles.len() > 512 * 8 {
-            panic!(
-                "Font is too large! May contain at most 512 glyphs, actually contains {}",
-                font_tiles.len() / 8
-            );
-        }
-
-        // DMA transfer font tiles
-        unsafe {
-            DMA3::set_source(font_tiles.as_ptr());
-            DMA3::set_dest((VRAM_BASE_USIZE + (TEXT_CHARBLOCK * CHARBLOCK_SIZE_BYTES)) as *mut u32);
-            DMA3::set_count(font_tiles.len().try_into().unwrap());
-            DMA3::set_control(
-                DMAControlSetting::new()
-                    .with_use_32bit(true)
-                    .with_enabled(true),
-            );
-        }
-
-        let pal_file = crate::FS
-            .get_file_data_by_name_as_u16_slice("font_sharedPal")
-            .unwrap();
+        let pal_file = font_palette.load::<u16>();
 
         if pal_file.len() > (TEXT_BG_PALETTE_END - TEXT_BG_PALETTE_START) {
             panic!("Font palette too big");
@@ -106,6 +87,7 @@ impl TextEngine {
         }
 
         let mut engine = TextEngine {
+            font_tiles: Some(font_tiles),
             char_to_tile_id: hashmap,
             cursor_x: 0,
             cursor_y: 0,
@@ -131,7 +113,7 @@ impl TextEngine {
     }
 
     /// Initializes a text engine with the default font from GBFS on the given screenblock and background.
-    /// The filename must be "font", and a UTF8 file "font_chars.txt" must also exist,
+    /// The font's tiles are stored in "fontTiles", and a UTF8 file "font_chars.txt" must also exist,
     /// containing all characters in order of appearance in the tile file.
     /// The file is assumed to contain the font in a 4bpp format, where each tile is exactly
     /// 1 character.
@@ -142,8 +124,9 @@ impl TextEngine {
         make_visible: bool,
     ) -> TextEngine {
         return TextEngine::init(
-            "fontTiles",
-            "font_chars.txt",
+            files::FONT_TILES,
+            files::FONT_CHARS_TXT,
+            palettes::FONT_SHARED,
             screenblock as u16,
             background,
             make_visible,
@@ -198,13 +181,20 @@ impl TextEngine {
             }
         };
         debug_log!(Text, "Character {} has tile ID {}", chara, *tile_id);
-        let glyph = TextScreenblockEntry::from_tile_id(*tile_id);
+        self.put_tile(*tile_id, x, y);
+    }
 
-        // Because grit has a bug which breaks the "generate palette at an offset" flag,
-        // we have to manually adjust each entry to use the text palbank.
-        // FIXME: Transition to nin10kit and remove this workaround
+    /// Puts the given tile of the text charblock at the given screen position,
+    /// which allows drawing graphics other than text.
+    /// The tile has to use the text palbank, just like the font.
+    pub fn put_tile(&mut self, tile_id: u16, x: u8, y: u8) {
+        assert!(x < SCREEN_WIDTH_TILES as u8);
+        assert!(y < SCREEN_HEIGHT_TILES as u8);
+        let entry = TextScreenblockEntry::from_tile_id(tile_id);
+
+        // The font's tiles use the first colors of a palbank, so each entry has to select the text palbank.
         let text_palbank = (TEXT_BG_PALETTE_START / 16) as u16;
-        let glyph = glyph.with_palbank(text_palbank);
+        let entry = entry.with_palbank(text_palbank);
 
         // TODO: This cast should be abstracted away by the lib; submit a PR
         unsafe {
@@ -212,7 +202,7 @@ impl TextEngine {
             let sb_entries = SCREEN_BASE_BLOCKS
                 .index(self.screenblock as usize)
                 .cast::<TextScreenblockEntry>();
-            sb_entries.offset(offset_in_sb).write(glyph);
+            sb_entries.offset(offset_in_sb).write(entry);
         }
     }
 
@@ -233,6 +223,14 @@ impl TextEngine {
     }
 }
 
+impl Drop for TextEngine {
+    fn drop(&mut self) {
+        if let Some(font_tiles) = self.font_tiles.take() {
+            TEXT_TILES.lock().free(font_tiles);
+        }
+    }
+}
+
 impl fmt::Write for TextEngine {
     fn write_str(&mut self, s: &str) -> fmt::Result {
         for chara in s.chars() {
@@ -1,11 +1,16 @@
 //! This module enables creation of windows for lists, menus etc.
 
+use crate::assets::{sprites, SpriteAsset};
+use crate::bg_tiles::TEXT_TILES;
 use crate::components::InventoryComponent;
-use crate::item::{Item, ITEM_SPRITE_SIZE};
-use crate::shared_constants::{SCREEN_HEIGHT, SCREEN_HEIGHT_TILES, SCREEN_WIDTH};
+use crate::item::Item;
+use crate::map::Minimap;
+use crate::shared_constants::{
+    SCREEN_HEIGHT, SCREEN_HEIGHT_TILES, SCREEN_WIDTH, SCREEN_WIDTH_TILES, TEXT_BG_PALETTE_START,
+};
 use crate::shared_constants::{WINDOW_0_SCREENBLOCK, WINDOW_1_SCREENBLOCK};
 use crate::shared_types::Background;
-use crate::sprite::{HWSpriteAllocator, HWSpriteHandle, HWSpriteSize};
+use crate::sprite::{HWSpriteAllocator, HWSpriteHandle};
 use crate::text::{TextEngine, CHARA_SIZE_IN_PX};
 use crate::{debug_log, debug_log::Subsystems};
 
@@ -14,11 +19,15 @@ use alloc::vec::Vec;
 use core::convert::AsRef;
 use core::fmt::Write;
 
+use gba::io::display::{VBLANK_SCANLINE, VCOUNT};
 use gba::io::{display, keypad, window};
+use gba::{palram, Color};
 use spinning_top::{const_spinlock, Spinlock};
 
 static WINDOW_0_TAKEN: Spinlock<bool> = const_spinlock(false);
 static WINDOW_1_TAKEN: Spinlock<bool> = const_spinlock(false);
+/// Row (in tiles) at which the minimap starts, leaving room for the title above it
+const MINIMAP_FIRST_ROW: usize = 2;
 
 /// Which window this is
 enum WindowNum {
@@ -161,27 +170,21 @@ impl Window {
     ) {
         let items: Vec<(&Item, &usize)> = inv.peek().iter().collect();
         // TODO: Sort the item list alphabetically based on item name (blocked on lexical-sort no_std support)
-        let entries: Vec<(String, &str, HWSpriteSize)> = items
+        let entries: Vec<(String, SpriteAsset)> = items
             .iter()
-            .map(|item| {
-                (
-                    format!("{}: {}", item.0, item.1),
-                    item.0.to_sprite_name(),
-                    ITEM_SPRITE_SIZE,
-                )
-            })
+            .map(|item| (format!("{}: {}", item.0, item.1), item.0.to_sprite()))
             .collect();
         self.make_sprite_list("Inventory", entries.as_slice(), sprite_alloc);
     }
     /// Create a list of text and sprites to the right of the text.
     /// This will block until the player presses "A" or "Start".
     /// Note that all other sprites will be invisible while the list is open.
-    /// The entries are given in a (description, sprite filename, sprite size) form.
+    /// The entries are given in a (description, sprite) form.
     /// Internally, the sprites are allocated and disposed of once the window is closed.
-    pub fn make_sprite_list<S: AsRef<str>, T: AsRef<str>, U: AsRef<str>>(
+    pub fn make_sprite_list<S: AsRef<str>, T: AsRef<str>>(
         &mut self,
         title: S,
-        entries: &[(T, U, HWSpriteSize)],
+        entries: &[(T, SpriteAsset)],
         sprite_alloc: &mut HWSpriteAllocator,
     ) {
         self.text.clear();
@@ -193,13 +196,11 @@ impl Window {
         sprite_alloc.hide_sprites_push();
         let mut sprite_handles: Vec<HWSpriteHandle> = Vec::new();
         // Now we plot all sprites and their descriptions
-        for (desc, sprite, size) in entries {
+        for (desc, sprite) in entries {
             // Sprite to the left, text to the right
             let (cursor_x, cursor_y) = self.text.get_cursor_pos();
 
-            let sprite_handle = sprite_alloc
-                .alloc_from_fs_file(sprite.as_ref(), *size)
-                .unwrap();
+            let sprite_handle = sprite_alloc.alloc_from_fs_file(sprite).unwrap();
             // Put the sprite as the first element on a new line
             sprite_handle.set_x_pos(0);
             sprite_handle.set_y_pos((cursor_y) as u16);
@@ -207,7 +208,7 @@ impl Window {
             sprite_handles.push(sprite_handle);
 
             // Followed by the description text
-            let (sprite_x_size, sprite_y_size) = size.to_size_in_px();
+            let (sprite_x_size, sprite_y_size) = sprite.size.to_size_in_px();
             self.text.set_cursor_pos(
                 (cursor_x + sprite_x_size as u8) / CHARA_SIZE_IN_PX,
                 cursor_y / CHARA_SIZE_IN_PX,
@@ -220,6 +221,7 @@ impl Window {
                 (sprite_y_size as u8 / CHARA_SIZE_IN_PX) + (cursor_y / CHARA_SIZE_IN_PX),
             );
         }
+        sprite_alloc.update_oam();
 
         // Wait for player to press "A" or "Start"
         debug_log!(Subsystems::Menu, "Waiting for player to dismiss list");
@@ -233,12 +235,100 @@ impl Window {
         // Cleanup
         debug_log!(Subsystems::Menu, "List dismissed, cleaning up");
         self.disable_sprites();
-        for handle in sprite_handles {
-            sprite_alloc.free(handle);
-        }
+        drop(sprite_handles);
         sprite_alloc.show_sprites_pop().unwrap(); // Restore all the other sprite's visibility
     }
 
+    /// Show the minimap and let the player pick a position on it with a cursor,
+    /// which starts out at the given position on the map (in pixels).
+    /// This will block until the player presses "A", returning the picked position on the map (in pixels),
+    /// or "B" or "Select", returning None.
+    /// Note that all other sprites will be invisible while the minimap is open.
+    pub fn make_minimap(
+        &mut self,
+        minimap: &Minimap,
+        focus: (u32, u32),
+        sprite_alloc: &mut HWSpriteAllocator,
+    ) -> Option<(u32, u32)> {
+        let tiles = match TEXT_TILES.lock().alloc(&minimap.tiles) {
+            Ok(tiles) => tiles,
+            Err(err) => {
+                debug_log!(Subsystems::Menu, "Not showing minimap: {}", err);
+                return None;
+            }
+        };
+        // The minimap's colors come after the font's in the text palbank
+        let text_palbank = (TEXT_BG_PALETTE_START / 16) as u8;
+        for (i, color) in minimap.palette.iter().enumerate() {
+            palram::index_palram_bg_4bpp(text_palbank, (Minimap::FIRST_COLOR + i) as u8)
+                .write(Color(*color));
+        }
+
+        self.text.clear();
+        writeln!(&mut self.text, "Minimap").unwrap();
+        let first_column = (SCREEN_WIDTH_TILES - minimap.width_in_tiles) / 2;
+        for y in 0..minimap.height_in_tiles {
+            for x in 0..minimap.width_in_tiles {
+                let tile_id = tiles.first_tile + (x + y * minimap.width_in_tiles) as u16;
+                self.text.put_tile(
+                    tile_id,
+                    (first_column + x) as u8,
+                    (MINIMAP_FIRST_ROW + y) as u8,
+                );
+            }
+        }
+
+        self.enable_sprites();
+        // Hide all the other sprites
+        sprite_alloc.hide_sprites_push();
+        let cursor = sprite_alloc.alloc_from_fs_file(&sprites::CURSOR).unwrap();
+        cursor.set_visibility(true);
+        let origin_x = (first_column * CHARA_SIZE_IN_PX as usize) as u32;
+        let origin_y = (MINIMAP_FIRST_ROW * CHARA_SIZE_IN_PX as usize) as u32;
+        let (mut cursor_x, mut cursor_y) = minimap.to_minimap_pos(focus.0, focus.1);
+
+        debug_log!(Subsystems::Menu, "Waiting for player to pick a position");
+        let mut last_keys = keypad::read_key_input();
+        let target = loop {
+            // Move the cursor by one pixel per frame
+            while VCOUNT.read() < VBLANK_SCANLINE {}
+            cursor.set_x_pos((origin_x + cursor_x) as u16);
+            cursor.set_y_pos((origin_y + cursor_y) as u16);
+            sprite_alloc.update_oam();
+
+            let keys = keypad::read_key_input();
+            let delta = keys.difference(last_keys);
+            last_keys = keys;
+            if keys.left() && cursor_x > 0 {
+                cursor_x -= 1;
+            } else if keys.right() && cursor_x + 1 < minimap.width_in_px as u32 {
+                cursor_x += 1;
+            }
+            if keys.up() && cursor_y > 0 {
+                cursor_y -= 1;
+            } else if keys.down() && cursor_y + 1 < minimap.height_in_px as u32 {
+                cursor_y += 1;
+            }
+            if delta.a() && keys.a() {
+                break Some(minimap.to_map_pos(cursor_x, cursor_y));
+            } else if (delta.b() && keys.b()) || (delta.select() && keys.select()) {
+                break None;
+            }
+            while VCOUNT.read() >= VBLANK_SCANLINE {}
+        };
+
+        // Cleanup
+        debug_log!(Subsystems::Menu, "Minimap dismissed, cleaning up");
+        self.disable_sprites();
+        drop(cursor);
+        // Restore all the other sprite's visibility
+        sprite_alloc.show_sprites_pop().unwrap();
+        // Nothing may refer to the minimap's tiles anymore once they're freed
+        self.text.clear();
+        TEXT_TILES.lock().free(tiles);
+        return target;
+    }
+
     /// Create a text-based menu, and return the index of the choice the player picked
     pub fn make_text_menu(&mut self, title: &str, menu_entries: &[&str]) -> usize {
         // Redraws the cursor